@@ -0,0 +1,161 @@
+//! Capture pause/resume state.
+//!
+//! Capture can be paused globally or for a single app or source, either until
+//! resumed or until a given time. The state is persisted in `capture_state` so a
+//! restart keeps honouring a pause, and timed pauses lapse on their own.
+use std::collections::BTreeMap;
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::storage::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Global,
+    App,
+    Source,
+}
+
+impl Scope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Global => "global",
+            Scope::App => "app",
+            Scope::Source => "source",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "global" => Some(Scope::Global),
+            "app" => Some(Scope::App),
+            "source" => Some(Scope::Source),
+            _ => None,
+        }
+    }
+}
+
+/// A pause on one scope; `until: None` means paused until explicitly resumed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pause {
+    pub until: Option<OffsetDateTime>,
+}
+
+impl Pause {
+    fn active(&self, now: OffsetDateTime) -> bool {
+        self.until.is_none_or(|u| now < u)
+    }
+}
+
+/// Why an event was refused while capture is paused.
+#[derive(Debug, Clone, Serialize)]
+pub struct PauseReason {
+    pub scope: Scope,
+    pub value: Option<String>,
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScopeStatus {
+    pub scope: Scope,
+    pub value: Option<String>,
+    pub state: &'static str,
+    pub until: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureStatus {
+    /// Global state: `resumed`, `paused` or `paused_until`.
+    pub state: &'static str,
+    pub until: Option<String>,
+    /// Active per-app and per-source pauses.
+    pub scopes: Vec<ScopeStatus>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CaptureState {
+    pauses: BTreeMap<(Scope, String), Pause>,
+}
+
+impl CaptureState {
+    /// Loads persisted pauses; a missing table yields the default (capturing).
+    pub fn load(conn: &Connection) -> Result<Self> {
+        let mut stmt = conn.prepare("SELECT scope, scope_value, until FROM capture_state")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)))?;
+        let mut state = CaptureState::default();
+        for r in rows {
+            let (scope, value, until) = r?;
+            let Some(scope) = Scope::parse(&scope) else { continue };
+            let until = match until {
+                Some(u) => Some(OffsetDateTime::parse(&u, &Rfc3339)?),
+                None => None,
+            };
+            state.pauses.insert((scope, value), Pause { until });
+        }
+        Ok(state)
+    }
+
+    /// Pauses one scope and persists it. `value` is ignored for `Scope::Global`.
+    pub fn pause(&mut self, conn: &Connection, scope: Scope, value: &str, until: Option<OffsetDateTime>) -> Result<()> {
+        let key = (scope, if scope == Scope::Global { String::new() } else { value.to_string() });
+        let until_str = match until { Some(u) => Some(u.format(&Rfc3339)?), None => None };
+        conn.execute(
+            r#"INSERT INTO capture_state (scope, scope_value, until) VALUES (?1, ?2, ?3)
+               ON CONFLICT(scope, scope_value) DO UPDATE SET until = excluded.until, updated_at = CURRENT_TIMESTAMP"#,
+            params![key.0.as_str(), key.1, until_str],
+        )?;
+        self.pauses.insert(key, Pause { until });
+        Ok(())
+    }
+
+    /// Resumes one scope, or every scope when `scope` is `Global`.
+    pub fn resume(&mut self, conn: &Connection, scope: Scope, value: &str) -> Result<()> {
+        if scope == Scope::Global {
+            conn.execute("DELETE FROM capture_state", [])?;
+            self.pauses.clear();
+        } else {
+            conn.execute("DELETE FROM capture_state WHERE scope = ?1 AND scope_value = ?2", params![scope.as_str(), value])?;
+            self.pauses.remove(&(scope, value.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the pause blocking an event from `app`/`source`, if any.
+    pub fn check(&self, app: &str, source: &str, now: OffsetDateTime) -> Option<PauseReason> {
+        let candidates = [(Scope::Global, ""), (Scope::App, app), (Scope::Source, source)];
+        candidates.into_iter().find_map(|(scope, value)| {
+            let p = self.pauses.get(&(scope, value.to_string()))?;
+            if !p.active(now) { return None; }
+            Some(PauseReason {
+                scope,
+                value: if scope == Scope::Global { None } else { Some(value.to_string()) },
+                until: p.until.and_then(|u| u.format(&Rfc3339).ok()),
+            })
+        })
+    }
+
+    pub fn status(&self, now: OffsetDateTime) -> CaptureStatus {
+        let view = |p: &Pause| -> (&'static str, Option<String>) {
+            match p.until {
+                Some(u) => ("paused_until", u.format(&Rfc3339).ok()),
+                None => ("paused", None),
+            }
+        };
+        let (state, until) = match self.pauses.get(&(Scope::Global, String::new())) {
+            Some(p) if p.active(now) => view(p),
+            _ => ("resumed", None),
+        };
+        let scopes = self.pauses.iter()
+            .filter(|((scope, _), p)| *scope != Scope::Global && p.active(now))
+            .map(|((scope, value), p)| {
+                let (state, until) = view(p);
+                ScopeStatus { scope: *scope, value: Some(value.clone()), state, until }
+            })
+            .collect();
+        CaptureStatus { state, until, scopes }
+    }
+}
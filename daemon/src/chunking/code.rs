@@ -0,0 +1,265 @@
+//! Source code chunking: one span per definition, long ones split into
+//! overlapping windows of `max_chars` characters.
+//!
+//! Python's chunker walks the `ast` module's tree; here definitions are found
+//! with a tokenizer (strings, comments, brackets, continuations) and indentation,
+//! which gives the same line ranges for valid code. Like Python, unparseable
+//! source (unbalanced brackets, unterminated strings, bad indentation) is one
+//! span; subtler syntax errors are not detected.
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{Span, SPACE, WORD};
+
+pub const DEFAULT_MAX_CHARS: usize = 4000;
+pub const DEFAULT_OVERLAP_CHARS: usize = 800;
+
+static JS_TS_DEFS: LazyLock<[Regex; 3]> = LazyLock::new(|| [
+    Regex::new(&format!(r"class{s}+{w}+{s}*\{{", s = SPACE, w = WORD)).unwrap(),
+    Regex::new(&format!(r"function{s}+{w}+{s}*\(", s = SPACE, w = WORD)).unwrap(),
+    Regex::new(&format!(r"{w}+{s}*={s}*\([^)]*\){s}*=>", s = SPACE, w = WORD)).unwrap(),
+]);
+static GO_FUNC: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!("func{}+", SPACE)).unwrap());
+static RUST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new("fn|struct|impl").unwrap());
+static WORD_CHAR: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!("^{}$", WORD)).unwrap());
+
+/// Python's `\w`, for the `\b` checks the `regex` crate would do with its own `\w`.
+fn is_word(c: char) -> bool {
+    WORD_CHAR.is_match(c.encode_utf8(&mut [0; 4]))
+}
+
+/// Whether `[start, end)` of `text` is a whole word (`\b...\b`).
+fn at_word_boundaries(text: &str, start: usize, end: usize) -> bool {
+    !text[..start].chars().next_back().is_some_and(is_word) && !text[end..].chars().next().is_some_and(is_word)
+}
+
+/// Pushes `[s, e)`, split into windows of `max_chars` overlapping by
+/// `overlap_chars` when it is longer. Lengths are counted in characters.
+fn push_span(text: &str, (s, e): Span, max_chars: usize, overlap_chars: usize, spans: &mut Vec<Span>) {
+    let bounds: Vec<usize> = text[s..e].char_indices().map(|(i, _)| s + i).chain(std::iter::once(e)).collect();
+    let len = bounds.len() - 1;
+    if len <= max_chars {
+        spans.push((s, e));
+        return;
+    }
+    let mut start = 0;
+    while start < len {
+        let end = len.min(start + max_chars);
+        spans.push((bounds[start], bounds[end]));
+        if end >= len { break; }
+        start += max_chars.saturating_sub(overlap_chars).max(1);
+    }
+}
+
+/// Spans from each start offset to the next; text before the first is dropped.
+fn spans_between(text: &str, mut starts: Vec<usize>) -> Vec<Span> {
+    if starts.is_empty() { return vec![(0, text.len())]; }
+    starts.sort_unstable();
+    starts.dedup();
+    starts.push(text.len());
+    let mut spans = Vec::new();
+    for w in starts.windows(2) {
+        push_span(text, (w[0], w[1]), DEFAULT_MAX_CHARS, DEFAULT_OVERLAP_CHARS, &mut spans);
+    }
+    spans
+}
+
+pub fn chunk_js_ts(text: &str) -> Vec<Span> {
+    let starts = JS_TS_DEFS.iter().flat_map(|re| re.find_iter(text).map(|m| m.start())).collect();
+    spans_between(text, starts)
+}
+
+pub fn chunk_go(text: &str) -> Vec<Span> {
+    let starts = GO_FUNC.find_iter(text)
+        .filter(|m| !text[..m.start()].chars().next_back().is_some_and(is_word))
+        .map(|m| m.start())
+        .collect();
+    spans_between(text, starts)
+}
+
+pub fn chunk_rust(text: &str) -> Vec<Span> {
+    let starts = RUST_ITEM.find_iter(text)
+        .filter(|m| at_word_boundaries(text, m.start(), m.end()))
+        .map(|m| m.start())
+        .collect();
+    spans_between(text, starts)
+}
+
+/// One span per function and class, nested ones included, ordered by start.
+pub fn chunk_python(text: &str) -> Vec<Span> {
+    let Some(defs) = python_defs(text) else { return vec![(0, text.len())] };
+    // Byte offset of each line start, then the end of the text.
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let offset = |line: usize| line_starts[line.saturating_sub(1)];
+    let mut spans = Vec::new();
+    for (first, last) in defs {
+        // Like Python, the span stops at the start of the definition's last line.
+        push_span(text, (offset(first), offset(last)), DEFAULT_MAX_CHARS, DEFAULT_OVERLAP_CHARS, &mut spans);
+    }
+    if spans.is_empty() { spans.push((0, text.len())); }
+    spans.sort_by_key(|s| s.0);
+    spans
+}
+
+/// A statement, possibly spanning several physical lines.
+struct LogicalLine {
+    first_line: usize,
+    /// Line of the statement's last token.
+    last_line: usize,
+    indent: usize,
+    /// Leading code, enough to recognise `def`/`class` headers.
+    head: String,
+    /// Ends with `:`, so an indented block follows.
+    opens_block: bool,
+}
+
+/// `(first line, last line)` of every `def`, `async def` and `class`, in source
+/// order, or `None` when the source doesn't tokenize.
+fn python_defs(text: &str) -> Option<Vec<(usize, usize)>> {
+    let lines = logical_lines(text)?;
+    let mut indents = vec![0];
+    for (i, l) in lines.iter().enumerate() {
+        let top = *indents.last().unwrap();
+        if l.indent > top {
+            // Only a block opener may be followed by an indent.
+            if i == 0 || !lines[i - 1].opens_block { return None; }
+            indents.push(l.indent);
+        } else {
+            if i > 0 && lines[i - 1].opens_block { return None; }
+            while l.indent < *indents.last().unwrap() { indents.pop(); }
+            if l.indent != *indents.last().unwrap() { return None; }
+        }
+    }
+    if lines.last().is_some_and(|l| l.opens_block) { return None; }
+
+    let mut defs = Vec::new();
+    for (i, l) in lines.iter().enumerate() {
+        if !is_def_header(&l.head) { continue; }
+        let last = if l.opens_block {
+            lines[i + 1..].iter().take_while(|b| b.indent > l.indent).last().map_or(l.last_line, |b| b.last_line)
+        } else {
+            l.last_line
+        };
+        defs.push((l.first_line, last));
+    }
+    Some(defs)
+}
+
+/// `s` after a leading keyword `kw` that is followed by whitespace.
+fn after_keyword<'a>(s: &'a str, kw: &str) -> Option<&'a str> {
+    s.strip_prefix(kw).filter(|r| r.starts_with([' ', '\t', '\x0c']))
+}
+
+fn is_def_header(head: &str) -> bool {
+    let head = after_keyword(head, "async").map(|r| r.trim_start()).unwrap_or(head);
+    after_keyword(head, "def").or_else(|| after_keyword(head, "class")).is_some()
+}
+
+fn logical_lines(text: &str) -> Option<Vec<LogicalLine>> {
+    const HEAD_CHARS: usize = 16;
+    let chars: Vec<char> = text.chars().collect();
+    let mut lines = Vec::new();
+    let mut current: Option<LogicalLine> = None;
+    let mut line = 1;
+    let mut depth = 0usize;
+    let mut continued = false;
+    let mut at_line_start = true;
+    let mut i = 0;
+    while i < chars.len() {
+        if at_line_start && depth == 0 && !continued {
+            at_line_start = false;
+            // Measure indentation; tabs advance to the next multiple of 8.
+            let mut col = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    ' ' => col += 1,
+                    '\t' => col = (col / 8 + 1) * 8,
+                    '\x0c' => col = 0,
+                    _ => break,
+                }
+                i += 1;
+            }
+            let blank = i >= chars.len() || matches!(chars[i], '#' | '\n' | '\r');
+            if !blank && current.is_none() {
+                current = Some(LogicalLine { first_line: line, last_line: line, indent: col, head: String::new(), opens_block: false });
+            }
+            continue;
+        }
+        at_line_start = false;
+        let c = chars[i];
+        match c {
+            '\n' => {
+                line += 1;
+                at_line_start = true;
+                if depth == 0 && !continued {
+                    if let Some(l) = current.take() { lines.push(l); }
+                }
+                continued = false;
+                i += 1;
+                continue;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' { i += 1; }
+                continue;
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') || (chars.get(i + 1) == Some(&'\r') && chars.get(i + 2) == Some(&'\n')) => {
+                continued = true;
+                i += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let l = current.as_mut()?;
+        let token_start = i;
+        match c {
+            '"' | '\'' => {
+                let triple = chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c);
+                i += if triple { 3 } else { 1 };
+                loop {
+                    let ch = *chars.get(i)?;
+                    if ch == '\\' {
+                        if chars.get(i + 1) == Some(&'\n') { line += 1; }
+                        i += 2;
+                        continue;
+                    }
+                    if ch == '\n' {
+                        if !triple { return None; }
+                        line += 1;
+                    }
+                    i += 1;
+                    if ch == c && (!triple || (chars.get(i) == Some(&c) && chars.get(i + 1) == Some(&c))) {
+                        if triple { i += 2; }
+                        break;
+                    }
+                }
+            }
+            '(' | '[' | '{' => {
+                depth += 1;
+                i += 1;
+            }
+            ')' | ']' | '}' => {
+                depth = depth.checked_sub(1)?;
+                i += 1;
+            }
+            _ => i += 1,
+        }
+        l.last_line = line;
+        l.opens_block = c == ':' && i == token_start + 1;
+        if l.head.chars().count() < HEAD_CHARS {
+            l.head.extend(chars[token_start..i].iter().take(HEAD_CHARS));
+            // Keep the separator between keywords (`async def`, `def name`).
+            if chars.get(i).is_some_and(|n| *n == ' ' || *n == '\t') { l.head.push(' '); }
+        }
+    }
+    if depth > 0 || continued { return None; }
+    if let Some(l) = current.take() { lines.push(l); }
+    Some(lines)
+}
@@ -0,0 +1,201 @@
+//! Block extraction from HTML and Markdown.
+//!
+//! The HTML side follows the tokenizer of Python's `html.parser.HTMLParser`
+//! (tolerant start tags, `script`/`style` raw text, comments and declarations
+//! skipped, character references decoded in text) closely enough to yield the
+//! same blocks for real pages. Only common named references are decoded; a block
+//! with a decoded reference can't be found in the source again, so either way it
+//! is dropped by `chunk_text`.
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{SPACE, WORD};
+
+pub const ALLOWED_TAGS: &[&str] = &["p", "li", "h1", "h2", "h3", "h4", "h5", "h6", "pre", "code"];
+
+/// Elements whose content is raw text up to the matching end tag.
+const CDATA_CONTENT_ELEMENTS: &[&str] = &["script", "style"];
+
+static FENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!(r"(?m)^```(?:{}+)?\n([\s\S]*?)\n```", WORD)).unwrap());
+static PARAGRAPH_BREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n\n+").unwrap());
+
+fn is_py_space(c: char) -> bool {
+    c.is_whitespace() || ('\x1c'..='\x1f').contains(&c)
+}
+
+#[derive(Default)]
+struct BlockParser {
+    blocks: Vec<String>,
+    stack: Vec<String>,
+    buf: String,
+}
+
+impl BlockParser {
+    fn start_tag(&mut self, tag: &str) {
+        if ALLOWED_TAGS.contains(&tag) { self.stack.push(tag.to_string()); }
+    }
+
+    fn end_tag(&mut self, tag: &str) {
+        if ALLOWED_TAGS.contains(&tag) && self.stack.last().is_some_and(|t| t == tag) {
+            let text = self.buf.trim_matches(is_py_space);
+            if !text.is_empty() { self.blocks.push(text.to_string()); }
+            self.buf.clear();
+            self.stack.pop();
+        }
+    }
+
+    fn data(&mut self, data: &str) {
+        if !self.stack.is_empty() { self.buf.push_str(data); }
+    }
+}
+
+/// Text of every `p`, `li`, `h1`-`h6`, `pre` and `code` element, trimmed, in
+/// document order (an inner element closes before its parent).
+pub fn normalize_html_to_blocks(text: &str) -> Vec<String> {
+    let mut p = BlockParser::default();
+    let mut i = 0;
+    while i < text.len() {
+        let Some(lt) = text[i..].find('<').map(|j| i + j) else {
+            p.data(&unescape(&text[i..]));
+            break;
+        };
+        if lt > i { p.data(&unescape(&text[i..lt])); }
+        i = lt;
+        let rest = &text[i..];
+        let next = rest[1..].chars().next();
+        // Every branch that runs out of input stops: the parser would wait for more.
+        if next.is_some_and(|c| c.is_ascii_alphabetic()) {
+            let Some((tag, len, self_closing)) = parse_start_tag(rest) else { break };
+            i += len;
+            p.start_tag(&tag);
+            if self_closing {
+                p.end_tag(&tag);
+            } else if CDATA_CONTENT_ELEMENTS.contains(&tag.as_str()) {
+                let end = Regex::new(&format!(r"(?i)</{}*{}{}*>", SPACE, tag, SPACE)).unwrap();
+                let Some(m) = end.find(&text[i..]) else { break };
+                p.data(&text[i..i + m.start()]);
+                p.end_tag(&tag);
+                i += m.end();
+            }
+        } else if rest.starts_with("</") {
+            let Some(len) = parse_end_tag(rest, &mut p) else { break };
+            i += len;
+        } else if let Some(comment) = rest.strip_prefix("<!--") {
+            let Some(end) = comment.find("-->") else { break };
+            i += 4 + end + 3;
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            let Some(end) = rest.find('>') else { break };
+            i += end + 1;
+        } else if rest.len() > 1 {
+            p.data("<");
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    p.blocks
+}
+
+/// Parses `<name attrs...>` at the start of `s`: the lowercased name, the tag
+/// length and whether it ends in `/>`. `None` when the tag isn't closed.
+fn parse_start_tag(s: &str) -> Option<(String, usize, bool)> {
+    let name_len = s[1..].find(['\t', '\n', '\r', '\x0c', ' ', '/', '>', '\0']).unwrap_or(s.len() - 1);
+    let name = s[1..1 + name_len].to_lowercase();
+    let mut chars = s.char_indices().skip(1 + name_len);
+    let mut after_equals = false;
+    while let Some((j, c)) = chars.next() {
+        match c {
+            '>' => return Some((name, j + 1, s[..j].ends_with('/'))),
+            '"' | '\'' if after_equals => {
+                // A quoted value may contain `>`.
+                chars.by_ref().find(|&(_, q)| q == c)?;
+                after_equals = false;
+            }
+            '=' => after_equals = true,
+            c if is_py_space(c) => {}
+            _ => after_equals = false,
+        }
+    }
+    None
+}
+
+/// Handles `</name>` (or a bogus end tag) at the start of `s`; returns its length.
+fn parse_end_tag(s: &str, p: &mut BlockParser) -> Option<usize> {
+    let gt = s.find('>')?;
+    let inner = &s[2..gt];
+    let trimmed = inner.trim_start_matches(is_py_space);
+    if trimmed.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        let name_len = trimmed.find(['\t', '\n', '\r', '\x0c', ' ', '/', '>', '\0']).unwrap_or(trimmed.len());
+        p.end_tag(&trimmed[..name_len].to_lowercase());
+    }
+    // `</>` and `</ 1>` are dropped like comments.
+    Some(gt + 1)
+}
+
+/// Decodes numeric and common named character references, as `html.unescape` does.
+fn unescape(s: &str) -> String {
+    const NAMED: &[(&str, &str)] = &[
+        ("amp", "&"), ("lt", "<"), ("gt", ">"), ("quot", "\""), ("apos", "'"), ("nbsp", "\u{a0}"),
+        ("copy", "\u{a9}"), ("reg", "\u{ae}"), ("trade", "\u{2122}"), ("hellip", "\u{2026}"),
+        ("mdash", "\u{2014}"), ("ndash", "\u{2013}"), ("lsquo", "\u{2018}"), ("rsquo", "\u{2019}"),
+        ("ldquo", "\u{201c}"), ("rdquo", "\u{201d}"), ("bull", "\u{2022}"), ("middot", "\u{b7}"),
+    ];
+    /// References HTML5 also accepts without the trailing `;`.
+    const LEGACY: &[&str] = &["amp", "lt", "gt", "quot", "nbsp", "copy", "reg"];
+    if !s.contains('&') { return s.to_string(); }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let body = &rest[1..];
+        if let Some(num) = body.strip_prefix('#') {
+            let (digits, radix) = match num.strip_prefix(['x', 'X']) {
+                Some(hex) => (hex, 16),
+                None => (num, 10),
+            };
+            let len = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+            if len > 0 {
+                let code = u32::from_str_radix(&digits[..len], radix).ok();
+                out.push(code.filter(|&c| c != 0).and_then(char::from_u32).unwrap_or('\u{fffd}'));
+                let consumed = 1 + (num.len() - digits.len()) + 1 + len;
+                rest = &rest[consumed..];
+                if rest.starts_with(';') { rest = &rest[1..]; }
+                continue;
+            }
+        } else if let Some((name, value)) = NAMED.iter().find(|(n, _)| body.starts_with(n) && body[n.len()..].starts_with(';')) {
+            out.push_str(value);
+            rest = &rest[1 + name.len() + 1..];
+            continue;
+        } else if let Some((name, value)) = NAMED.iter().find(|(n, _)| LEGACY.contains(n) && body.starts_with(n)) {
+            out.push_str(value);
+            rest = &rest[1 + name.len()..];
+            continue;
+        }
+        out.push('&');
+        rest = body;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Fenced code bodies and the paragraphs around them, in order.
+pub fn parse_markdown_to_blocks(text: &str) -> Vec<String> {
+    let paragraphs = |s: &str| -> Vec<String> {
+        let s = s.trim_matches(is_py_space);
+        if s.is_empty() { return Vec::new(); }
+        PARAGRAPH_BREAK.split(s).filter(|x| !x.trim_matches(is_py_space).is_empty()).map(str::to_string).collect()
+    };
+    let mut blocks = Vec::new();
+    let mut pos = 0;
+    for caps in FENCE.captures_iter(text) {
+        let m = caps.get(0).unwrap();
+        blocks.extend(paragraphs(&text[pos..m.start()]));
+        let code = caps[1].trim_end_matches(is_py_space);
+        if !code.is_empty() { blocks.push(code.to_string()); }
+        pos = m.end();
+    }
+    blocks.extend(paragraphs(&text[pos..]));
+    blocks
+}
@@ -0,0 +1,138 @@
+//! Native port of the `intelligence/chunking` strategies.
+//!
+//! `chunk_text` produces the same spans, texts and chunk ids as the Python
+//! `chunk_text`, so the daemon can write `chunks` rows without a Python worker.
+//! Offsets are character offsets, like Python string indices: spans are found
+//! on byte offsets and converted once the chunks are assembled. Parity is
+//! checked against fixtures generated from the Python chunkers
+//! (`tests/fixtures/chunking_golden.json`).
+pub mod code;
+pub mod markup;
+pub mod prose;
+
+use uuid::Uuid;
+
+use crate::storage::db::ChunkRow;
+
+/// A `[start, end)` byte range into the text being chunked.
+pub type Span = (usize, usize);
+
+/// Python's `\w` on `str`: letters, numbers and `_` (narrower than Rust's `\w`,
+/// which also takes marks and connector punctuation).
+pub(crate) const WORD: &str = r"[\p{L}\p{N}_]";
+/// Python's `\s` on `str`, which also takes the ASCII separators `\x1c`-`\x1f`.
+pub(crate) const SPACE: &str = r"[\s\x1C-\x1F]";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub chunk_id: String,
+    pub event_id: Option<String>,
+    pub text: String,
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub content_type: String,
+}
+
+impl Chunk {
+    pub fn to_row(&self) -> ChunkRow {
+        ChunkRow {
+            chunk_id: self.chunk_id.clone(),
+            event_id: self.event_id.clone().unwrap_or_default(),
+            start_offset: self.start_offset as i64,
+            end_offset: self.end_offset as i64,
+            content_type: self.content_type.clone(),
+        }
+    }
+}
+
+/// Deterministic for an event (UUIDv5 of `<event_id>:<start>:<end>` in the URL
+/// namespace, as hex); random when there is no event id.
+pub fn make_chunk_id(event_id: Option<&str>, start_offset: usize, end_offset: usize) -> String {
+    match event_id.filter(|e| !e.is_empty()) {
+        Some(ev) => {
+            let base = format!("{}:{}:{}", ev, start_offset, end_offset);
+            Uuid::new_v5(&Uuid::NAMESPACE_URL, base.as_bytes()).simple().to_string()
+        }
+        None => Uuid::new_v4().simple().to_string(),
+    }
+}
+
+/// The `chunk_text` strategy for a file: its extension decides when there is
+/// one, then its MIME type (parameters ignored); `prose` otherwise.
+pub fn strategy_for(mime: &str, path: Option<&str>) -> &'static str {
+    let ext = path.and_then(|p| std::path::Path::new(p).extension()).and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    let by_ext = match ext.as_deref() {
+        Some("md" | "markdown") => Some("markdown"),
+        Some("html" | "htm") => Some("html"),
+        Some("py") => Some("python"),
+        Some("js" | "mjs" | "cjs" | "jsx") => Some("javascript"),
+        Some("ts" | "tsx") => Some("typescript"),
+        Some("go") => Some("go"),
+        Some("rs") => Some("rust"),
+        _ => None,
+    };
+    let essence = mime.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    by_ext.unwrap_or(match essence.as_str() {
+        "text/markdown" | "text/x-markdown" => "markdown",
+        "text/html" | "application/xhtml+xml" => "html",
+        "text/x-python" | "application/x-python" => "python",
+        "text/javascript" | "application/javascript" => "javascript",
+        "application/typescript" | "text/typescript" => "typescript",
+        "text/x-go" => "go",
+        "text/x-rust" => "rust",
+        _ => "prose",
+    })
+}
+
+/// Chunks `content` with the strategy for `content_type` (`prose`, `markdown`,
+/// `html`, `python`, `js`/`ts`, `go`, `rust`, ...); unknown types are chunked as prose.
+pub fn chunk_text(content: &str, content_type: &str, event_id: Option<&str>) -> Vec<Chunk> {
+    let ct = content_type.to_lowercase();
+    let spans = match ct.as_str() {
+        "text" | "prose" | "plain" => prose::chunk_prose(content),
+        "html" => {
+            let mut spans = Vec::new();
+            let mut offset = 0;
+            for block in markup::normalize_html_to_blocks(content) {
+                let Some(i) = content[offset..].find(&block).map(|i| offset + i) else { continue };
+                spans.extend(prose::chunk_prose(&block).into_iter().map(|(s, e)| (i + s, i + e)));
+                offset = i + block.len();
+            }
+            spans
+        }
+        "markdown" | "md" => {
+            let mut spans = Vec::new();
+            let mut cursor = 0;
+            for block in markup::parse_markdown_to_blocks(content) {
+                let found = content[cursor..].find(&block).map(|i| cursor + i).or_else(|| content.find(&block));
+                let Some(i) = found else { continue };
+                spans.extend(prose::chunk_prose(&block).into_iter().map(|(s, e)| (i + s, i + e)));
+                cursor = i + block.len();
+            }
+            spans
+        }
+        "python" | "py" => code::chunk_python(content),
+        "javascript" | "js" | "typescript" | "ts" => code::chunk_js_ts(content),
+        "go" => code::chunk_go(content),
+        "rust" | "rs" => code::chunk_rust(content),
+        _ => prose::chunk_prose(content),
+    };
+    assemble(content, &spans, &ct, event_id)
+}
+
+fn assemble(content: &str, spans: &[Span], content_type: &str, event_id: Option<&str>) -> Vec<Chunk> {
+    // Byte offset of every char boundary; a boundary's index is its char offset.
+    let boundaries: Vec<usize> = content.char_indices().map(|(i, _)| i).chain(std::iter::once(content.len())).collect();
+    let char_offset = |b: usize| boundaries.partition_point(|&x| x < b);
+    spans.iter().map(|&(s, e)| {
+        let (start, end) = (char_offset(s), char_offset(e));
+        Chunk {
+            chunk_id: make_chunk_id(event_id, start, end),
+            event_id: event_id.map(str::to_string),
+            text: content[s..e].to_string(),
+            start_offset: start,
+            end_offset: end,
+            content_type: content_type.to_string(),
+        }
+    }).collect()
+}
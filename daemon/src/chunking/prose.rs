@@ -0,0 +1,82 @@
+//! Paragraph/sentence chunking with token-count targets and overlap.
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{Span, SPACE, WORD};
+
+pub const DEFAULT_TARGET_TOKENS: usize = 400;
+pub const DEFAULT_OVERLAP_RATIO: f64 = 0.25;
+
+static WORDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!("{}+", WORD)).unwrap());
+static SPACES: LazyLock<Regex> = LazyLock::new(|| Regex::new(&format!("{}+", SPACE)).unwrap());
+static PARAGRAPH_BREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n\n+").unwrap());
+
+/// Word count, at least 1.
+pub fn estimate_tokens(text: &str) -> usize {
+    WORDS.find_iter(text).count().max(1)
+}
+
+/// Splits after `.`, `!` or `?` followed by whitespace. Each sentence keeps the
+/// first whitespace character after its punctuation, as in Python.
+pub fn split_sentences(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    // Python matches `(?<=[.!?])\s+`; `regex` has no lookbehind, so check the
+    // char before each whitespace run instead.
+    for m in SPACES.find_iter(text) {
+        if !matches!(text[..m.start()].chars().next_back(), Some('.' | '!' | '?')) { continue; }
+        let first = text[m.start()..].chars().next().map_or(1, char::len_utf8);
+        spans.push((start, m.start() + first));
+        start = m.end();
+    }
+    if start < text.len() { spans.push((start, text.len())); }
+    spans
+}
+
+pub fn split_paragraphs(text: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    for m in PARAGRAPH_BREAK.find_iter(text) {
+        if m.start() > start { spans.push((start, m.start())); }
+        start = m.end();
+    }
+    if start < text.len() { spans.push((start, text.len())); }
+    spans
+}
+
+pub fn chunk_prose(text: &str) -> Vec<Span> {
+    chunk_prose_with(text, DEFAULT_TARGET_TOKENS, DEFAULT_OVERLAP_RATIO)
+}
+
+/// Accumulates sentences of each paragraph until `target_tokens`, then starts
+/// the next chunk with trailing sentences worth `overlap_ratio` of the target.
+pub fn chunk_prose_with(text: &str, target_tokens: usize, overlap_ratio: f64) -> Vec<Span> {
+    let tokens = |(s, e): Span| estimate_tokens(&text[s..e]);
+    let overlap_target = (target_tokens as f64 * overlap_ratio) as usize;
+    let mut chunks = Vec::new();
+    for (ps, pe) in split_paragraphs(text) {
+        let mut acc: Vec<Span> = Vec::new();
+        let mut acc_tokens = 0;
+        for (s, e) in split_sentences(&text[ps..pe]) {
+            let sentence = (ps + s, ps + e);
+            acc.push(sentence);
+            acc_tokens += tokens(sentence);
+            if acc_tokens >= target_tokens {
+                chunks.push((acc[0].0, acc[acc.len() - 1].1));
+                let mut back_tokens = 0;
+                let mut keep = acc.len();
+                while keep > 0 && back_tokens < overlap_target {
+                    keep -= 1;
+                    back_tokens += tokens(acc[keep]);
+                }
+                acc.drain(..keep);
+                acc_tokens = acc.iter().map(|&a| tokens(a)).sum();
+            }
+        }
+        if let (Some(first), Some(last)) = (acc.first(), acc.last()) {
+            if first.0 < last.1 { chunks.push((first.0, last.1)); }
+        }
+    }
+    chunks
+}
@@ -0,0 +1,271 @@
+//! Text embedding providers used by the daemon itself (the `embed` pipeline
+//! stage and query embedding for hybrid retrieval).
+//!
+//! Every provider tags its vectors with a model version; the vector index only
+//! holds vectors of one version, so switching models means re-embedding.
+//! `MockEmbedder` reproduces `intelligence/embeddings/mock.py` bit for bit, so
+//! vectors from the intel workers and from the daemon are interchangeable.
+//! `OpenAiCompatEmbedder` talks to any local server exposing the OpenAI
+//! `/v1/embeddings` API (llama.cpp server, Ollama, vLLM, ...).
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::storage::Result;
+
+pub const MOCK_DIM: usize = 128;
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+pub const DEFAULT_MAX_RETRIES: usize = 3;
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(250);
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Turns text into vectors.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Tag stored with every vector; vectors with different tags are never compared.
+    fn model_version(&self) -> &str;
+
+    /// Most texts sent in one `embed_batch` call.
+    fn batch_size(&self) -> usize {
+        DEFAULT_BATCH_SIZE
+    }
+
+    /// Embeds at most `batch_size` texts, one vector per text, in order.
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Embeds any number of texts, `batch_size` at a time.
+pub async fn embed(provider: &dyn EmbeddingProvider, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    let mut out = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(provider.batch_size().max(1)) {
+        let vectors = provider.embed_batch(batch).await?;
+        if vectors.len() != batch.len() {
+            return Err(format!("{} returned {} vectors for {} texts", provider.name(), vectors.len(), batch.len()).into());
+        }
+        out.extend(vectors);
+    }
+    Ok(out)
+}
+
+/// Deterministic vectors seeded from a hash of the text; for tests and offline use.
+pub struct MockEmbedder {
+    dim: usize,
+    model_version: String,
+}
+
+impl MockEmbedder {
+    pub fn new(dim: usize) -> Self {
+        Self { dim, model_version: format!("mock-emb-{}", dim) }
+    }
+
+    /// Python's `random.Random(seed).uniform(-1, 1)`, drawn `dim` times, where
+    /// the seed is the first 8 bytes of the text's SHA-256 read little-endian.
+    pub fn embed_one(&self, text: &str) -> Vec<f32> {
+        let digest = Sha256::digest(text.as_bytes());
+        let seed = u64::from_le_bytes(digest[..8].try_into().expect("8 bytes"));
+        let mut rng = Mt19937::from_python_seed(seed);
+        (0..self.dim).map(|_| (-1.0 + 2.0 * rng.random()) as f32).collect()
+    }
+}
+
+impl Default for MockEmbedder {
+    fn default() -> Self {
+        Self::new(MOCK_DIM)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for MockEmbedder {
+    fn name(&self) -> &str { "mock" }
+    fn model_version(&self) -> &str { &self.model_version }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|t| self.embed_one(t)).collect())
+    }
+}
+
+/// The Mersenne Twister behind Python's `random`, seeded the way
+/// `random.seed(int)` does it.
+struct Mt19937 {
+    state: [u32; 624],
+    index: usize,
+}
+
+impl Mt19937 {
+    fn new(seed: u32) -> Self {
+        let mut state = [0u32; 624];
+        state[0] = seed;
+        for i in 1..624 {
+            state[i] = 1_812_433_253u32.wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30)).wrapping_add(i as u32);
+        }
+        Self { state, index: 624 }
+    }
+
+    /// `init_by_array` with the seed split into 32-bit words, low word first.
+    fn from_python_seed(seed: u64) -> Self {
+        let key: Vec<u32> = if seed >> 32 == 0 { vec![seed as u32] } else { vec![seed as u32, (seed >> 32) as u32] };
+        let mut mt = Self::new(19_650_218);
+        let s = &mut mt.state;
+        let (mut i, mut j) = (1usize, 0usize);
+        for _ in 0..624.max(key.len()) {
+            s[i] = (s[i] ^ (s[i - 1] ^ (s[i - 1] >> 30)).wrapping_mul(1_664_525)).wrapping_add(key[j]).wrapping_add(j as u32);
+            i += 1;
+            j += 1;
+            if i >= 624 { s[0] = s[623]; i = 1; }
+            if j >= key.len() { j = 0; }
+        }
+        for _ in 0..623 {
+            s[i] = (s[i] ^ (s[i - 1] ^ (s[i - 1] >> 30)).wrapping_mul(1_566_083_941)).wrapping_sub(i as u32);
+            i += 1;
+            if i >= 624 { s[0] = s[623]; i = 1; }
+        }
+        s[0] = 0x8000_0000;
+        mt
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= 624 {
+            for k in 0..624 {
+                let y = (self.state[k] & 0x8000_0000) | (self.state[(k + 1) % 624] & 0x7fff_ffff);
+                let mut next = self.state[(k + 397) % 624] ^ (y >> 1);
+                if y & 1 != 0 { next ^= 0x9908_b0df; }
+                self.state[k] = next;
+            }
+            self.index = 0;
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c_5680;
+        y ^= (y << 15) & 0xefc6_0000;
+        y ^ (y >> 18)
+    }
+
+    /// A float in `[0, 1)` with 53 random bits, as `random.random()`.
+    fn random(&mut self) -> f64 {
+        let a = (self.next_u32() >> 5) as f64;
+        let b = (self.next_u32() >> 6) as f64;
+        (a * 67_108_864.0 + b) / 9_007_199_254_740_992.0
+    }
+}
+
+/// Provider for any server exposing the OpenAI `/v1/embeddings` API.
+///
+/// Requests that fail to connect, time out, or get a 429 or 5xx answer are
+/// retried `max_retries` times with exponential backoff; other errors are final.
+pub struct OpenAiCompatEmbedder {
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+    pub model_version: String,
+    pub batch_size: usize,
+    pub max_retries: usize,
+    /// Wait before the first retry; doubled for each further one.
+    pub retry_backoff: Duration,
+}
+
+impl OpenAiCompatEmbedder {
+    /// Tags vectors `openai-compat:<model>` until `model_version` is changed.
+    pub fn new(base_url: &str, model: &str, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            client,
+            model_version: format!("openai-compat:{}", model),
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+        })
+    }
+
+    /// One request; `Err((retryable, error))` on failure.
+    async fn request(&self, texts: &[String]) -> std::result::Result<Vec<Vec<f32>>, (bool, BoxError)> {
+        let body = json!({ "model": self.model, "input": texts });
+        let resp = self.client.post(format!("{}/v1/embeddings", self.base_url)).json(&body).send().await
+            .map_err(|e| (e.is_timeout() || e.is_connect(), e.into()))?;
+        let status = resp.status();
+        if !status.is_success() {
+            let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            return Err((retryable, format!("embedding backend returned {}", status).into()));
+        }
+        let v: Value = resp.json().await.map_err(|e| (false, e.into()))?;
+        parse_embeddings(&v, texts.len()).map_err(|e| (false, e))
+    }
+}
+
+/// Vectors from an OpenAI embeddings response, put back in input order by `index`.
+fn parse_embeddings(v: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let data = v["data"].as_array().ok_or("embedding response lacks data")?;
+    let mut out: Vec<Option<Vec<f32>>> = vec![None; expected];
+    for (pos, item) in data.iter().enumerate() {
+        let index = item["index"].as_u64().map(|i| i as usize).unwrap_or(pos);
+        let vector: Vec<f32> = item["embedding"].as_array().ok_or("embedding response item lacks embedding")?
+            .iter().map(|x| x.as_f64().map(|f| f as f32)).collect::<Option<_>>().ok_or("embedding has non-numeric values")?;
+        *out.get_mut(index).ok_or("embedding index out of range")? = Some(vector);
+    }
+    let out: Vec<Vec<f32>> = out.into_iter().collect::<Option<_>>().ok_or("embedding response is missing inputs")?;
+    if out.windows(2).any(|w| w[0].len() != w[1].len()) {
+        return Err("embedding response mixes dimensions".into());
+    }
+    Ok(out)
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiCompatEmbedder {
+    fn name(&self) -> &str { "openai-compat" }
+    fn model_version(&self) -> &str { &self.model_version }
+    fn batch_size(&self) -> usize { self.batch_size }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut attempt = 0;
+        loop {
+            match self.request(texts).await {
+                Ok(vectors) => return Ok(vectors),
+                Err((true, e)) if attempt < self.max_retries => {
+                    let wait = self.retry_backoff * 2u32.pow(attempt as u32);
+                    attempt += 1;
+                    info!(attempt, %e, "embedding request failed; retrying in {} ms", wait.as_millis());
+                    tokio::time::sleep(wait).await;
+                }
+                Err((_, e)) => return Err(e),
+            }
+        }
+    }
+}
+
+/// `VYASOAI_EMBED_URL` selects the OpenAI-compatible provider, configured by
+/// `VYASOAI_EMBED_MODEL`, `VYASOAI_EMBED_MODEL_VERSION`, `VYASOAI_EMBED_BATCH`,
+/// `VYASOAI_EMBED_RETRIES` and `VYASOAI_EMBED_TIMEOUT_MS`. Without it the mock
+/// provider is used, matching the intel workers' default backend.
+pub fn provider_from_env() -> Arc<dyn EmbeddingProvider> {
+    let num = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+    match std::env::var("VYASOAI_EMBED_URL") {
+        Ok(url) if !url.trim().is_empty() => {
+            let model = std::env::var("VYASOAI_EMBED_MODEL").unwrap_or_else(|_| "local".to_string());
+            let timeout = Duration::from_millis(num("VYASOAI_EMBED_TIMEOUT_MS").unwrap_or(30_000));
+            match OpenAiCompatEmbedder::new(&url, &model, timeout) {
+                Ok(mut p) => {
+                    if let Ok(v) = std::env::var("VYASOAI_EMBED_MODEL_VERSION") {
+                        if !v.trim().is_empty() { p.model_version = v; }
+                    }
+                    if let Some(n) = num("VYASOAI_EMBED_BATCH") { p.batch_size = (n as usize).max(1); }
+                    if let Some(n) = num("VYASOAI_EMBED_RETRIES") { p.max_retries = n as usize; }
+                    Arc::new(p)
+                }
+                Err(e) => {
+                    warn!(%e, "failed to build embedding provider; using the mock provider");
+                    Arc::new(MockEmbedder::default())
+                }
+            }
+        }
+        _ => Arc::new(MockEmbedder::default()),
+    }
+}
@@ -0,0 +1,186 @@
+//! Live feed of persisted memories.
+//!
+//! The ingest worker publishes a `stored` notice once an event row is committed
+//! and an `indexed` notice once its chunks are written. Notices fan out over a
+//! broadcast channel and are kept in a bounded ring so a reconnecting client can
+//! replay what it missed from its last event id. Ids are `<boot>-<seq>`: an id
+//! from an earlier daemon run, or one older than the ring, yields a `resync`
+//! notice telling the client to refetch the timeline instead.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
+
+use crate::handlers::{EventEnvelope, PrivacyFlag};
+
+const RING_CAPACITY: usize = 1024;
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedKind {
+    Stored,
+    Indexed,
+    /// The client missed notices that can no longer be replayed.
+    Resync,
+}
+
+impl FeedKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedKind::Stored => "stored",
+            FeedKind::Indexed => "indexed",
+            FeedKind::Resync => "resync",
+        }
+    }
+}
+
+/// One feed notice. Metadata only: content never goes over the feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedEvent {
+    pub id: String,
+    pub kind: FeedKind,
+    pub event_id: Option<String>,
+    pub timestamp: Option<String>,
+    pub app: Option<String>,
+    pub source: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub privacy_flag: Option<PrivacyFlag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<u64>,
+}
+
+/// Subscriber filters; `tags` matches when the event carries any of them.
+#[derive(Debug, Default, Clone)]
+pub struct FeedFilter {
+    pub app: Option<String>,
+    pub source: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl FeedFilter {
+    pub fn matches(&self, ev: &FeedEvent) -> bool {
+        if ev.kind == FeedKind::Resync { return true; }
+        if self.app.as_ref().is_some_and(|a| ev.app.as_ref() != Some(a)) { return false; }
+        if self.source.as_ref().is_some_and(|s| ev.source.as_ref() != Some(s)) { return false; }
+        self.tags.is_empty() || self.tags.iter().any(|t| ev.tags.contains(t))
+    }
+}
+
+struct Ring {
+    next_seq: u64,
+    events: VecDeque<(u64, FeedEvent)>,
+}
+
+pub struct Feed {
+    boot: String,
+    tx: broadcast::Sender<FeedEvent>,
+    ring: Mutex<Ring>,
+    closed: watch::Sender<bool>,
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Feed {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let boot = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        let (closed, _) = watch::channel(false);
+        Self { boot, tx, ring: Mutex::new(Ring { next_seq: 1, events: VecDeque::with_capacity(RING_CAPACITY) }), closed }
+    }
+
+    /// Ends every open subscription, so long-lived connections don't hold up shutdown.
+    pub fn close(&self) {
+        let _ = self.closed.send(true);
+    }
+
+    pub fn publish(&self, kind: FeedKind, ev: &EventEnvelope, chunks: Option<u64>) {
+        let mut ring = self.ring.lock().unwrap();
+        let seq = ring.next_seq;
+        ring.next_seq += 1;
+        let notice = FeedEvent {
+            id: format!("{}-{}", self.boot, seq),
+            kind,
+            event_id: Some(ev.event_id.clone()),
+            timestamp: Some(ev.timestamp.clone()),
+            app: Some(ev.app.clone()),
+            source: Some(ev.source.clone()),
+            tags: ev.tags.clone(),
+            privacy_flag: Some(ev.privacy_flag.clone()),
+            chunks,
+        };
+        if ring.events.len() == RING_CAPACITY { ring.events.pop_front(); }
+        ring.events.push_back((seq, notice.clone()));
+        // Sent under the ring lock so `subscribe` sees each notice exactly once.
+        let _ = self.tx.send(notice);
+    }
+
+    /// Returns the notices to replay after `last_event_id` and a receiver for
+    /// everything published afterwards.
+    pub fn subscribe(&self, last_event_id: Option<&str>) -> (Vec<FeedEvent>, broadcast::Receiver<FeedEvent>) {
+        let ring = self.ring.lock().unwrap();
+        let rx = self.tx.subscribe();
+        let Some(last) = last_event_id.filter(|s| !s.is_empty()) else { return (Vec::new(), rx) };
+        let seq = last.strip_prefix(&self.boot)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .and_then(|n| n.parse::<u64>().ok());
+        let oldest = ring.events.front().map(|(s, _)| *s).unwrap_or(ring.next_seq);
+        match seq {
+            // Caught up, or everything after `seq` is still in the ring.
+            Some(seq) if seq < ring.next_seq && seq + 1 >= oldest => {
+                let backlog = ring.events.iter().filter(|(s, _)| *s > seq).map(|(_, e)| e.clone()).collect();
+                (backlog, rx)
+            }
+            _ => (vec![self.resync_at(&ring)], rx),
+        }
+    }
+
+    /// A `resync` notice carrying the latest id, so the client resumes from here.
+    pub fn resync(&self) -> FeedEvent {
+        let ring = self.ring.lock().unwrap();
+        self.resync_at(&ring)
+    }
+
+    fn resync_at(&self, ring: &Ring) -> FeedEvent {
+        FeedEvent {
+            id: format!("{}-{}", self.boot, ring.next_seq - 1),
+            kind: FeedKind::Resync,
+            event_id: None,
+            timestamp: None,
+            app: None,
+            source: None,
+            tags: Vec::new(),
+            privacy_flag: None,
+            chunks: None,
+        }
+    }
+
+    /// Replay after `last_event_id`, then live notices, filtered. Ends on `close`.
+    pub fn stream(self: &Arc<Self>, last_event_id: Option<&str>, filter: FeedFilter) -> impl Stream<Item = FeedEvent> + Send + 'static {
+        let (backlog, rx) = self.subscribe(last_event_id);
+        let closed = self.closed.subscribe();
+        let live = futures_util::stream::unfold((rx, closed, Arc::clone(self)), |(mut rx, mut closed, feed)| async move {
+            if *closed.borrow() { return None; }
+            let next = tokio::select! {
+                r = rx.recv() => r,
+                _ = closed.changed() => return None,
+            };
+            match next {
+                Ok(ev) => Some((ev, (rx, closed, feed))),
+                // This subscriber fell behind the channel; tell it to refetch.
+                Err(broadcast::error::RecvError::Lagged(_)) => Some((feed.resync(), (rx, closed, feed))),
+                Err(broadcast::error::RecvError::Closed) => None,
+            }
+        });
+        futures_util::stream::iter(backlog)
+            .chain(live)
+            .filter(move |ev| std::future::ready(filter.matches(ev)))
+    }
+}
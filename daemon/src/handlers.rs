@@ -1,5 +1,6 @@
 use axum::{extract::State, http::{StatusCode, HeaderMap}, Json};
-use axum::extract::Path;
+use axum::extract::{Path, Query};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use uuid::{Uuid, Version};
@@ -62,18 +63,285 @@ pub async fn post_event(
     State(app): State<std::sync::Arc<crate::state::AppState>>,
     headers: HeaderMap,
     Json(envelope): Json<EventEnvelope>,
-) -> (StatusCode, Json<Value>) {
-    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    if let Err(r) = validate_client(&headers) { return r.into_response(); }
+    admission_response(admit_event(&app, envelope.into()).await)
+}
+
+/// How an admitted event was handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Admitted {
+    Queued,
+    /// Already stored with the same content_hash: a connector retry.
+    Duplicate,
+}
+
+fn admission_response(r: Result<Admitted, (StatusCode, Value)>) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    match r {
+        Ok(Admitted::Queued) => (StatusCode::ACCEPTED, Json(json!({ "queued": true })) ).into_response(),
+        Ok(Admitted::Duplicate) => (StatusCode::OK, Json(json!({ "queued": false, "duplicate": true }))).into_response(),
+        Err((code, body)) => {
+            let retry_after = body.get("retry_after_secs").and_then(Value::as_u64);
+            let mut resp = (code, Json(body)).into_response();
+            if let Some(secs) = retry_after {
+                resp.headers_mut().insert(axum::http::header::RETRY_AFTER, secs.into());
+            }
+            resp
+        }
+    }
+}
+
+/// Validates one job, applies the capture pause and enqueues it.
+/// Shared by the single-event, batch and upload endpoints so they admit identically.
+async fn admit_event(app: &crate::state::AppState, job: crate::state::IngestJob) -> Result<Admitted, (StatusCode, Value)> {
+    let envelope = &job.envelope;
     if let Err(e) = envelope.validate() {
-        return (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+        return Err((StatusCode::BAD_REQUEST, json!({ "error": e })));
+    }
+    // Events still in the queue slip past this; the worker repeats the check.
+    let existing = {
+        let conn = app.db.lock().unwrap();
+        crate::storage::db::find_event_hash(&conn, &envelope.event_id)
+    };
+    match existing {
+        Ok(Some(h)) if h == envelope.content_hash => return Ok(Admitted::Duplicate),
+        Ok(Some(h)) => return Err((StatusCode::CONFLICT, json!({ "error": "event_id already stored with different content", "existing_hash": h }))),
+        Ok(None) => {}
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, json!({ "error": e.to_string() }))),
+    }
+    if job.content.is_none() {
+        // Checked again in the worker, right before the file is read.
+        if let Err(reason) = app.path_policy.resolve(&envelope.content_pointer) {
+            return Err((StatusCode::FORBIDDEN, json!({ "error": reason })));
+        }
+    }
+    if let Some(bytes) = job.content.as_deref() {
+        if let Err(mismatch) = crate::queue::verify_content(envelope, bytes) {
+            let conn = app.db.lock().unwrap();
+            let _ = crate::storage::db::record_ingest_error(&conn, &mismatch);
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, json!({ "error": mismatch.message, "kind": mismatch.kind })));
+        }
+    }
+    let paused = app.capture.lock().unwrap().check(&envelope.app, &envelope.source, time::OffsetDateTime::now_utc());
+    if let Some(reason) = paused {
+        return Err((StatusCode::LOCKED, json!({ "queued": false, "error": "capture_paused", "paused": reason })));
+    }
+    // Never wait for queue space: a full queue is the client's cue to back off.
+    let permit = match app.queue_tx.try_reserve() {
+        Ok(permit) => Some(permit),
+        Err(tokio::sync::mpsc::error::TrySendError::Full(())) => {
+            app.queue_stats.record_dropped();
+            let retry_after = app.queue_stats.retry_after(crate::queue::queue_depth(app));
+            return Err((StatusCode::TOO_MANY_REQUESTS, json!({ "queued": false, "error": "queue_full", "retry_after_secs": retry_after })));
+        }
+        // The worker is gone; the journal still carries the event to the next start.
+        Err(tokio::sync::mpsc::error::TrySendError::Closed(())) => None,
+    };
+    // Journal before answering, so an accepted event survives a crash.
+    let mut job = job;
+    let seq = {
+        let conn = app.db.lock().unwrap();
+        crate::storage::journal::append(&conn, &job.envelope, job.content.as_deref())
+            .and_then(|seq| crate::storage::processing::mark_queued(&conn, &job.envelope.event_id).map(|()| seq))
+    };
+    match seq {
+        Ok(seq) => job.journal_seq = Some(seq),
+        Err(e) => return Err((StatusCode::SERVICE_UNAVAILABLE, json!({ "queued": false, "error": e.to_string() }))),
+    }
+    match permit {
+        Some(permit) => permit.send(job),
+        None => tracing::warn!("ingest worker unavailable; event left in journal"),
+    }
+    app.queue_stats.record_accepted();
+    Ok(Admitted::Queued)
+}
+
+pub const UPLOAD_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// `upload`: multipart form with an `envelope` part (JSON) and a `content`
+/// part carrying the bytes, for clients without a shared filesystem. The bytes
+/// must match `content_hash` and `size_bytes`; `content_pointer` is ignored.
+async fn event_upload(app: &crate::state::AppState, mut multipart: axum::extract::Multipart) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    let mut envelope: Option<EventEnvelope> = None;
+    let mut content: Option<Vec<u8>> = None;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(f)) => f,
+            Ok(None) => break,
+            Err(e) => return (e.status(), Json(json!({ "error": e.body_text() }))).into_response(),
+        };
+        let name = field.name().unwrap_or_default().to_string();
+        let bytes = match field.bytes().await {
+            Ok(b) => b,
+            Err(e) => return (e.status(), Json(json!({ "error": e.body_text() }))).into_response(),
+        };
+        match name.as_str() {
+            "envelope" => match serde_json::from_slice(&bytes) {
+                Ok(e) => envelope = Some(e),
+                Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("invalid envelope: {}", e) }))).into_response(),
+            },
+            "content" => content = Some(bytes.to_vec()),
+            other => return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("unexpected part: {}", other) }))).into_response(),
+        }
+    }
+    let (Some(envelope), Some(content)) = (envelope, content) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "multipart body needs envelope and content parts" }))).into_response();
+    };
+    admission_response(admit_event(app, crate::state::IngestJob { envelope, content: Some(content), journal_seq: None }).await)
+}
+
+const BATCH_MAX_EVENTS: usize = 1000;
+const BATCH_MAX_BYTES: usize = 16 * 1024 * 1024;
+const NDJSON_MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Per-event outcome of `/v1/events:batch`, in request order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    pub index: usize,
+    pub event_id: Option<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Set when the event was refused because capture is paused.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused: Option<Value>,
+    /// Set when the event was refused because the ingest queue is full.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Default)]
+struct BatchOutcome {
+    accepted: usize,
+    rejected: usize,
+    results: Vec<BatchItemResult>,
+}
+
+impl BatchOutcome {
+    /// Parses and admits one element; failures are recorded rather than aborting the batch.
+    async fn admit(&mut self, app: &crate::state::AppState, value: Result<Value, String>) {
+        let index = self.results.len();
+        let event_id = value.as_ref().ok()
+            .and_then(|v| v.get("event_id")).and_then(|v| v.as_str()).map(str::to_string);
+        let outcome = match value {
+            Ok(v) => match serde_json::from_value::<EventEnvelope>(v) {
+                Ok(envelope) => admit_event(app, envelope.into()).await,
+                Err(e) => Err((StatusCode::BAD_REQUEST, json!({ "error": e.to_string() }))),
+            },
+            Err(e) => Err((StatusCode::BAD_REQUEST, json!({ "error": e }))),
+        };
+        let result = match outcome {
+            Ok(admitted) => {
+                // A duplicate is already stored, which is what a retrying connector wants to hear.
+                self.accepted += 1;
+                let status = if admitted == Admitted::Duplicate { "duplicate" } else { "accepted" };
+                BatchItemResult { index, event_id, status: status.into(), code: None, error: None, paused: None, retry_after_secs: None }
+            }
+            Err((code, mut body)) => {
+                self.rejected += 1;
+                let error = body.get("error").and_then(|e| e.as_str()).map(str::to_string);
+                let paused = body.get_mut("paused").map(Value::take);
+                let retry_after_secs = body.get("retry_after_secs").and_then(Value::as_u64);
+                BatchItemResult { index, event_id, status: "rejected".into(), code: Some(code.as_u16()), error, paused, retry_after_secs }
+            }
+        };
+        self.results.push(result);
+    }
+
+    fn into_response(self) -> (StatusCode, Json<Value>) {
+        (StatusCode::OK, Json(json!({ "accepted": self.accepted, "rejected": self.rejected, "results": self.results })))
     }
-    match app.queue_tx.send(envelope).await {
-        Ok(()) => (StatusCode::ACCEPTED, Json(json!({ "queued": true })) ),
-        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "queued": false, "error": e.to_string() })) ),
+}
+
+/// `POST /v1/events:batch` and `POST /v1/events:upload`. The router reads `:`
+/// as the start of a path parameter, so both custom verbs share one route and
+/// are dispatched here; anything else after `/v1/events` is a 404.
+pub async fn post_events_verb(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    Path(verb): Path<String>,
+    headers: HeaderMap,
+    req: axum::extract::Request,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    if let Err(r) = validate_client(&headers) { return r.into_response(); }
+    // The route captures everything after `/v1/events`, colon included.
+    match verb.as_str() {
+        ":batch" => events_batch(&app, &headers, req.into_body()).await.into_response(),
+        ":upload" => {
+            use axum::extract::FromRequest;
+            match axum::extract::Multipart::from_request(req, &()).await {
+                Ok(m) => event_upload(&app, m).await,
+                Err(e) => (StatusCode::BAD_REQUEST, Json(json!({ "error": e.body_text() }))).into_response(),
+            }
+        }
+        _ => (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))).into_response(),
     }
 }
 
-#[derive(Deserialize, Serialize)]
+/// `batch`: a JSON array of envelopes, or one envelope per line with
+/// `Content-Type: application/x-ndjson`. NDJSON bodies are read and enqueued
+/// incrementally, so a connector can stream a long burst.
+async fn events_batch(app: &crate::state::AppState, headers: &HeaderMap, body: axum::body::Body) -> (StatusCode, Json<Value>) {
+    let ndjson = headers.get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.starts_with("application/x-ndjson") || ct.starts_with("application/jsonl"))
+        .unwrap_or(false);
+    let mut outcome = BatchOutcome::default();
+    if ndjson {
+        use futures_util::StreamExt;
+        let mut stream = body.into_data_stream();
+        let mut buf: Vec<u8> = Vec::new();
+        loop {
+            let chunk = match stream.next().await {
+                Some(Ok(c)) => Some(c),
+                Some(Err(e)) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string(), "accepted": outcome.accepted, "results": outcome.results }))),
+                None => None,
+            };
+            let done = chunk.is_none();
+            if let Some(c) = chunk { buf.extend_from_slice(&c); }
+            while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buf.drain(..=pos).collect();
+                admit_ndjson_line(app, &mut outcome, &line).await;
+            }
+            if buf.len() > NDJSON_MAX_LINE_BYTES {
+                return (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({ "error": "ndjson line too long", "accepted": outcome.accepted, "results": outcome.results })));
+            }
+            if done {
+                admit_ndjson_line(app, &mut outcome, &buf).await;
+                break;
+            }
+        }
+    } else {
+        let bytes = match axum::body::to_bytes(body, BATCH_MAX_BYTES).await {
+            Ok(b) => b,
+            Err(e) => return (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({ "error": e.to_string() }))),
+        };
+        let items: Vec<Value> = match serde_json::from_slice(&bytes) {
+            Ok(v) => v,
+            Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("expected a JSON array of envelopes: {}", e) }))),
+        };
+        if items.len() > BATCH_MAX_EVENTS {
+            return (StatusCode::PAYLOAD_TOO_LARGE, Json(json!({ "error": format!("at most {} events per batch", BATCH_MAX_EVENTS) })));
+        }
+        for item in items {
+            outcome.admit(app, Ok(item)).await;
+        }
+    }
+    outcome.into_response()
+}
+
+async fn admit_ndjson_line(app: &crate::state::AppState, outcome: &mut BatchOutcome, line: &[u8]) {
+    if line.iter().all(|b| b.is_ascii_whitespace()) { return; }
+    let value = serde_json::from_slice::<Value>(line).map_err(|e| e.to_string());
+    outcome.admit(app, value).await;
+}
+
+#[derive(Deserialize, Serialize, Default)]
 pub struct PurgeRequest {
     pub event_ids: Option<Vec<String>>,
     pub start: Option<String>,
@@ -81,6 +349,34 @@ pub struct PurgeRequest {
     pub app: Option<String>,
     pub source: Option<String>,
     pub privacy_flag: Option<PrivacyFlag>,
+    /// Report what would be deleted without deleting it.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Events listed in a dry run's `sample`.
+    pub sample_limit: Option<u32>,
+    /// Run as a background job; by default only purges of more than
+    /// `PURGE_SYNC_MAX_EVENTS` events do. Requires `skip_trash`: moving
+    /// events to the trash is a single statement and always runs inline.
+    pub background: Option<bool>,
+    /// Delete right away instead of moving the events to the trash.
+    #[serde(default)]
+    pub skip_trash: bool,
+    /// Also purge events on hold; they are skipped and counted otherwise.
+    #[serde(default)]
+    pub include_held: bool,
+}
+
+fn privacy_flag_str(p: &PrivacyFlag) -> &'static str {
+    match p {
+        PrivacyFlag::Default => "default",
+        PrivacyFlag::Sensitive => "sensitive",
+        PrivacyFlag::NeverStore => "never_store",
+    }
+}
+
+/// Whether `id` is in the trash, which read APIs treat as not found.
+fn is_trashed(conn: &rusqlite::Connection, id: &str) -> bool {
+    crate::storage::trash::is_trashed(conn, id).unwrap_or(false)
 }
 
 pub async fn get_mem(
@@ -94,12 +390,151 @@ pub async fn get_mem(
         _ => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "event_id must be uuid v4" }))),
     }
     let conn = app.db.lock().unwrap();
-    let ev = match crate::storage::db::get_event(&conn, &id) { Ok(v) => v, Err(_) => return (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))) };
+    let ev = match crate::storage::db::get_event(&conn, &id) { Ok(v) if !is_trashed(&conn, &id) => v, _ => return (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))) };
     let blob = match crate::storage::db::get_blob_index(&conn, &ev.content_hash) { Ok(Some((p, r))) => Some(json!({ "path": p, "ref_count": r })), _ => None };
-    let resp = json!({ "event": ev, "blob": blob });
+    let hold = crate::storage::holds::get_hold(&conn, &id).ok().flatten();
+    let resp = json!({ "event": ev, "blob": blob, "hold": hold });
     (StatusCode::OK, Json(resp))
 }
 
+/// `GET /v1/mem/:id/status`: where the event is in the ingest pipeline.
+pub async fn get_mem_status(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    if is_trashed(&conn, &id) { return (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))); }
+    match crate::storage::processing::get_status(&conn, &id) {
+        Ok(Some(status)) => (StatusCode::OK, Json(json!(status))),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `GET /v1/mem/:id/stages`: every pipeline stage run for the event, with outputs and timings.
+pub async fn get_mem_stages(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    if is_trashed(&conn, &id) { return (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))); }
+    match crate::storage::processing::list_stage_runs(&conn, &id) {
+        Ok(runs) => (StatusCode::OK, Json(json!({ "event_id": id, "stages": runs }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Header a client must send (value `true`) to read back `sensitive` content.
+pub const CONFIRM_SENSITIVE_HEADER: &str = "X-Vyaso-Confirm-Sensitive";
+
+/// `GET /v1/mem/:id/content`: the decrypted, decompressed blob, with single-range
+/// `Range: bytes=` support. `never_store` events have no content; `sensitive` ones
+/// need the confirmation header.
+pub async fn get_mem_content(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    use axum::http::header;
+    if let Err(r) = validate_client(&headers) { return r.into_response(); }
+    match uuid::Uuid::parse_str(&id) {
+        Ok(u) if u.get_version() == Some(uuid::Version::Random) => {}
+        _ => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "event_id must be uuid v4" }))).into_response(),
+    }
+    let (ev, blob) = {
+        let conn = app.db.lock().unwrap();
+        let ev = match crate::storage::db::get_event(&conn, &id) { Ok(v) if !is_trashed(&conn, &id) => v, _ => return (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))).into_response() };
+        let blob = crate::storage::db::get_blob_index(&conn, &ev.content_hash).ok().flatten();
+        (ev, blob)
+    };
+    match ev.privacy_flag {
+        PrivacyFlag::NeverStore => return (StatusCode::NOT_FOUND, Json(json!({ "error": "no content stored for never_store events" }))).into_response(),
+        PrivacyFlag::Sensitive => {
+            let confirmed = headers.get(CONFIRM_SENSITIVE_HEADER).and_then(|v| v.to_str().ok()).is_some_and(|v| v.eq_ignore_ascii_case("true"));
+            if !confirmed {
+                return (StatusCode::FORBIDDEN, Json(json!({ "error": "sensitive content requires confirmation", "confirm_header": CONFIRM_SENSITIVE_HEADER }))).into_response();
+            }
+        }
+        PrivacyFlag::Default => {}
+    }
+    let Some((blob_path, _)) = blob else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "no content stored" }))).into_response();
+    };
+    let path = std::path::PathBuf::from(blob_path);
+    if !path.exists() {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "no content stored" }))).into_response();
+    }
+    let bytes = match tokio::task::spawn_blocking(move || crate::storage::blobs::load_blob(&path).map_err(|e| e.to_string())).await {
+        Ok(Ok(b)) => b,
+        Ok(Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e }))).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))).into_response(),
+    };
+    let total = bytes.len() as u64;
+    let content_type = crate::storage::blobs::sniff_content_type(&bytes);
+    let common = [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::CACHE_CONTROL, "no-store".to_string()),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok()).map(|r| parse_byte_range(r, total));
+    match range {
+        None | Some(ByteRange::Ignored) => (StatusCode::OK, common, bytes).into_response(),
+        Some(ByteRange::Unsatisfiable) => {
+            (StatusCode::RANGE_NOT_SATISFIABLE, [(header::CONTENT_RANGE, format!("bytes */{}", total))], Json(json!({ "error": "range not satisfiable" }))).into_response()
+        }
+        Some(ByteRange::Satisfiable(start, end)) => {
+            let part = bytes[start as usize..=end as usize].to_vec();
+            (StatusCode::PARTIAL_CONTENT, common, [(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total))], part).into_response()
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    /// Inclusive `(start, end)` within the content.
+    Satisfiable(u64, u64),
+    Unsatisfiable,
+    /// Malformed, multi-range or non-byte ranges; served as a full 200 per RFC 9110.
+    Ignored,
+}
+
+fn parse_byte_range(header: &str, total: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else { return ByteRange::Ignored };
+    if spec.contains(',') { return ByteRange::Ignored; }
+    let Some((first, last)) = spec.trim().split_once('-') else { return ByteRange::Ignored };
+    let (first, last) = (first.trim(), last.trim());
+    let (start, end) = match (first.is_empty(), last.is_empty()) {
+        // `bytes=-N`: the last N bytes.
+        (true, false) => match last.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (total.saturating_sub(n), total.saturating_sub(1)),
+            Err(_) => return ByteRange::Ignored,
+        },
+        (false, _) => {
+            let Ok(start) = first.parse::<u64>() else { return ByteRange::Ignored };
+            let end = if last.is_empty() { u64::MAX } else {
+                match last.parse::<u64>() { Ok(e) if e >= start => e, _ => return ByteRange::Ignored }
+            };
+            (start, end.min(total.saturating_sub(1)))
+        }
+        (true, true) => return ByteRange::Ignored,
+    };
+    if total == 0 || start >= total { return ByteRange::Unsatisfiable; }
+    ByteRange::Satisfiable(start, end)
+}
+
+/// Above this many matching events a purge runs as a background job unless the
+/// request says otherwise.
+pub const PURGE_SYNC_MAX_EVENTS: u64 = 1000;
+const PURGE_SAMPLE_DEFAULT: u32 = 10;
+const PURGE_SAMPLE_MAX: u32 = 100;
+
 pub async fn purge(
     State(app): State<std::sync::Arc<crate::state::AppState>>,
     headers: HeaderMap,
@@ -121,11 +556,10 @@ pub async fn purge(
             return (StatusCode::BAD_REQUEST, Json(json!({ "error": "end must be RFC3339" })));
         }
     }
-    let privacy_str = req.privacy_flag.as_ref().map(|p| match p {
-        PrivacyFlag::Default => "default".to_string(),
-        PrivacyFlag::Sensitive => "sensitive".to_string(),
-        PrivacyFlag::NeverStore => "never_store".to_string(),
-    });
+    if req.background == Some(true) && !req.skip_trash {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "background requires skip_trash; trashing always runs inline" })));
+    }
+    let privacy_str = req.privacy_flag.as_ref().map(|p| privacy_flag_str(p).to_string());
 
     let criteria = crate::storage::db::PurgeCriteria {
         event_ids: req.event_ids.clone(),
@@ -134,14 +568,972 @@ pub async fn purge(
         app: req.app.clone(),
         source: req.source.clone(),
         privacy_flag: privacy_str,
+        trash: if req.skip_trash { crate::storage::db::TrashScope::All } else { crate::storage::db::TrashScope::Live },
+        include_held: req.include_held,
+        ..Default::default()
+    };
+    if req.dry_run {
+        let limit = req.sample_limit.unwrap_or(PURGE_SAMPLE_DEFAULT).min(PURGE_SAMPLE_MAX);
+        let conn = app.db.lock().unwrap();
+        return match crate::storage::db::purge_preview(&conn, &criteria, limit) {
+            Ok(preview) => {
+                let mut body = json!(preview);
+                body["dry_run"] = json!(true);
+                (StatusCode::OK, Json(body))
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+        };
+    }
+    if !req.skip_trash {
+        let conn = app.db.lock().unwrap();
+        return match crate::storage::trash::trash_events(&conn, &criteria, app.trash.retention, time::OffsetDateTime::now_utc()) {
+            Ok(batch) => (StatusCode::OK, Json(json!(batch))),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+        };
+    }
+    let candidates = {
+        let conn = app.db.lock().unwrap();
+        crate::storage::db::purge_candidates(&conn, &criteria)
+            .and_then(|ids| Ok((ids, crate::storage::db::count_held(&conn, &criteria)?)))
+    };
+    let (candidates, skipped_held) = match candidates {
+        Ok(c) => c,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    };
+    if req.background.unwrap_or(candidates.len() as u64 > PURGE_SYNC_MAX_EVENTS) {
+        let job = crate::purge::PurgeJobs::start(&app, candidates, req.include_held, skipped_held);
+        return (StatusCode::ACCEPTED, Json(json!(job.status())));
+    }
+    match crate::purge::purge(&app, criteria).await {
+        Ok(summary) => (StatusCode::OK, Json(json!(summary))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+pub async fn list_purge_jobs(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    (StatusCode::OK, Json(json!({ "jobs": app.purge_jobs.list() })))
+}
+
+pub async fn get_purge_job(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    match app.purge_jobs.get(&id) {
+        Some(job) => (StatusCode::OK, Json(json!(job.status()))),
+        None => (StatusCode::NOT_FOUND, Json(json!({ "error": "no such purge job" }))),
+    }
+}
+
+pub async fn cancel_purge_job(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let Some(job) = app.purge_jobs.get(&id) else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "no such purge job" })));
+    };
+    if !job.cancel() {
+        return (StatusCode::CONFLICT, Json(json!({ "error": "purge job already finished", "job": job.status() })));
+    }
+    (StatusCode::ACCEPTED, Json(json!(job.status())))
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HoldRequest {
+    /// Why the event is held, e.g. a case or incident reference.
+    pub reason: Option<String>,
+}
+
+/// `POST /v1/mem/:id/hold`: protects the event from purges and retention; the
+/// body is optional. Holding an already held event updates its reason.
+pub async fn set_hold(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    body: axum::body::Bytes,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let req: HoldRequest = if body.iter().all(|b| b.is_ascii_whitespace()) {
+        HoldRequest::default()
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(r) => r,
+            Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))),
+        }
+    };
+    let conn = app.db.lock().unwrap();
+    if crate::storage::db::get_event(&conn, &id).is_err() || is_trashed(&conn, &id) {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" })));
+    }
+    match crate::storage::holds::set_hold(&conn, &id, req.reason.as_deref()) {
+        Ok(hold) => (StatusCode::OK, Json(json!(hold))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `DELETE /v1/mem/:id/hold`: the event can be purged and expired again.
+pub async fn clear_hold(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    match crate::storage::holds::clear_hold(&conn, &id) {
+        Ok(true) => (StatusCode::OK, Json(json!({ "event_id": id, "held": false }))),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({ "error": "event is not held" }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `GET /v1/holds`: every held event, most recently held first.
+pub async fn list_holds(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    match crate::storage::holds::list_holds(&conn) {
+        Ok(holds) => (StatusCode::OK, Json(json!({ "holds": holds }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+const TRASH_DEFAULT_LIMIT: u32 = 50;
+const TRASH_MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TrashParams {
+    /// Only the events trashed by this purge.
+    pub trash_id: Option<String>,
+    /// `id` of the last item on the previous page.
+    pub before: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// `GET /v1/trash`: trashed events, most recently trashed first.
+pub async fn list_trash(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<TrashParams>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let limit = params.limit.unwrap_or(TRASH_DEFAULT_LIMIT).clamp(1, TRASH_MAX_LIMIT);
+    let conn = app.db.lock().unwrap();
+    match crate::storage::trash::list(&conn, params.trash_id.as_deref(), params.before, limit) {
+        Ok(items) => {
+            let next_before = if items.len() == limit as usize { items.last().map(|i| i.id) } else { None };
+            (StatusCode::OK, Json(json!({ "items": items, "next_before": next_before })))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RestoreRequest {
+    /// Restores everything one purge trashed.
+    pub trash_id: Option<String>,
+    pub event_ids: Option<Vec<String>>,
+}
+
+/// `POST /v1/trash/restore`: takes events out of the trash, by purge and/or id.
+pub async fn restore_trash(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<RestoreRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    if req.trash_id.is_none() && req.event_ids.is_none() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "trash_id or event_ids required" })));
+    }
+    let conn = app.db.lock().unwrap();
+    match crate::storage::trash::restore(&conn, req.trash_id.as_deref(), req.event_ids.as_deref()) {
+        Ok(ids) => (StatusCode::OK, Json(json!({ "restored": ids.len(), "event_ids": ids }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `POST /v1/trash/sweep`: purges expired trash now instead of at the next scheduled sweep.
+pub async fn sweep_trash(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    match crate::purge::sweep_trash(&app).await {
+        Ok(summary) => (StatusCode::OK, Json(json!(summary))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Body of `POST /v1/retention/rules` and `PUT /v1/retention/rules/:id`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RetentionRuleRequest {
+    pub name: Option<String>,
+    pub app: Option<String>,
+    pub source: Option<String>,
+    pub tag: Option<String>,
+    pub privacy_flag: Option<PrivacyFlag>,
+    /// Days to keep matching events; omitted or null keeps them forever.
+    pub keep_days: Option<u32>,
+    pub enabled: Option<bool>,
+}
+
+impl RetentionRuleRequest {
+    fn into_spec(self) -> Result<crate::storage::retention::RuleSpec, (StatusCode, Json<Value>)> {
+        let bad = |e: &str| (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+        for (name, value) in [("app", &self.app), ("source", &self.source), ("tag", &self.tag)] {
+            if value.as_ref().is_some_and(|v| v.trim().is_empty()) { return Err(bad(&format!("{} must be non-empty", name))); }
+        }
+        // Deleting right away is what purge is for.
+        if self.keep_days == Some(0) { return Err(bad("keep_days must be at least 1")); }
+        Ok(crate::storage::retention::RuleSpec {
+            name: self.name,
+            app: self.app,
+            source: self.source,
+            tag: self.tag,
+            privacy_flag: self.privacy_flag.as_ref().map(|p| privacy_flag_str(p).to_string()),
+            keep_days: self.keep_days,
+            enabled: self.enabled.unwrap_or(true),
+        })
+    }
+}
+
+/// `GET /v1/retention/rules`.
+pub async fn list_retention_rules(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    match crate::storage::retention::list_rules(&conn) {
+        Ok(rules) => (StatusCode::OK, Json(json!({ "rules": rules }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `POST /v1/retention/rules`: the rule applies from the next sweep.
+pub async fn create_retention_rule(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<RetentionRuleRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let spec = match req.into_spec() { Ok(s) => s, Err(r) => return r };
+    let conn = app.db.lock().unwrap();
+    match crate::storage::retention::create_rule(&conn, &spec) {
+        Ok(rule) => (StatusCode::CREATED, Json(json!(rule))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `GET /v1/retention/rules/:id`.
+pub async fn get_retention_rule(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    match crate::storage::retention::get_rule(&conn, &id) {
+        Ok(Some(rule)) => (StatusCode::OK, Json(json!(rule))),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({ "error": "no such retention rule" }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `PUT /v1/retention/rules/:id`: replaces the rule; omitted fields are cleared.
+pub async fn update_retention_rule(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(req): Json<RetentionRuleRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let spec = match req.into_spec() { Ok(s) => s, Err(r) => return r };
+    let conn = app.db.lock().unwrap();
+    match crate::storage::retention::update_rule(&conn, &id, &spec) {
+        Ok(Some(rule)) => (StatusCode::OK, Json(json!(rule))),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!({ "error": "no such retention rule" }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `DELETE /v1/retention/rules/:id`: past runs of the rule stay in the report.
+pub async fn delete_retention_rule(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let conn = app.db.lock().unwrap();
+    match crate::storage::retention::delete_rule(&conn, &id) {
+        Ok(true) => (StatusCode::OK, Json(json!({ "rule_id": id, "deleted": true }))),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({ "error": "no such retention rule" }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// `POST /v1/retention/run`: sweeps now instead of at the next scheduled sweep.
+pub async fn run_retention(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    match crate::retention::sweep(&app).await {
+        Ok(report) => (StatusCode::OK, Json(json!(report))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+const RETENTION_RUNS_DEFAULT_LIMIT: u32 = 50;
+const RETENTION_RUNS_MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RetentionRunsParams {
+    pub rule_id: Option<String>,
+    /// `id` of the last run on the previous page.
+    pub before: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// `GET /v1/retention/runs`: what each rule deleted on each sweep, newest first.
+pub async fn list_retention_runs(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<RetentionRunsParams>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let limit = params.limit.unwrap_or(RETENTION_RUNS_DEFAULT_LIMIT).clamp(1, RETENTION_RUNS_MAX_LIMIT);
+    let conn = app.db.lock().unwrap();
+    match crate::storage::retention::list_runs(&conn, params.rule_id.as_deref(), params.before, limit) {
+        Ok(runs) => {
+            let next_before = if runs.len() == limit as usize { runs.last().map(|r| r.id) } else { None };
+            (StatusCode::OK, Json(json!({ "runs": runs, "next_before": next_before })))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+const TIMELINE_DEFAULT_LIMIT: u32 = 50;
+const TIMELINE_MAX_LIMIT: u32 = 200;
+const TITLE_MAX_CHARS: usize = 80;
+const SNIPPET_MAX_CHARS: usize = 240;
+/// Decoded bytes a preview is taken from; plenty for a title and a snippet.
+const PREVIEW_MAX_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TimelineParams {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub app: Option<String>,
+    pub source: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// Timeline projection of an event; mirrors the app's `Memory` type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineItem {
+    pub id: String,
+    pub title: String,
+    pub snippet: String,
+    pub timestamp: String,
+    pub source: String,
+    pub metadata: Value,
+}
+
+pub async fn timeline(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<TimelineParams>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    for (name, value) in [("start", &params.start), ("end", &params.end)] {
+        if let Err(r) = check_rfc3339(name, value) { return r; }
+    }
+    let before = match params.cursor.as_deref() {
+        Some(c) => match decode_cursor(c) {
+            Some(k) => Some(k),
+            None => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid cursor" }))),
+        },
+        None => None,
+    };
+    let limit = params.limit.unwrap_or(TIMELINE_DEFAULT_LIMIT).clamp(1, TIMELINE_MAX_LIMIT);
+    let q = crate::storage::db::TimelineQuery {
+        start: params.start.clone(),
+        end: params.end.clone(),
+        app: params.app.clone(),
+        source: params.source.clone(),
+        tag: params.tag.clone(),
+        before,
+        // Fetch one extra row to learn whether another page exists.
+        limit: limit + 1,
+    };
+    // Resolve blob paths under the lock; decrypt previews after releasing it.
+    let (mut events, mut paths) = {
+        let conn = app.db.lock().unwrap();
+        let events = match crate::storage::db::query_timeline(&conn, &q) {
+            Ok(v) => v,
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+        };
+        let paths: Vec<Option<String>> = events.iter()
+            .map(|ev| crate::storage::db::get_blob_index(&conn, &ev.content_hash).ok().flatten().map(|(p, _)| p))
+            .collect();
+        (events, paths)
+    };
+    let next_cursor = if events.len() > limit as usize {
+        events.truncate(limit as usize);
+        paths.truncate(limit as usize);
+        events.last().map(|ev| encode_cursor(&ev.timestamp, &ev.event_id))
+    } else {
+        None
+    };
+    // Decrypting previews is blocking work.
+    let items = tokio::task::spawn_blocking(move || {
+        events.into_iter().zip(paths).map(|(ev, path)| timeline_item(ev, path)).collect::<Vec<TimelineItem>>()
+    }).await;
+    let items = match items {
+        Ok(items) => items,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    };
+    (StatusCode::OK, Json(json!({ "items": items, "next_cursor": next_cursor })))
+}
+
+fn timeline_item(ev: EventEnvelope, blob_path: Option<String>) -> TimelineItem {
+    // Only default-privacy events get a content preview; sensitive content stays in the blob store.
+    let text = match (&ev.privacy_flag, blob_path) {
+        (PrivacyFlag::Default, Some(p)) => crate::storage::blobs::load_text_prefix(std::path::Path::new(&p), PREVIEW_MAX_BYTES).ok().flatten(),
+        _ => None,
+    };
+    let title = text.as_deref()
+        .and_then(|t| t.lines().map(str::trim).find(|l| !l.is_empty()))
+        .map(|l| truncate_chars(l, TITLE_MAX_CHARS))
+        .unwrap_or_else(|| ev.app.clone());
+    let snippet = text.as_deref()
+        .map(|t| truncate_chars(&t.split_whitespace().collect::<Vec<_>>().join(" "), SNIPPET_MAX_CHARS))
+        .unwrap_or_default();
+    TimelineItem {
+        id: ev.event_id,
+        title,
+        snippet,
+        timestamp: ev.timestamp,
+        source: ev.source,
+        metadata: json!({ "app": ev.app, "tags": ev.tags, "privacy_flag": ev.privacy_flag, "size_bytes": ev.size_bytes }),
+    }
+}
+
+fn truncate_chars(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((i, _)) => format!("{}…", &s[..i]),
+        None => s.to_string(),
+    }
+}
+
+fn encode_cursor(timestamp: &str, event_id: &str) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(format!("{}\n{}", timestamp, event_id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(String, String)> {
+    let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    let s = String::from_utf8(raw).ok()?;
+    let (ts, id) = s.split_once('\n')?;
+    Some((ts.to_string(), id.to_string()))
+}
+
+const SEARCH_DEFAULT_LIMIT: u32 = 20;
+const SEARCH_MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SearchFilters {
+    pub source: Option<String>,
+    pub app: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub filters: Option<SearchFilters>,
+    pub limit: Option<u32>,
+    /// `hybrid` (default), `lexical` or `vector`.
+    pub mode: Option<crate::retrieval::Mode>,
+    pub recency: Option<crate::retrieval::RecencyParams>,
+}
+
+/// Runs a hybrid retrieval for a search or RAG request and shapes failures as responses.
+async fn retrieve_hits(
+    app: &std::sync::Arc<crate::state::AppState>,
+    q: &crate::storage::fts::SearchQuery,
+    mode: Option<crate::retrieval::Mode>,
+    recency: Option<&crate::retrieval::RecencyParams>,
+) -> Result<crate::retrieval::Retrieval, (StatusCode, Json<Value>)> {
+    let config = app.retrieval.with(recency).map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))))?;
+    crate::retrieval::retrieve(app, q, mode.unwrap_or_default(), &config).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))))
+}
+
+pub async fn search_memories(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<SearchRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    if crate::storage::fts::to_match_expr(&req.query).is_none() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "query must be non-empty" })));
+    }
+    let filters = req.filters.unwrap_or_default();
+    for (name, value) in [("start", &filters.start), ("end", &filters.end)] {
+        if let Err(r) = check_rfc3339(name, value) { return r; }
+    }
+    let q = crate::storage::fts::SearchQuery {
+        query: req.query.clone(),
+        app: filters.app,
+        // The app sends "all" to mean no source filter.
+        source: filters.source.filter(|s| s != "all"),
+        start: filters.start,
+        end: filters.end,
+        limit: req.limit.unwrap_or(SEARCH_DEFAULT_LIMIT).clamp(1, SEARCH_MAX_LIMIT),
+        any_term: false,
     };
-    let mut conn = app.db.lock().unwrap();
-    match crate::storage::db::purge_events(&mut conn, criteria) {
-        Ok((de, db)) => (StatusCode::OK, Json(json!({ "deleted_events": de, "deleted_blobs": db }))),
+    let mode = req.mode.unwrap_or_default();
+    match retrieve_hits(&app, &q, Some(mode), req.recency.as_ref()).await {
+        Ok(r) => {
+            let mut body = json!({ "query": req.query, "mode": mode, "hits": r.hits });
+            if let Some(e) = r.vector_error { body["vector_error"] = json!(e); }
+            (StatusCode::OK, Json(body))
+        }
+        Err(r) => r,
+    }
+}
+
+const SIMILAR_DEFAULT_LIMIT: u32 = 10;
+const SIMILAR_MAX_LIMIT: u32 = 50;
+
+/// `GET /v1/index/status`: size, dimension and embedding model of the vector index.
+pub async fn index_status(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    (StatusCode::OK, Json(json!(app.vectors.status())))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VectorSearchRequest {
+    pub vector: Vec<f32>,
+    pub limit: Option<u32>,
+}
+
+/// `POST /v1/index/search`: chunks nearest to a caller-supplied embedding.
+pub async fn vector_search(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<VectorSearchRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let status = app.vectors.status();
+    if status.dimension.is_some_and(|d| d != req.vector.len()) {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": format!("vector must have {} dimensions", status.dimension.unwrap_or_default()) })));
+    }
+    let limit = req.limit.unwrap_or(SEARCH_DEFAULT_LIMIT).clamp(1, SEARCH_MAX_LIMIT) as usize;
+    let mut hits = app.vectors.search(&req.vector, limit);
+    let ids: Vec<String> = hits.iter().map(|h| h.event_id.clone()).collect();
+    let trashed = crate::storage::trash::trashed_among(&app.db.lock().unwrap(), &ids).unwrap_or_default();
+    hits.retain(|h| !trashed.contains(&h.event_id));
+    (StatusCode::OK, Json(json!({ "model_version": status.model_version, "hits": hits })))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SimilarParams {
+    pub limit: Option<u32>,
+}
+
+/// `GET /v1/mem/:id/similar`: other memories with the chunks nearest to this one's,
+/// best chunk per memory.
+pub async fn similar_memories(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(params): Query<SimilarParams>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let own = app.vectors.vectors_for_event(&id);
+    if own.is_empty() || is_trashed(&app.db.lock().unwrap(), &id) {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "event has no vectors" })));
+    }
+    let limit = params.limit.unwrap_or(SIMILAR_DEFAULT_LIMIT).clamp(1, SIMILAR_MAX_LIMIT) as usize;
+    let mut best: std::collections::HashMap<String, crate::index::VectorHit> = std::collections::HashMap::new();
+    for (_, vector) in &own {
+        // The event's own chunks come back first; ask for enough to get past them.
+        for hit in app.vectors.search(vector, limit + own.len()) {
+            if hit.event_id == id { continue; }
+            if best.get(&hit.event_id).is_none_or(|b| hit.score > b.score) {
+                best.insert(hit.event_id.clone(), hit);
+            }
+        }
+    }
+    let ids: Vec<String> = best.keys().cloned().collect();
+    let trashed = crate::storage::trash::trashed_among(&app.db.lock().unwrap(), &ids).unwrap_or_default();
+    let mut similar: Vec<_> = best.into_values().filter(|h| !trashed.contains(&h.event_id)).collect();
+    similar.sort_by(|a, b| b.score.total_cmp(&a.score));
+    similar.truncate(limit);
+    (StatusCode::OK, Json(json!({ "event_id": id, "similar": similar })))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RagRequest {
+    pub query: String,
+    pub top_k: Option<u32>,
+    pub max_tokens: Option<usize>,
+    pub filters: Option<SearchFilters>,
+    pub mode: Option<crate::retrieval::Mode>,
+    pub recency: Option<crate::retrieval::RecencyParams>,
+}
+
+pub async fn rag_query(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<RagRequest>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    if crate::storage::fts::to_match_expr(&req.query).is_none() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "query must be non-empty" })));
+    }
+    let filters = req.filters.unwrap_or_default();
+    for (name, value) in [("start", &filters.start), ("end", &filters.end)] {
+        if let Err(r) = check_rfc3339(name, value) { return r; }
+    }
+    let q = crate::storage::fts::SearchQuery {
+        query: req.query.clone(),
+        app: filters.app,
+        source: filters.source.filter(|s| s != "all"),
+        start: filters.start,
+        end: filters.end,
+        limit: req.top_k.unwrap_or(crate::rag::DEFAULT_TOP_K).clamp(1, SEARCH_MAX_LIMIT),
+        // Questions are phrased in natural language; any shared term is a candidate.
+        any_term: true,
+    };
+    let hits = match retrieve_hits(&app, &q, req.mode, req.recency.as_ref()).await {
+        Ok(r) => r.hits,
+        Err(r) => return r,
+    };
+    let max_tokens = req.max_tokens.unwrap_or(crate::rag::DEFAULT_MAX_TOKENS);
+    match crate::rag::answer(app.llm.as_ref(), &req.query, hits, max_tokens).await {
+        Ok(answer) => (StatusCode::OK, Json(json!(answer))),
+        Err(e) => (StatusCode::BAD_GATEWAY, Json(json!({ "error": e.to_string(), "backend": app.llm.name() }))),
+    }
+}
+
+/// Body of `/v1/pause` and `/v1/resume`; both accept an empty body (global scope).
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CaptureRequest {
+    pub app: Option<String>,
+    pub source: Option<String>,
+    /// RFC3339 time at which a pause lapses.
+    pub until: Option<String>,
+    /// Alternative to `until`: pause for this many seconds.
+    pub duration_secs: Option<u64>,
+}
+
+impl CaptureRequest {
+    fn scope(&self) -> Result<(crate::capture::Scope, String), String> {
+        match (self.app.as_deref(), self.source.as_deref()) {
+            (Some(_), Some(_)) => Err("specify at most one of app or source".to_string()),
+            (Some(a), None) if !a.trim().is_empty() => Ok((crate::capture::Scope::App, a.to_string())),
+            (None, Some(s)) if !s.trim().is_empty() => Ok((crate::capture::Scope::Source, s.to_string())),
+            (None, None) => Ok((crate::capture::Scope::Global, String::new())),
+            _ => Err("app/source must be non-empty".to_string()),
+        }
+    }
+}
+
+fn parse_capture_body(body: &[u8]) -> Result<CaptureRequest, (StatusCode, Json<Value>)> {
+    if body.iter().all(|b| b.is_ascii_whitespace()) { return Ok(CaptureRequest::default()); }
+    serde_json::from_slice(body).map_err(|e| (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))))
+}
+
+pub async fn pause_capture(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let req = match parse_capture_body(&body) { Ok(r) => r, Err(r) => return r };
+    let (scope, value) = match req.scope() { Ok(s) => s, Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))) };
+    let now = time::OffsetDateTime::now_utc();
+    let until = match (req.until.as_deref(), req.duration_secs) {
+        (Some(_), Some(_)) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "specify at most one of until or duration_secs" }))),
+        (Some(u), None) => match time::OffsetDateTime::parse(u, &time::format_description::well_known::Rfc3339) {
+            Ok(t) if t > now => Some(t),
+            Ok(_) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "until must be in the future" }))),
+            Err(_) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": "until must be RFC3339" }))),
+        },
+        (None, Some(secs)) => Some(now + time::Duration::seconds(secs.min(i64::MAX as u64) as i64)),
+        (None, None) => None,
+    };
+    let conn = app.db.lock().unwrap();
+    let mut capture = app.capture.lock().unwrap();
+    if let Err(e) = capture.pause(&conn, scope, &value, until) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() })));
+    }
+    (StatusCode::OK, Json(json!(capture.status(now))))
+}
+
+pub async fn resume_capture(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let req = match parse_capture_body(&body) { Ok(r) => r, Err(r) => return r };
+    let (scope, value) = match req.scope() { Ok(s) => s, Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e }))) };
+    let conn = app.db.lock().unwrap();
+    let mut capture = app.capture.lock().unwrap();
+    if let Err(e) = capture.resume(&conn, scope, &value) {
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() })));
+    }
+    (StatusCode::OK, Json(json!(capture.status(time::OffsetDateTime::now_utc()))))
+}
+
+pub async fn capture_status(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let status = app.capture.lock().unwrap().status(time::OffsetDateTime::now_utc());
+    (StatusCode::OK, Json(json!(status)))
+}
+
+/// `GET /v1/queue/status`: depth, drain rate and admission counters of the ingest queue.
+pub async fn queue_status(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    (StatusCode::OK, Json(json!(app.queue_stats.status(&app))))
+}
+
+/// `GET /v1/intel/status`: the intelligence worker pool.
+pub async fn intel_status(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    (StatusCode::OK, Json(json!({ "workers": app.intel.status() })))
+}
+
+const INGEST_ERRORS_DEFAULT_LIMIT: u32 = 50;
+const INGEST_ERRORS_MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct IngestErrorsParams {
+    pub event_id: Option<String>,
+    /// `id` of the last error on the previous page.
+    pub before: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// `GET /v1/ingest/errors`: events rejected during ingestion, newest first.
+pub async fn ingest_errors(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<IngestErrorsParams>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let limit = params.limit.unwrap_or(INGEST_ERRORS_DEFAULT_LIMIT).clamp(1, INGEST_ERRORS_MAX_LIMIT);
+    let conn = app.db.lock().unwrap();
+    match crate::storage::db::list_ingest_errors(&conn, params.event_id.as_deref(), params.before, limit) {
+        Ok(errors) => {
+            let next_before = if errors.len() == limit as usize { errors.last().map(|e| e.id) } else { None };
+            (StatusCode::OK, Json(json!({ "errors": errors, "next_before": next_before })))
+        }
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
     }
 }
 
+const DEAD_LETTERS_DEFAULT_LIMIT: u32 = 50;
+const DEAD_LETTERS_MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeadLettersParams {
+    /// `id` of the last dead letter on the previous page.
+    pub before: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// `GET /v1/dead-letters`: events whose intel job failed, newest first.
+pub async fn list_dead_letters(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<DeadLettersParams>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let limit = params.limit.unwrap_or(DEAD_LETTERS_DEFAULT_LIMIT).clamp(1, DEAD_LETTERS_MAX_LIMIT);
+    let conn = app.db.lock().unwrap();
+    match crate::storage::processing::list_dead_letters(&conn, params.before, limit) {
+        Ok(letters) => {
+            let next_before = if letters.len() == limit as usize { letters.last().map(|l| l.id) } else { None };
+            (StatusCode::OK, Json(json!({ "dead_letters": letters, "next_before": next_before })))
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RetryRequest {
+    /// Dead letters to retry; all of them when omitted.
+    pub event_ids: Option<Vec<String>>,
+}
+
+/// `POST /v1/dead-letters/retry`: re-runs intel for the given dead letters, or all.
+pub async fn retry_dead_letters(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let req: RetryRequest = if body.iter().all(|b| b.is_ascii_whitespace()) {
+        RetryRequest::default()
+    } else {
+        match serde_json::from_slice(&body) {
+            Ok(r) => r,
+            Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({ "error": e.to_string() }))),
+        }
+    };
+    let ids = {
+        let conn = app.db.lock().unwrap();
+        match crate::storage::processing::dead_letter_ids(&conn) {
+            // Only ids that are actually dead-lettered are retried.
+            Ok(all) => match req.event_ids {
+                Some(wanted) => all.into_iter().filter(|id| wanted.contains(id)).collect(),
+                None => all,
+            },
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+        }
+    };
+    start_retry(&app, ids)
+}
+
+/// `POST /v1/dead-letters/:event_id/retry`.
+pub async fn retry_dead_letter(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Path(event_id): Path<String>,
+) -> (StatusCode, Json<Value>) {
+    if let Err((code, body)) = validate_client(&headers) { return (code, body); }
+    let known = {
+        let conn = app.db.lock().unwrap();
+        crate::storage::processing::dead_letter_ids(&conn).map(|ids| ids.contains(&event_id))
+    };
+    match known {
+        Ok(true) => start_retry(&app, vec![event_id]),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!({ "error": "not_found" }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
+
+/// Marks the events queued and hands them to `queue::reprocess`; the dead
+/// letter stays until a retry succeeds.
+fn start_retry(app: &std::sync::Arc<crate::state::AppState>, ids: Vec<String>) -> (StatusCode, Json<Value>) {
+    {
+        let conn = app.db.lock().unwrap();
+        for id in &ids {
+            if let Err(e) = crate::storage::processing::set_state(&conn, id, crate::storage::processing::ProcessingState::Queued, None) {
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() })));
+            }
+        }
+    }
+    let n = ids.len();
+    if n > 0 { crate::queue::reprocess(app.clone(), ids.clone()); }
+    (StatusCode::ACCEPTED, Json(json!({ "retrying": n, "event_ids": ids })))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FeedParams {
+    pub app: Option<String>,
+    pub source: Option<String>,
+    /// Comma-separated; matches events carrying any of them.
+    pub tags: Option<String>,
+    /// Resume point for clients that can't send `Last-Event-ID` (e.g. WebSocket).
+    pub last_event_id: Option<String>,
+}
+
+impl FeedParams {
+    fn filter(&self) -> crate::feed::FeedFilter {
+        crate::feed::FeedFilter {
+            app: self.app.clone(),
+            source: self.source.clone(),
+            tags: self.tags.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect(),
+        }
+    }
+}
+
+/// `GET /v1/feed`: Server-Sent Events, one per feed notice. `Last-Event-ID`
+/// (or `last_event_id`) replays what the client missed.
+pub async fn feed_sse(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<FeedParams>,
+) -> axum::response::Response {
+    use axum::response::{IntoResponse, sse::{Event, KeepAlive, Sse}};
+    use futures_util::StreamExt;
+    if let Err(r) = validate_client(&headers) { return r.into_response(); }
+    let last = headers.get("Last-Event-ID").and_then(|v| v.to_str().ok()).map(str::to_string).or(params.last_event_id.clone());
+    let stream = app.feed.stream(last.as_deref(), params.filter()).map(|ev| {
+        Event::default().id(ev.id.clone()).event(ev.kind.as_str()).json_data(&ev)
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// `GET /v1/feed/ws`: the same notices as `/v1/feed`, as JSON text frames.
+pub async fn feed_ws(
+    State(app): State<std::sync::Arc<crate::state::AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<FeedParams>,
+    ws: axum::extract::ws::WebSocketUpgrade,
+) -> axum::response::Response {
+    use axum::extract::ws::Message;
+    use axum::response::IntoResponse;
+    use futures_util::StreamExt;
+    if let Err(r) = validate_client(&headers) { return r.into_response(); }
+    let stream = app.feed.stream(params.last_event_id.as_deref(), params.filter());
+    ws.on_upgrade(move |mut socket| async move {
+        let mut stream = std::pin::pin!(stream);
+        loop {
+            tokio::select! {
+                next = stream.next() => {
+                    let Some(ev) = next else { let _ = socket.send(Message::Close(None)).await; break };
+                    let Ok(text) = serde_json::to_string(&ev) else { continue };
+                    if socket.send(Message::Text(text)).await.is_err() { break; }
+                }
+                msg = socket.recv() => match msg {
+                    // Clients only listen; anything but a close is ignored.
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    })
+}
+
+fn check_rfc3339(name: &str, value: &Option<String>) -> Result<(), (StatusCode, Json<Value>)> {
+    match value {
+        Some(v) if time::OffsetDateTime::parse(v, &time::format_description::well_known::Rfc3339).is_err() => {
+            Err((StatusCode::BAD_REQUEST, Json(json!({ "error": format!("{} must be RFC3339", name) }))))
+        }
+        _ => Ok(()),
+    }
+}
+
 fn validate_client(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
     const ALLOWED: [&str; 3] = ["browser-extension", "vscode", "desktop-app"];
     match headers.get("X-Vyaso-Local-Client").and_then(|v| v.to_str().ok()) {
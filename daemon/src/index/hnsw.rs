@@ -0,0 +1,242 @@
+//! Hierarchical navigable small world graph (Malkov & Yashunin) over
+//! L2-normalised vectors, so distance is `1 - cosine`.
+//!
+//! Removal marks a node deleted: it keeps routing searches but is never
+//! returned. `compact` rebuilds the graph from the live nodes.
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use rand::Rng;
+
+use crate::storage::Result;
+
+pub const DEFAULT_M: usize = 16;
+pub const DEFAULT_EF_CONSTRUCTION: usize = 200;
+pub const DEFAULT_EF_SEARCH: usize = 64;
+
+pub struct Node {
+    pub chunk_id: String,
+    pub event_id: String,
+    pub vector: Vec<f32>,
+    /// Neighbour lists, one per layer from 0 up to the node's level.
+    pub neighbors: Vec<Vec<u32>>,
+    pub deleted: bool,
+}
+
+impl Node {
+    pub fn level(&self) -> usize {
+        self.neighbors.len() - 1
+    }
+}
+
+/// Orders candidates by distance; NaN never occurs for normalised vectors.
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate(f32, u32);
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+pub struct Hnsw {
+    pub dim: usize,
+    pub m: usize,
+    pub ef_construction: usize,
+    pub nodes: Vec<Node>,
+    pub entry: Option<u32>,
+    /// Live nodes by chunk id.
+    by_chunk: HashMap<String, u32>,
+}
+
+pub fn normalize(v: &[f32]) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 { return v.to_vec(); }
+    v.iter().map(|x| x / norm).collect()
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+impl Hnsw {
+    pub fn new(dim: usize) -> Self {
+        Self { dim, m: DEFAULT_M, ef_construction: DEFAULT_EF_CONSTRUCTION, nodes: Vec::new(), entry: None, by_chunk: HashMap::new() }
+    }
+
+    /// Rebuilds lookups for nodes loaded from disk.
+    pub fn from_parts(dim: usize, m: usize, ef_construction: usize, nodes: Vec<Node>, entry: Option<u32>) -> Self {
+        let by_chunk = nodes.iter().enumerate().filter(|(_, n)| !n.deleted).map(|(i, n)| (n.chunk_id.clone(), i as u32)).collect();
+        Self { dim, m, ef_construction, nodes, entry, by_chunk }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_chunk.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_chunk.is_empty()
+    }
+
+    pub fn deleted(&self) -> usize {
+        self.nodes.len() - self.by_chunk.len()
+    }
+
+    pub fn get(&self, chunk_id: &str) -> Option<&Node> {
+        self.by_chunk.get(chunk_id).map(|&i| &self.nodes[i as usize])
+    }
+
+    pub fn live(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(|n| !n.deleted)
+    }
+
+    fn max_neighbors(&self, layer: usize) -> usize {
+        if layer == 0 { self.m * 2 } else { self.m }
+    }
+
+    fn random_level(&self) -> usize {
+        let ml = 1.0 / (self.m as f64).ln();
+        let u: f64 = rand::thread_rng().gen_range(f64::EPSILON..1.0);
+        (-u.ln() * ml).floor() as usize
+    }
+
+    /// Inserts a vector, replacing any live entry for the same chunk.
+    pub fn insert(&mut self, chunk_id: &str, event_id: &str, vector: &[f32]) -> Result<()> {
+        if vector.len() != self.dim {
+            return Err(format!("vector has {} dimensions; the index holds {}", vector.len(), self.dim).into());
+        }
+        self.remove(chunk_id);
+        let vector = normalize(vector);
+        let id = self.nodes.len() as u32;
+        let level = self.random_level();
+        self.nodes.push(Node { chunk_id: chunk_id.to_string(), event_id: event_id.to_string(), vector, neighbors: vec![Vec::new(); level + 1], deleted: false });
+        self.by_chunk.insert(chunk_id.to_string(), id);
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            return Ok(());
+        };
+        let top = self.nodes[entry as usize].level();
+        let query = self.nodes[id as usize].vector.clone();
+        let mut eps = vec![entry];
+        for layer in (level + 1..=top).rev() {
+            eps = vec![self.search_layer(&query, &eps, 1, layer)[0].1];
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &eps, self.ef_construction, layer);
+            let selected = self.select_neighbors(&found, self.m);
+            for &n in &selected {
+                self.link(n, id, layer);
+            }
+            self.nodes[id as usize].neighbors[layer] = selected;
+            eps = found.iter().map(|c| c.1).collect();
+        }
+        if level > top { self.entry = Some(id); }
+        Ok(())
+    }
+
+    /// Adds `to` to the neighbours of `from`, pruning the list when it is full.
+    fn link(&mut self, from: u32, to: u32, layer: usize) {
+        let cap = self.max_neighbors(layer);
+        let list = &mut self.nodes[from as usize].neighbors[layer];
+        list.push(to);
+        if list.len() <= cap { return; }
+        let base = &self.nodes[from as usize].vector;
+        let mut candidates: Vec<Candidate> = self.nodes[from as usize].neighbors[layer].iter()
+            .map(|&n| Candidate(distance(base, &self.nodes[n as usize].vector), n))
+            .collect();
+        candidates.sort();
+        let pruned = self.select_neighbors(&candidates, cap);
+        self.nodes[from as usize].neighbors[layer] = pruned;
+    }
+
+    /// The neighbour-selection heuristic: keep a candidate only if it is closer
+    /// to the base than to every neighbour kept so far, then top up with the
+    /// closest discarded ones. `candidates` is sorted by distance.
+    fn select_neighbors(&self, candidates: &[Candidate], m: usize) -> Vec<u32> {
+        let mut kept: Vec<u32> = Vec::with_capacity(m);
+        let mut discarded = Vec::new();
+        for &Candidate(d, c) in candidates {
+            if kept.len() == m { break; }
+            let v = &self.nodes[c as usize].vector;
+            if kept.iter().all(|&k| distance(v, &self.nodes[k as usize].vector) > d) {
+                kept.push(c);
+            } else {
+                discarded.push(c);
+            }
+        }
+        kept.extend(discarded.into_iter().take(m.saturating_sub(kept.len())));
+        kept
+    }
+
+    /// Best-first search of one layer; returns up to `ef` nodes sorted by distance.
+    fn search_layer(&self, query: &[f32], eps: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = eps.iter().copied().collect();
+        let mut frontier: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut best: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &e in eps {
+            let c = Candidate(distance(query, &self.nodes[e as usize].vector), e);
+            frontier.push(Reverse(c));
+            best.push(c);
+        }
+        while let Some(Reverse(current)) = frontier.pop() {
+            if best.len() >= ef && best.peek().is_some_and(|worst| current.0 > worst.0) { break; }
+            for &n in self.nodes[current.1 as usize].neighbors.get(layer).map(Vec::as_slice).unwrap_or_default() {
+                if !visited.insert(n) { continue; }
+                let c = Candidate(distance(query, &self.nodes[n as usize].vector), n);
+                if best.len() < ef || best.peek().is_some_and(|worst| c.0 < worst.0) {
+                    frontier.push(Reverse(c));
+                    best.push(c);
+                    if best.len() > ef { best.pop(); }
+                }
+            }
+        }
+        best.into_sorted_vec()
+    }
+
+    /// The `k` nearest live nodes as `(node, cosine similarity)`, best first.
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(&Node, f32)> {
+        let Some(entry) = self.entry else { return Vec::new() };
+        if query.len() != self.dim || k == 0 { return Vec::new(); }
+        let query = normalize(query);
+        let mut ep = entry;
+        for layer in (1..=self.nodes[entry as usize].level()).rev() {
+            ep = self.search_layer(&query, &[ep], 1, layer)[0].1;
+        }
+        // Deleted nodes take up room in the candidate list, so widen it by as many.
+        let ef = ef.max(k) + self.deleted().min(ef.max(k));
+        self.search_layer(&query, &[ep], ef, 0).into_iter()
+            .map(|c| &self.nodes[c.1 as usize])
+            .filter(|n| !n.deleted)
+            .take(k)
+            .map(|n| (n, 1.0 - distance(&query, &n.vector)))
+            .collect()
+    }
+
+    pub fn remove(&mut self, chunk_id: &str) -> bool {
+        match self.by_chunk.remove(chunk_id) {
+            Some(id) => {
+                self.nodes[id as usize].deleted = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Rebuilds the graph without deleted nodes.
+    pub fn compact(&mut self) {
+        let mut fresh = Hnsw { dim: self.dim, m: self.m, ef_construction: self.ef_construction, nodes: Vec::new(), entry: None, by_chunk: HashMap::new() };
+        for n in self.nodes.drain(..).filter(|n| !n.deleted) {
+            // Dimensions already match, so this can't fail.
+            let _ = fresh.insert(&n.chunk_id, &n.event_id, &n.vector);
+        }
+        *self = fresh;
+    }
+}
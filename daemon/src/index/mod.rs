@@ -1,5 +1,355 @@
-use tracing::info;
+//! In-daemon vector index over chunk embeddings.
+//!
+//! An HNSW graph (`hnsw`) holds one vector per chunk across all events, keyed
+//! by chunk id and tagged with the event and the embedding model version. Adds
+//! and removes are incremental. The daemon's index lives in `data/index` as one
+//! file, compressed then encrypted like blobs, and is rewritten by `flush`
+//! (periodically and at shutdown) when it changed. Test and scratch indexes
+//! can stay in memory.
+pub mod hnsw;
 
-pub fn flush_vector_index() {
-    info!("vector index flush: not implemented");
-}
\ No newline at end of file
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::storage::{crypto, Result};
+use hnsw::{Hnsw, Node};
+
+pub const DEFAULT_DIR: &str = "data/index";
+const INDEX_FILE: &str = "vectors.idx";
+const MAGIC: &[u8; 4] = b"VYVI";
+const FORMAT_VERSION: u32 = 1;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VectorHit {
+    pub chunk_id: String,
+    pub event_id: String,
+    /// Cosine similarity.
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub vectors: usize,
+    /// Removed vectors still in the graph until the next compaction.
+    pub deleted: usize,
+    pub dimension: Option<usize>,
+    pub model_version: Option<String>,
+    pub path: Option<String>,
+    /// Changes not yet flushed to disk.
+    pub dirty: bool,
+}
+
+struct Inner {
+    graph: Option<Hnsw>,
+    model_version: Option<String>,
+}
+
+pub struct VectorIndex {
+    inner: RwLock<Inner>,
+    path: Option<PathBuf>,
+    dirty: AtomicBool,
+    /// Candidate list size for searches.
+    pub ef_search: usize,
+}
+
+impl Default for VectorIndex {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
+impl VectorIndex {
+    /// An index that is never written to disk.
+    pub fn in_memory() -> Self {
+        Self { inner: RwLock::new(Inner { graph: None, model_version: None }), path: None, dirty: AtomicBool::new(false), ef_search: hnsw::DEFAULT_EF_SEARCH }
+    }
+
+    /// Loads the index persisted under `dir`, or starts an empty one there.
+    pub fn open(dir: &Path) -> Result<Self> {
+        let path = dir.join(INDEX_FILE);
+        let mut index = Self::in_memory();
+        if path.exists() {
+            let sealed = std::fs::read(&path)?;
+            let packed = crypto::decrypt_bytes(&sealed)?;
+            let raw = zstd::decode_all(&packed[..])?;
+            let (graph, model_version) = decode(&raw)?;
+            index.inner = RwLock::new(Inner { graph, model_version });
+        }
+        index.path = Some(path);
+        Ok(index)
+    }
+
+    /// Adds or replaces the vector of a chunk. The first vector fixes the
+    /// dimension and model version; vectors from another model are refused,
+    /// since their similarities would be meaningless.
+    pub fn add(&self, chunk_id: &str, event_id: &str, vector: &[f32], model_version: &str) -> Result<()> {
+        let mut inner = self.inner.write().unwrap();
+        let inner = &mut *inner;
+        if inner.graph.as_ref().is_some_and(|g| g.is_empty()) {
+            // An emptied index can switch models.
+            inner.graph = None;
+        }
+        match (&inner.graph, &inner.model_version) {
+            (Some(_), Some(current)) if current != model_version => {
+                return Err(format!("index holds {} embeddings; got {}", current, model_version).into());
+            }
+            (None, _) => {
+                if vector.is_empty() { return Err("empty embedding vector".into()); }
+                inner.graph = Some(Hnsw::new(vector.len()));
+                inner.model_version = Some(model_version.to_string());
+            }
+            _ => {}
+        }
+        inner.graph.as_mut().unwrap().insert(chunk_id, event_id, vector)?;
+        self.dirty.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn remove_chunks(&self, chunk_ids: &[String]) -> usize {
+        let mut inner = self.inner.write().unwrap();
+        let Some(graph) = inner.graph.as_mut() else { return 0 };
+        let removed = chunk_ids.iter().filter(|c| graph.remove(c)).count();
+        self.after_remove(graph, removed)
+    }
+
+    /// Removes every vector of the given events.
+    pub fn remove_events(&self, event_ids: &[String]) -> usize {
+        let mut inner = self.inner.write().unwrap();
+        let Some(graph) = inner.graph.as_mut() else { return 0 };
+        let chunks: Vec<String> = graph.live().filter(|n| event_ids.contains(&n.event_id)).map(|n| n.chunk_id.clone()).collect();
+        let removed = chunks.iter().filter(|c| graph.remove(c)).count();
+        self.after_remove(graph, removed)
+    }
+
+    /// Removes every vector of the given events for good: the graph is
+    /// compacted so no tombstone keeps them, and a persisted index is
+    /// rewritten right away rather than at the next periodic flush. Blocks for
+    /// as long as that takes, so async callers run it on a blocking thread.
+    pub fn purge_events(&self, event_ids: &[String]) -> Result<usize> {
+        let removed = {
+            let mut inner = self.inner.write().unwrap();
+            let Some(graph) = inner.graph.as_mut() else { return Ok(0) };
+            let events: HashSet<&String> = event_ids.iter().collect();
+            let chunks: Vec<String> = graph.live().filter(|n| events.contains(&n.event_id)).map(|n| n.chunk_id.clone()).collect();
+            let removed = chunks.iter().filter(|c| graph.remove(c)).count();
+            if removed > 0 {
+                graph.compact();
+                self.dirty.store(true, Ordering::Relaxed);
+            }
+            removed
+        };
+        if removed > 0 { self.flush()?; }
+        Ok(removed)
+    }
+
+    fn after_remove(&self, graph: &mut Hnsw, removed: usize) -> usize {
+        if removed > 0 {
+            self.dirty.store(true, Ordering::Relaxed);
+            // Tombstones slow searches down; rebuild once they outnumber live nodes.
+            if graph.deleted() > graph.len() { graph.compact(); }
+        }
+        removed
+    }
+
+    /// The `k` chunks nearest to `query`, best first.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<VectorHit> {
+        let inner = self.inner.read().unwrap();
+        let Some(graph) = inner.graph.as_ref() else { return Vec::new() };
+        graph.search(query, k, self.ef_search).into_iter()
+            .map(|(n, score)| VectorHit { chunk_id: n.chunk_id.clone(), event_id: n.event_id.clone(), score })
+            .collect()
+    }
+
+    /// Stored (normalised) vectors of an event's chunks.
+    pub fn vectors_for_event(&self, event_id: &str) -> Vec<(String, Vec<f32>)> {
+        let inner = self.inner.read().unwrap();
+        let Some(graph) = inner.graph.as_ref() else { return Vec::new() };
+        graph.live().filter(|n| n.event_id == event_id).map(|n| (n.chunk_id.clone(), n.vector.clone())).collect()
+    }
+
+    /// Event ids that have vectors.
+    pub fn event_ids(&self) -> Vec<String> {
+        let inner = self.inner.read().unwrap();
+        let Some(graph) = inner.graph.as_ref() else { return Vec::new() };
+        let mut ids: Vec<String> = graph.live().map(|n| n.event_id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().graph.as_ref().map_or(0, Hnsw::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        let inner = self.inner.read().unwrap();
+        IndexStatus {
+            vectors: inner.graph.as_ref().map_or(0, Hnsw::len),
+            deleted: inner.graph.as_ref().map_or(0, Hnsw::deleted),
+            dimension: inner.graph.as_ref().map(|g| g.dim),
+            model_version: inner.model_version.clone(),
+            path: self.path.as_ref().map(|p| p.display().to_string()),
+            dirty: self.dirty.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Writes the index if it changed since the last flush. Returns whether it wrote.
+    pub fn flush(&self) -> Result<bool> {
+        let Some(path) = self.path.as_ref() else { return Ok(false) };
+        if !self.dirty.swap(false, Ordering::Relaxed) { return Ok(false); }
+        let raw = {
+            let inner = self.inner.read().unwrap();
+            encode(inner.graph.as_ref(), inner.model_version.as_deref())
+        };
+        let written = (|| -> Result<()> {
+            let sealed = crypto::encrypt_bytes(&zstd::encode_all(&raw[..], 3)?)?;
+            if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+            // Write then rename, so a crash mid-flush keeps the previous index.
+            let tmp = path.with_extension("idx.tmp");
+            std::fs::write(&tmp, sealed)?;
+            std::fs::rename(&tmp, path)?;
+            Ok(())
+        })();
+        if written.is_err() { self.dirty.store(true, Ordering::Relaxed); }
+        written.map(|_| true)
+    }
+
+    /// Flushes every `VYASOAI_INDEX_FLUSH_SECS` (default 60) in the background.
+    pub fn start_periodic_flush(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let interval = std::env::var("VYASOAI_INDEX_FLUSH_SECS").ok().and_then(|v| v.parse().ok())
+            .filter(|&s: &u64| s > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_FLUSH_INTERVAL);
+        let index = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let index = index.clone();
+                match tokio::task::spawn_blocking(move || index.flush()).await {
+                    Ok(Err(e)) => error!(%e, "vector index flush failed"),
+                    Err(e) => error!(%e, "vector index flush panicked"),
+                    Ok(Ok(_)) => {}
+                }
+            }
+        })
+    }
+}
+
+/// Final flush on shutdown, after the ingest worker has drained.
+pub fn flush_vector_index(index: &VectorIndex) {
+    match index.flush() {
+        Ok(true) => info!(vectors = index.len(), "vector index flushed"),
+        Ok(false) => info!("vector index unchanged; nothing to flush"),
+        Err(e) => error!(%e, "vector index flush failed"),
+    }
+}
+
+// Layout (little endian): magic, format version, dim, m, ef_construction,
+// model version, entry (u32::MAX for none), node count, then per node: chunk id,
+// event id, deleted flag, layer count, vector, and each layer's neighbour list.
+// Strings are a u32 length followed by UTF-8 bytes.
+
+fn encode(graph: Option<&Hnsw>, model_version: Option<&str>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let put_u32 = |out: &mut Vec<u8>, v: u32| out.extend_from_slice(&v.to_le_bytes());
+    let put_str = |out: &mut Vec<u8>, s: &str| {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    };
+    out.extend_from_slice(MAGIC);
+    put_u32(&mut out, FORMAT_VERSION);
+    let Some(g) = graph else {
+        put_u32(&mut out, 0);
+        return out;
+    };
+    put_u32(&mut out, g.dim as u32);
+    put_u32(&mut out, g.m as u32);
+    put_u32(&mut out, g.ef_construction as u32);
+    put_str(&mut out, model_version.unwrap_or_default());
+    put_u32(&mut out, g.entry.unwrap_or(u32::MAX));
+    put_u32(&mut out, g.nodes.len() as u32);
+    for n in &g.nodes {
+        put_str(&mut out, &n.chunk_id);
+        put_str(&mut out, &n.event_id);
+        out.push(n.deleted as u8);
+        put_u32(&mut out, n.neighbors.len() as u32);
+        for x in &n.vector { out.extend_from_slice(&x.to_le_bytes()); }
+        for layer in &n.neighbors {
+            put_u32(&mut out, layer.len() as u32);
+            for &id in layer { put_u32(&mut out, id); }
+        }
+    }
+    out
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8]> {
+        if self.buf.len() < n { return Err("vector index file is truncated".into()); }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+}
+
+fn decode(raw: &[u8]) -> Result<(Option<Hnsw>, Option<String>)> {
+    let mut r = Reader { buf: raw };
+    if r.take(4)? != MAGIC { return Err("not a vector index file".into()); }
+    let version = r.u32()?;
+    if version != FORMAT_VERSION { return Err(format!("unsupported vector index format {}", version).into()); }
+    let dim = r.u32()? as usize;
+    if dim == 0 { return Ok((None, None)); }
+    let m = r.u32()? as usize;
+    let ef_construction = r.u32()? as usize;
+    let model_version = Some(r.string()?).filter(|s| !s.is_empty());
+    let entry = Some(r.u32()?).filter(|&e| e != u32::MAX);
+    let count = r.u32()? as usize;
+    let mut nodes = Vec::with_capacity(count);
+    for _ in 0..count {
+        let chunk_id = r.string()?;
+        let event_id = r.string()?;
+        let deleted = r.take(1)?[0] != 0;
+        let layers = r.u32()? as usize;
+        let vector = (0..dim).map(|_| r.f32()).collect::<Result<Vec<_>>>()?;
+        let mut neighbors = Vec::with_capacity(layers);
+        for _ in 0..layers {
+            let len = r.u32()? as usize;
+            neighbors.push((0..len).map(|_| r.u32()).collect::<Result<Vec<_>>>()?);
+        }
+        if neighbors.is_empty() || neighbors.iter().flatten().any(|&id| id as usize >= count) {
+            return Err("vector index file is corrupt".into());
+        }
+        nodes.push(Node { chunk_id, event_id, vector, neighbors, deleted });
+    }
+    if entry.is_some_and(|e| e as usize >= count) { return Err("vector index file is corrupt".into()); }
+    Ok((Some(Hnsw::from_parts(dim, m, ef_construction, nodes, entry)), model_version))
+}
@@ -0,0 +1,372 @@
+//! Supervised pool of long-lived intelligence workers.
+//!
+//! Each worker runs `python3 -m intelligence.cli serve` and answers JSON-RPC 2.0
+//! requests on stdin/stdout, one `Content-Length`-framed message at a time (the
+//! LSP framing). Workers keep their models and index loaded between requests.
+//! They start on first use, are pinged periodically, and are killed and
+//! restarted when they crash, time out or fail a health check.
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::{Arc, MutexGuard};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{info, warn};
+
+/// Largest response frame accepted from a worker.
+const MAX_FRAME_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct IntelConfig {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Working directory for workers; the daemon's own when `None`.
+    pub workdir: Option<PathBuf>,
+    pub workers: usize,
+    pub request_timeout: Duration,
+    pub health_interval: Duration,
+}
+
+impl Default for IntelConfig {
+    fn default() -> Self {
+        Self {
+            program: "python3".to_string(),
+            args: ["-m", "intelligence.cli", "serve"].iter().map(|s| s.to_string()).collect(),
+            workdir: None,
+            workers: 2,
+            request_timeout: Duration::from_secs(15),
+            health_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+impl IntelConfig {
+    /// `VYASOAI_INTEL_PYTHON`, `VYASOAI_INTEL_WORKDIR`, `VYASOAI_INTEL_WORKERS`,
+    /// `VYASOAI_INTEL_TIMEOUT_MS` and `VYASOAI_INTEL_HEALTH_SECS` override the defaults.
+    pub fn from_env() -> Self {
+        let mut c = Self::default();
+        if let Ok(p) = std::env::var("VYASOAI_INTEL_PYTHON") {
+            if !p.trim().is_empty() { c.program = p; }
+        }
+        if let Some(dir) = std::env::var_os("VYASOAI_INTEL_WORKDIR").filter(|d| !d.is_empty()) {
+            c.workdir = Some(PathBuf::from(dir));
+        }
+        let num = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        if let Some(n) = num("VYASOAI_INTEL_WORKERS") { c.workers = (n as usize).max(1); }
+        if let Some(ms) = num("VYASOAI_INTEL_TIMEOUT_MS") { c.request_timeout = Duration::from_millis(ms.max(1)); }
+        if let Some(s) = num("VYASOAI_INTEL_HEALTH_SECS") { c.health_interval = Duration::from_secs(s.max(1)); }
+        c
+    }
+}
+
+#[derive(Debug)]
+pub enum IntelError {
+    /// The worker process could not be started.
+    Spawn(String),
+    /// The worker exited or closed its pipes mid-request.
+    Crashed(String),
+    Timeout(Duration),
+    /// The worker sent something that is not a valid response frame.
+    Protocol(String),
+    /// The worker answered with a JSON-RPC error.
+    Rpc { code: i64, message: String },
+}
+
+impl std::fmt::Display for IntelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntelError::Spawn(e) => write!(f, "intel worker failed to start: {}", e),
+            IntelError::Crashed(e) => write!(f, "intel worker crashed: {}", e),
+            IntelError::Timeout(d) => write!(f, "intel request timed out after {} ms", d.as_millis()),
+            IntelError::Protocol(e) => write!(f, "intel protocol error: {}", e),
+            IntelError::Rpc { code, message } => write!(f, "intel error {}: {}", code, message),
+        }
+    }
+}
+
+impl std::error::Error for IntelError {}
+
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Worker {
+    fn spawn(config: &IntelConfig) -> Result<Self, IntelError> {
+        let mut cmd = Command::new(&config.program);
+        cmd.args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = &config.workdir { cmd.current_dir(dir); }
+        let mut child = cmd.spawn().map_err(|e| IntelError::Spawn(e.to_string()))?;
+        let pid = child.id();
+        let (Some(stdin), Some(stdout), Some(stderr)) = (child.stdin.take(), child.stdout.take(), child.stderr.take()) else {
+            return Err(IntelError::Spawn("worker pipes unavailable".to_string()));
+        };
+        // Worker logs go to stderr; forward them so they aren't lost.
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                warn!(target: "intel", pid, "{}", line);
+            }
+        });
+        info!(pid, "intel worker started");
+        Ok(Self { child, stdin, stdout: BufReader::new(stdout), next_id: 1 })
+    }
+
+    fn pid(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> Result<Value, IntelError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let body = serde_json::to_vec(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .map_err(|e| IntelError::Protocol(e.to_string()))?;
+        let crashed = |e: std::io::Error| IntelError::Crashed(e.to_string());
+        self.stdin.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await.map_err(crashed)?;
+        self.stdin.write_all(&body).await.map_err(crashed)?;
+        self.stdin.flush().await.map_err(crashed)?;
+
+        let resp = self.read_frame().await?;
+        if resp.get("id").and_then(Value::as_u64) != Some(id) {
+            return Err(IntelError::Protocol(format!("response id {} does not match request {}", resp["id"], id)));
+        }
+        if let Some(err) = resp.get("error") {
+            return Err(IntelError::Rpc {
+                code: err.get("code").and_then(Value::as_i64).unwrap_or(0),
+                message: err.get("message").and_then(Value::as_str).unwrap_or_default().to_string(),
+            });
+        }
+        resp.get("result").cloned().ok_or_else(|| IntelError::Protocol("response has neither result nor error".to_string()))
+    }
+
+    async fn read_frame(&mut self) -> Result<Value, IntelError> {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            let n = self.stdout.read_line(&mut line).await.map_err(|e| IntelError::Crashed(e.to_string()))?;
+            if n == 0 { return Err(IntelError::Crashed("worker closed stdout".to_string())); }
+            let line = line.trim();
+            if line.is_empty() { break; }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    length = Some(value.trim().parse::<usize>().map_err(|e| IntelError::Protocol(e.to_string()))?);
+                }
+            }
+        }
+        let length = length.ok_or_else(|| IntelError::Protocol("frame without Content-Length".to_string()))?;
+        if length > MAX_FRAME_BYTES {
+            return Err(IntelError::Protocol(format!("frame of {} bytes exceeds limit", length)));
+        }
+        let mut body = vec![0u8; length];
+        self.stdout.read_exact(&mut body).await.map_err(|e| IntelError::Crashed(e.to_string()))?;
+        serde_json::from_slice(&body).map_err(|e| IntelError::Protocol(e.to_string()))
+    }
+}
+
+struct Slot {
+    worker: Option<Worker>,
+    /// Readable while the slot is busy, hence outside the worker lock.
+    report: Arc<std::sync::Mutex<WorkerStatus>>,
+}
+
+impl Slot {
+    fn new(slot: usize) -> Self {
+        let report = WorkerStatus { slot, pid: None, state: "stopped", restarts: 0, last_error: None };
+        Self { worker: None, report: Arc::new(std::sync::Mutex::new(report)) }
+    }
+
+    fn report(&self) -> MutexGuard<'_, WorkerStatus> {
+        self.report.lock().unwrap()
+    }
+
+    /// Reports the slot busy until the guard is dropped, even if the request
+    /// is cancelled midway.
+    fn busy(&self) -> Busy {
+        self.report().state = "busy";
+        Busy(Arc::clone(&self.report))
+    }
+
+    /// Kills the worker; the next request or health check starts a fresh one.
+    fn discard(&mut self, err: &IntelError) {
+        let mut report = self.report.lock().unwrap();
+        if let Some(mut w) = self.worker.take() {
+            warn!(pid = ?w.pid(), %err, "restarting intel worker");
+            let _ = w.child.start_kill();
+            report.restarts += 1;
+        }
+        report.pid = None;
+        report.state = "stopped";
+        report.last_error = Some(err.to_string());
+    }
+
+    fn ensure_started(&mut self, config: &IntelConfig) -> Result<&mut Worker, IntelError> {
+        if self.worker.is_none() {
+            match Worker::spawn(config) {
+                Ok(w) => {
+                    let mut report = self.report.lock().unwrap();
+                    report.pid = w.pid();
+                    report.state = "idle";
+                    self.worker = Some(w);
+                }
+                Err(e) => {
+                    self.report().last_error = Some(e.to_string());
+                    return Err(e);
+                }
+            }
+        }
+        Ok(self.worker.as_mut().expect("worker just started"))
+    }
+}
+
+struct Busy(Arc<std::sync::Mutex<WorkerStatus>>);
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        let mut report = self.0.lock().unwrap();
+        report.state = if report.pid.is_some() { "idle" } else { "stopped" };
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerStatus {
+    pub slot: usize,
+    pub pid: Option<u32>,
+    /// `idle`, `busy` or `stopped`.
+    pub state: &'static str,
+    pub restarts: u64,
+    pub last_error: Option<String>,
+}
+
+pub struct IntelPool {
+    config: IntelConfig,
+    slots: Vec<Mutex<Slot>>,
+    reports: Vec<Arc<std::sync::Mutex<WorkerStatus>>>,
+    permits: Semaphore,
+}
+
+impl IntelPool {
+    pub fn new(config: IntelConfig) -> Self {
+        let n = config.workers.max(1);
+        let slots: Vec<Slot> = (0..n).map(Slot::new).collect();
+        let reports = slots.iter().map(|s| Arc::clone(&s.report)).collect();
+        Self { slots: slots.into_iter().map(Mutex::new).collect(), reports, permits: Semaphore::new(n), config }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(IntelConfig::from_env())
+    }
+
+    /// Sends one request to an idle worker, waiting for one if all are busy.
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value, IntelError> {
+        let _permit = self.permits.acquire().await.expect("pool semaphore is never closed");
+        // Holding a permit guarantees at least one slot is unlocked: only
+        // permit holders lock slots.
+        let mut slot = self.slots.iter().find_map(|s| s.try_lock().ok()).expect("a permit implies a free slot");
+        self.request(&mut slot, method, params).await
+    }
+
+    /// Sends a request to every worker, starting stopped ones, and returns
+    /// each worker's answer in slot order. For state every worker keeps, such
+    /// as its in-memory vector index.
+    pub async fn broadcast(&self, method: &str, params: Value) -> Vec<Result<Value, IntelError>> {
+        let mut results = Vec::with_capacity(self.slots.len());
+        for slot in &self.slots {
+            // Locked slots never outnumber held permits, which `call` relies on.
+            let _permit = self.permits.acquire().await.expect("pool semaphore is never closed");
+            let mut slot = slot.lock().await;
+            results.push(self.request(&mut slot, method, params.clone()).await);
+        }
+        results
+    }
+
+    async fn request(&self, slot: &mut Slot, method: &str, params: Value) -> Result<Value, IntelError> {
+        let timeout = self.config.request_timeout;
+        slot.ensure_started(&self.config)?;
+        let _busy = slot.busy();
+        let worker = slot.worker.as_mut().expect("worker just started");
+        let result = match tokio::time::timeout(timeout, worker.call(method, params)).await {
+            Ok(r) => r,
+            Err(_) => Err(IntelError::Timeout(timeout)),
+        };
+        match &result {
+            // The worker answered; it is still usable.
+            Ok(_) | Err(IntelError::Rpc { .. }) => {}
+            Err(e) => slot.discard(e),
+        }
+        result
+    }
+
+    /// Chunks and embeds one stored event.
+    pub async fn process(&self, job: Value) -> Result<Value, IntelError> {
+        self.call("process", job).await
+    }
+
+    /// Embeds free text (e.g. a search query) with the workers' model:
+    /// `{ "model_version", "dimensions", "vectors" }`.
+    pub async fn embed(&self, texts: &[String]) -> Result<Value, IntelError> {
+        self.call("embed", json!({ "texts": texts })).await
+    }
+
+    /// Drops purged events from every worker's index and deletes their job
+    /// files: `{ "vectors", "files" }` per worker.
+    pub async fn purge(&self, event_ids: &[String], chunk_ids: &[String], job_ids: &[String]) -> Vec<Result<Value, IntelError>> {
+        self.broadcast("purge", json!({ "event_ids": event_ids, "chunk_ids": chunk_ids, "job_ids": job_ids })).await
+    }
+
+    /// Pings idle workers and restarts any that crashed or stopped answering.
+    /// Busy workers are skipped; their own requests will surface a failure.
+    pub async fn check_health(&self) {
+        for slot in &self.slots {
+            // A ping occupies its slot like a request, so it needs a permit too.
+            let Ok(_permit) = self.permits.try_acquire() else { return };
+            let Ok(mut slot) = slot.try_lock() else { continue };
+            let exited = slot.worker.as_mut().map(|w| matches!(w.child.try_wait(), Ok(Some(_)) | Err(_)));
+            match exited {
+                Some(true) => slot.discard(&IntelError::Crashed("worker exited".to_string())),
+                Some(false) => {
+                    let _busy = slot.busy();
+                    let worker = slot.worker.as_mut().expect("checked above");
+                    let ping = match tokio::time::timeout(self.config.request_timeout, worker.call("ping", json!({}))).await {
+                        Ok(r) => r,
+                        Err(_) => Err(IntelError::Timeout(self.config.request_timeout)),
+                    };
+                    if let Err(e) = ping { slot.discard(&e); }
+                    continue;
+                }
+                None => {}
+            }
+            // Bring back workers that died, so the next event doesn't pay for the start-up.
+            if slot.report().restarts > 0 && slot.worker.is_none() {
+                let _ = slot.ensure_started(&self.config);
+            }
+        }
+    }
+
+    pub fn start_health_checks(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let pool = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(pool.config.health_interval);
+            tick.tick().await;
+            loop {
+                tick.tick().await;
+                pool.check_health().await;
+            }
+        })
+    }
+
+    /// Reads each slot's report without touching the slot locks, which only
+    /// permit holders may take.
+    pub fn status(&self) -> Vec<WorkerStatus> {
+        self.reports.iter().map(|r| r.lock().unwrap().clone()).collect()
+    }
+}
@@ -4,3 +4,14 @@ pub mod queue;
 pub mod storage;
 pub mod state;
 pub mod index;
+pub mod rag;
+pub mod capture;
+pub mod path_policy;
+pub mod feed;
+pub mod intel;
+pub mod pipeline;
+pub mod chunking;
+pub mod retrieval;
+pub mod embeddings;
+pub mod purge;
+pub mod retention;
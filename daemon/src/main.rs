@@ -11,7 +11,7 @@ use tracing::error;
 
 use vyasoai_daemon::{routes, queue, storage::{db, blobs}, state};
 use vyasoai_daemon::index;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> vyasoai_daemon::storage::Result<()> {
@@ -22,8 +22,16 @@ async fn main() -> vyasoai_daemon::storage::Result<()> {
     blobs::ensure_blob_base()?;
     blobs::ensure_today_blob_dir()?;
     let (tx, rx) = mpsc::channel::<state::IngestJob>(1024);
-    let app_state = Arc::new(state::AppState { db: Arc::new(Mutex::new(conn)), queue_tx: tx.clone(), key_manager: Some(state::KeyManager::new()) });
+    let mut app_state = state::AppState::new(conn, tx, Some(state::KeyManager::new()));
+    app_state.vectors = Arc::new(index::VectorIndex::open(std::path::Path::new(index::DEFAULT_DIR))?);
+    let app_state = Arc::new(app_state);
+    let vectors = app_state.vectors.clone();
     let worker = queue::start_worker(rx, app_state.clone());
+    queue::replay_journal(&app_state).await?;
+    app_state.intel.start_health_checks();
+    vectors.start_periodic_flush();
+    let trash_sweeper = vyasoai_daemon::purge::start_trash_sweeper(&app_state);
+    let retention_sweeper = vyasoai_daemon::retention::start_sweeper(&app_state);
     let app: Router = routes::router(app_state.clone());
 
     #[cfg(target_os = "windows")]
@@ -31,11 +39,12 @@ async fn main() -> vyasoai_daemon::storage::Result<()> {
         let addr = "127.0.0.1:8765";
         let listener = TcpListener::bind(addr).await?;
         info!(%addr, db_path = %db_path.display(), "Vyaso AI daemon listening on TCP loopback");
-        axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        axum::serve(listener, app).with_graceful_shutdown(shutdown_signal(app_state.feed.clone())).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        trash_sweeper.abort();
+        retention_sweeper.abort();
         drop(app_state);
-        let _ = worker.await;
-        info!("server stopped; worker drained");
-        index::flush_vector_index();
+        if worker.shutdown(queue::SHUTDOWN_DRAIN_TIMEOUT).await { info!("server stopped; worker drained"); }
+        index::flush_vector_index(&vectors);
         Ok(())
     }
 
@@ -46,11 +55,12 @@ async fn main() -> vyasoai_daemon::storage::Result<()> {
         let listener = UnixListener::bind(&sock_path)?;
         info!(path = %sock_path.display(), db_path = %db_path.display(), "Vyaso AI daemon listening on Unix Domain Socket");
         let incoming = UdsIncoming { listener };
-        axum::serve(incoming, app).with_graceful_shutdown(shutdown_signal()).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        axum::serve(incoming, app).with_graceful_shutdown(shutdown_signal(app_state.feed.clone())).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        trash_sweeper.abort();
+        retention_sweeper.abort();
         drop(app_state);
-        let _ = worker.await;
-        info!("server stopped; worker drained");
-        index::flush_vector_index();
+        if worker.shutdown(queue::SHUTDOWN_DRAIN_TIMEOUT).await { info!("server stopped; worker drained"); }
+        index::flush_vector_index(&vectors);
         Ok(())
     }
     #[cfg(all(not(target_os = "windows"), not(feature = "uds")))]
@@ -58,11 +68,12 @@ async fn main() -> vyasoai_daemon::storage::Result<()> {
         let addr = "127.0.0.1:8765";
         let listener = TcpListener::bind(addr).await?;
         info!(%addr, db_path = %db_path.display(), "Vyaso AI daemon listening on TCP loopback (fallback on non-Windows)");
-        axum::serve(listener, app).with_graceful_shutdown(shutdown_signal()).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        axum::serve(listener, app).with_graceful_shutdown(shutdown_signal(app_state.feed.clone())).await.map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.into() })?;
+        trash_sweeper.abort();
+        retention_sweeper.abort();
         drop(app_state);
-        let _ = worker.await;
-        info!("server stopped; worker drained");
-        index::flush_vector_index();
+        if worker.shutdown(queue::SHUTDOWN_DRAIN_TIMEOUT).await { info!("server stopped; worker drained"); }
+        index::flush_vector_index(&vectors);
         Ok(())
 }
 }
@@ -75,7 +86,7 @@ fn init_logging() {
         .try_init();
 }
 
-async fn shutdown_signal() {
+async fn shutdown_signal(feed: Arc<vyasoai_daemon::feed::Feed>) {
     #[cfg(unix)]
     {
         use tokio::signal::unix::{signal, SignalKind};
@@ -88,6 +99,8 @@ async fn shutdown_signal() {
         let _ = tokio::signal::ctrl_c().await;
     }
     info!("shutdown signal received");
+    // Open feed subscriptions would otherwise keep graceful shutdown waiting.
+    feed.close();
 }
 
 #[cfg(all(not(target_os = "windows"), feature = "uds"))]
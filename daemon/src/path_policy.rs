@@ -0,0 +1,118 @@
+//! Which files `content_pointer` may name.
+//!
+//! The daemon only reads pointers that resolve (after following symlinks) to a
+//! regular file under one of the capture roots and that match none of the
+//! denied globs. Roots come from `VYASOAI_CAPTURE_ROOTS` (a platform path list,
+//! like `PATH`) and default to the temp dir and the user's home. Extra globs can
+//! be added with `VYASOAI_CAPTURE_DENY` (comma-separated); the built-in denylist
+//! always applies.
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Credentials and key material that must never enter the store.
+pub const DEFAULT_DENY: &[&str] = &[
+    "**/.ssh/**",
+    "**/.gnupg/**",
+    "**/.aws/**",
+    "**/.azure/**",
+    "**/.config/gcloud/**",
+    "**/.kube/**",
+    "**/.docker/config.json",
+    "**/.password-store/**",
+    "**/Library/Keychains/**",
+    "**/.netrc",
+    "**/.git-credentials",
+    "**/.env",
+    "**/.env.*",
+    "**/id_rsa*",
+    "**/id_ed25519*",
+    "**/id_ecdsa*",
+    "**/*.pem",
+    "**/*.key",
+    "**/*.p12",
+    "**/*.pfx",
+    "**/*.kdbx",
+];
+
+#[derive(Debug, Clone)]
+pub struct PathPolicy {
+    roots: Vec<PathBuf>,
+    deny: GlobSet,
+    deny_patterns: Vec<String>,
+}
+
+impl PathPolicy {
+    /// Roots are canonicalized here; roots that don't exist are dropped.
+    pub fn new(roots: &[PathBuf], deny: &[String]) -> Result<Self, globset::Error> {
+        let roots = roots.iter().filter_map(|r| std::fs::canonicalize(r).ok()).collect();
+        let mut builder = GlobSetBuilder::new();
+        for pattern in deny {
+            builder.add(Glob::new(pattern)?);
+        }
+        Ok(Self { roots, deny: builder.build()?, deny_patterns: deny.to_vec() })
+    }
+
+    pub fn from_env() -> Result<Self, globset::Error> {
+        let roots: Vec<PathBuf> = match std::env::var_os("VYASOAI_CAPTURE_ROOTS") {
+            Some(v) => std::env::split_paths(&v).filter(|p| !p.as_os_str().is_empty()).collect(),
+            None => {
+                let mut r = vec![std::env::temp_dir()];
+                if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+                    r.push(PathBuf::from(home));
+                }
+                r
+            }
+        };
+        let mut deny: Vec<String> = DEFAULT_DENY.iter().map(|s| s.to_string()).collect();
+        if let Ok(extra) = std::env::var("VYASOAI_CAPTURE_DENY") {
+            deny.extend(extra.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string));
+        }
+        Self::new(&roots, &deny)
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Resolves `pointer` and returns the canonical path to read, or why it is refused.
+    /// `Ok(None)` means there is nothing to read (empty or missing pointer).
+    pub fn resolve(&self, pointer: &str) -> Result<Option<PathBuf>, String> {
+        if pointer.trim().is_empty() { return Ok(None); }
+        let path = Path::new(pointer);
+        if !path.is_absolute() {
+            return Err("content_pointer must be an absolute path".to_string());
+        }
+        // Check the path as given too, so a denied name can't be laundered through a symlink.
+        if let Some(p) = self.denied_by(path) {
+            return Err(format!("content_pointer matches denied pattern {}", p));
+        }
+        let canonical = match std::fs::canonicalize(path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("content_pointer cannot be resolved: {}", e)),
+        };
+        if !self.roots.iter().any(|root| canonical.starts_with(root)) {
+            return Err("content_pointer is outside the capture roots".to_string());
+        }
+        if let Some(p) = self.denied_by(&canonical) {
+            return Err(format!("content_pointer matches denied pattern {}", p));
+        }
+        if !canonical.is_file() {
+            return Err("content_pointer is not a regular file".to_string());
+        }
+        Ok(Some(canonical))
+    }
+
+    fn denied_by(&self, path: &Path) -> Option<&str> {
+        self.deny.matches(path).first().map(|&i| self.deny_patterns[i].as_str())
+    }
+}
+
+impl Default for PathPolicy {
+    /// The built-in denylist with no roots: every pointer is refused.
+    fn default() -> Self {
+        let deny: Vec<String> = DEFAULT_DENY.iter().map(|s| s.to_string()).collect();
+        Self::new(&[], &deny).expect("built-in deny globs are valid")
+    }
+}
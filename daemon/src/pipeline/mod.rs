@@ -0,0 +1,225 @@
+//! Post-ingest processing pipeline.
+//!
+//! Once an event is stored the worker runs it through an ordered list of
+//! `Processor` stages (text extraction, chunking, embedding, indexing, ...).
+//! Stages come from a registry; which of them run, and in what order, can be
+//! configured per content type. Every stage run is recorded in `stage_runs`
+//! with its output summary and timing. A failing stage stops the pipeline and
+//! fails the event.
+//!
+//! `VYASOAI_PIPELINE_STAGES` configures the order: a comma-separated stage list
+//! used for every content type, optionally followed by `;`-separated
+//! `<content-type prefix>=<stages>` overrides, e.g.
+//! `extract_text,intel,index_chunks;image/=intel;application/pdf=`.
+//! The longest matching prefix wins; an empty list runs nothing.
+pub mod stages;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use tracing::{error, warn};
+
+use crate::handlers::EventEnvelope;
+use crate::state::AppState;
+use crate::storage::db::ChunkRow;
+use crate::storage::processing::{self, StageRun};
+use crate::storage::{blobs, db, Result};
+
+/// Everything known about an event as it moves through the stages.
+pub struct StageContext {
+    pub state: Arc<AppState>,
+    pub event: EventEnvelope,
+    /// Sniffed from the decoded blob; `application/octet-stream` without one.
+    pub content_type: String,
+    /// Where the stored blob lives, or the original pointer when nothing was stored.
+    pub blob_path: String,
+    /// Decoded blob bytes, when the event has stored content.
+    pub bytes: Option<Vec<u8>>,
+    /// Indexable text; set by `extract_text` for `default` privacy events.
+    pub text: Option<String>,
+    pub chunks: Vec<ChunkRow>,
+    /// Vectors for `chunks`, when a stage embedded them.
+    pub embeddings: Option<Embeddings>,
+    /// Intel requests made for this event.
+    pub attempts: i64,
+    /// Outputs of the stages that already ran, by stage name.
+    pub outputs: Map<String, Value>,
+}
+
+/// Chunk vectors, aligned with `StageContext::chunks`, and the model that produced them.
+pub struct Embeddings {
+    pub model_version: String,
+    pub vectors: Vec<Vec<f32>>,
+}
+
+/// One post-ingest stage.
+#[async_trait]
+pub trait Processor: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Whether the stage applies to this event; skipped stages are still recorded.
+    fn accepts(&self, _ctx: &StageContext) -> bool {
+        true
+    }
+
+    /// Runs the stage. The returned value is recorded as the stage's output.
+    async fn process(&self, ctx: &mut StageContext) -> Result<Value>;
+}
+
+/// Result of running the pipeline over one event.
+pub struct PipelineOutcome {
+    pub ctx: StageContext,
+    /// The stage that failed and why.
+    pub failed: Option<(String, String)>,
+}
+
+pub struct Pipeline {
+    registry: Vec<Arc<dyn Processor>>,
+    /// Stage order for content types without an override; registration order when `None`.
+    default_order: Option<Vec<String>>,
+    overrides: Vec<(String, Vec<String>)>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::with_builtin_stages()
+    }
+}
+
+impl Pipeline {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self { registry: Vec::new(), default_order: None, overrides: Vec::new() }
+    }
+
+    /// `extract_text`, `chunk`, `intel`, `embed` and `index_chunks`, in that order.
+    pub fn with_builtin_stages() -> Self {
+        let mut p = Self::new();
+        p.register(Arc::new(stages::ExtractText));
+        p.register(Arc::new(stages::Chunk));
+        p.register(Arc::new(stages::Intel));
+        p.register(Arc::new(stages::Embed));
+        p.register(Arc::new(stages::IndexChunks));
+        p
+    }
+
+    /// Built-in stages ordered by `VYASOAI_PIPELINE_STAGES` when it is set.
+    pub fn from_env() -> Self {
+        let mut p = Self::with_builtin_stages();
+        if let Ok(spec) = std::env::var("VYASOAI_PIPELINE_STAGES") {
+            if !spec.trim().is_empty() { p.configure(&spec); }
+        }
+        p
+    }
+
+    /// Adds a stage, or replaces the registered stage of the same name.
+    pub fn register(&mut self, stage: Arc<dyn Processor>) {
+        match self.registry.iter_mut().find(|s| s.name() == stage.name()) {
+            Some(existing) => *existing = stage,
+            None => self.registry.push(stage),
+        }
+    }
+
+    pub fn stage_names(&self) -> Vec<String> {
+        self.registry.iter().map(|s| s.name().to_string()).collect()
+    }
+
+    /// Applies an order spec (see the module docs). Unknown stage names are
+    /// ignored with a warning.
+    pub fn configure(&mut self, spec: &str) {
+        let parse_list = |list: &str| -> Vec<String> {
+            list.split(',').map(str::trim).filter(|n| !n.is_empty()).filter(|n| {
+                let known = self.registry.iter().any(|s| s.name() == *n);
+                if !known { warn!(stage = %n, "unknown pipeline stage ignored"); }
+                known
+            }).map(str::to_string).collect()
+        };
+        let mut overrides = Vec::new();
+        let mut default_order = None;
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            match rule.split_once('=') {
+                Some((prefix, list)) => overrides.push((prefix.trim().to_ascii_lowercase(), parse_list(list))),
+                None => default_order = Some(parse_list(rule)),
+            }
+        }
+        self.default_order = default_order;
+        self.overrides = overrides;
+    }
+
+    /// Stages to run for `content_type`, in order.
+    pub fn stages_for(&self, content_type: &str) -> Vec<Arc<dyn Processor>> {
+        let content_type = content_type.to_ascii_lowercase();
+        let order = self.overrides.iter()
+            .filter(|(prefix, _)| content_type.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, order)| order)
+            .or(self.default_order.as_ref());
+        match order {
+            Some(order) => order.iter().filter_map(|n| self.registry.iter().find(|s| s.name() == n).cloned()).collect(),
+            None => self.registry.clone(),
+        }
+    }
+
+    /// Runs the stages for a stored event, recording each run.
+    pub async fn run(&self, state: &Arc<AppState>, ev: &EventEnvelope) -> Result<PipelineOutcome> {
+        let mut ctx = load_context(state, ev).await?;
+        for stage in self.stages_for(&ctx.content_type) {
+            let name = stage.name().to_string();
+            if !stage.accepts(&ctx) {
+                record(state, StageRun::skipped(&ev.event_id, &name));
+                continue;
+            }
+            let started = Instant::now();
+            let result = stage.process(&mut ctx).await;
+            let duration_ms = started.elapsed().as_millis() as i64;
+            match result {
+                Ok(output) => {
+                    record(state, StageRun::ok(&ev.event_id, &name, &output, duration_ms));
+                    ctx.outputs.insert(name, output);
+                }
+                Err(e) => {
+                    let message = e.to_string();
+                    record(state, StageRun::failed(&ev.event_id, &name, &message, duration_ms));
+                    return Ok(PipelineOutcome { ctx, failed: Some((name, message)) });
+                }
+            }
+        }
+        Ok(PipelineOutcome { ctx, failed: None })
+    }
+}
+
+fn record(state: &AppState, run: StageRun) {
+    let conn = state.db.lock().unwrap();
+    if let Err(e) = processing::record_stage_run(&conn, &run) { error!(%e, stage = %run.stage, "recording stage run failed"); }
+}
+
+/// Loads the stored blob once, so stages don't each decrypt it.
+async fn load_context(state: &Arc<AppState>, ev: &EventEnvelope) -> Result<StageContext> {
+    // Resolve blob path from db (authoritative path)
+    let stored = {
+        let conn = state.db.lock().unwrap();
+        db::get_blob_index(&conn, &ev.content_hash)?.map(|(p, _)| p)
+    };
+    let bytes = match stored.clone() {
+        Some(path) => tokio::task::spawn_blocking(move || blobs::load_blob(std::path::Path::new(&path)).ok()).await?,
+        None => None,
+    };
+    let blob_path = stored.unwrap_or_else(|| ev.content_pointer.clone());
+    // Workers may run from another directory.
+    let blob_path = std::fs::canonicalize(&blob_path).map(|p| p.to_string_lossy().into_owned()).unwrap_or(blob_path);
+    let content_type = bytes.as_deref().map(blobs::sniff_content_type).unwrap_or("application/octet-stream").to_string();
+    Ok(StageContext {
+        state: state.clone(),
+        event: ev.clone(),
+        content_type,
+        blob_path,
+        bytes,
+        text: None,
+        chunks: Vec::new(),
+        embeddings: None,
+        attempts: 0,
+        outputs: Map::new(),
+    })
+}
@@ -0,0 +1,215 @@
+//! Built-in pipeline stages.
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use super::{Embeddings, Processor, StageContext};
+use crate::chunking;
+use crate::embeddings;
+use crate::handlers::PrivacyFlag;
+use crate::intel::IntelError;
+use crate::storage::db::{self, ChunkRow};
+use crate::storage::{fts, Result};
+
+/// Intel requests per event before it fails. Only transport failures (crash,
+/// timeout) are retried; a job the worker rejects is final.
+const INTEL_MAX_ATTEMPTS: usize = 3;
+
+/// Decodes UTF-8 text for indexing. Sensitive content never gets text.
+pub struct ExtractText;
+
+#[async_trait]
+impl Processor for ExtractText {
+    fn name(&self) -> &str { "extract_text" }
+
+    fn accepts(&self, ctx: &StageContext) -> bool {
+        matches!(ctx.event.privacy_flag, PrivacyFlag::Default) && ctx.bytes.is_some()
+    }
+
+    async fn process(&self, ctx: &mut StageContext) -> Result<Value> {
+        ctx.text = ctx.bytes.as_deref().and_then(fts::extract_text);
+        Ok(json!({ "chars": ctx.text.as_ref().map(|t| t.chars().count()) }))
+    }
+}
+
+/// Chunking strategy for the event, from its pointer's extension or sniffed
+/// MIME type; the same one the intel workers are asked for.
+fn chunk_strategy(ctx: &StageContext) -> &'static str {
+    let pointer = Some(ctx.event.content_pointer.as_str()).filter(|p| !p.is_empty());
+    chunking::strategy_for(&ctx.content_type, pointer)
+}
+
+/// Chunks the extracted text natively, without an intelligence worker.
+pub struct Chunk;
+
+#[async_trait]
+impl Processor for Chunk {
+    fn name(&self) -> &str { "chunk" }
+
+    fn accepts(&self, ctx: &StageContext) -> bool {
+        ctx.text.is_some()
+    }
+
+    async fn process(&self, ctx: &mut StageContext) -> Result<Value> {
+        let strategy = chunk_strategy(ctx);
+        let text = ctx.text.as_deref().unwrap_or_default();
+        ctx.chunks = chunking::chunk_text(text, strategy, Some(&ctx.event.event_id)).iter().map(|c| c.to_row()).collect();
+        ctx.embeddings = None;
+        Ok(json!({ "chunks": ctx.chunks.len(), "content_type": strategy }))
+    }
+}
+
+/// Chunks and embeds the blob through the intelligence worker pool. Like text
+/// extraction, sensitive content is never handed to the workers. The workers'
+/// chunks replace native ones (same strategy, same ids); when the workers fail
+/// after `chunk` already ran, the native chunks are kept and the failure is
+/// only recorded in the stage output.
+pub struct Intel;
+
+#[async_trait]
+impl Processor for Intel {
+    fn name(&self) -> &str { "intel" }
+
+    fn accepts(&self, ctx: &StageContext) -> bool {
+        matches!(ctx.event.privacy_flag, PrivacyFlag::Default)
+    }
+
+    async fn process(&self, ctx: &mut StageContext) -> Result<Value> {
+        let job_id = uuid::Uuid::new_v4().to_string();
+        match request_intel(ctx, &job_id).await {
+            Err(e) if !ctx.chunks.is_empty() => {
+                warn!(job_id, event_id = %ctx.event.event_id, %e, "intel failed; keeping native chunks");
+                Ok(json!({ "job_id": job_id, "error": e.to_string(), "native_chunks": ctx.chunks.len() }))
+            }
+            result => result,
+        }
+    }
+}
+
+/// Sends the event to the worker pool, retrying transport failures.
+async fn request_intel(ctx: &mut StageContext, job_id: &str) -> Result<Value> {
+    let job = json!({
+        "job_id": job_id,
+        "event_id": ctx.event.event_id,
+        "blob_path": ctx.blob_path,
+        "content_type": chunk_strategy(ctx),
+        "source": ctx.event.source,
+        "params": {"backend": "mock"},
+        "created_at": time::OffsetDateTime::now_utc().to_string()
+    });
+    let mut last_error = None;
+    for attempt in 1..=INTEL_MAX_ATTEMPTS {
+        ctx.attempts += 1;
+        match ctx.state.intel.process(job.clone()).await {
+            Ok(result) => {
+                (ctx.chunks, ctx.embeddings) = parse_chunks(&ctx.event.event_id, &result)?;
+                return Ok(json!({
+                    "job_id": job_id,
+                    "chunks": ctx.chunks.len(),
+                    "model_version": ctx.embeddings.as_ref().map(|e| e.model_version.clone()),
+                    "timings_ms": result.get("timings_ms"),
+                }));
+            }
+            Err(e @ IntelError::Rpc { .. }) => return Err(e.into()),
+            Err(e) => {
+                info!(attempt, job_id, %e, "intel request failed; retrying");
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.map(Into::into).unwrap_or_else(|| "intel processing was not attempted".into()))
+}
+
+/// Chunk rows and, when every chunk carries a vector, their embeddings.
+fn parse_chunks(event_id: &str, v: &Value) -> Result<(Vec<ChunkRow>, Option<Embeddings>)> {
+    let status = v.get("status").and_then(|s| s.as_str()).unwrap_or("missing");
+    if status != "ok" {
+        let detail = v.pointer("/errors/0/message").and_then(|e| e.as_str()).unwrap_or_default();
+        return Err(format!("intel job status {}: {}", status, detail).into());
+    }
+    let arr = v.get("chunks").and_then(|c| c.as_array()).ok_or("intel output lacks chunks")?;
+    let mut rows = Vec::with_capacity(arr.len());
+    let mut vectors = Vec::with_capacity(arr.len());
+    for c in arr {
+        let row = (|| Some(ChunkRow {
+            chunk_id: c.get("id")?.as_str()?.to_string(),
+            event_id: event_id.to_string(),
+            start_offset: c.get("start")?.as_i64()?,
+            end_offset: c.get("end")?.as_i64()?,
+            content_type: c.get("type")?.as_str()?.to_string(),
+        }))();
+        let Some(row) = row else { continue };
+        rows.push(row);
+        let vector: Option<Vec<f32>> = c.get("vector").and_then(|v| v.as_array())
+            .and_then(|xs| xs.iter().map(|x| x.as_f64().map(|f| f as f32)).collect());
+        vectors.push(vector);
+    }
+    let model_version = v.pointer("/index/model_version").and_then(|m| m.as_str());
+    let embeddings = match (model_version, vectors.iter().all(Option::is_some)) {
+        (Some(model), true) if !rows.is_empty() => Some(Embeddings { model_version: model.to_string(), vectors: vectors.into_iter().flatten().collect() }),
+        _ => None,
+    };
+    Ok((rows, embeddings))
+}
+
+/// Embeds chunk text with the daemon's embedding provider, unless an earlier
+/// stage (`intel`) already embedded the chunks with the same model.
+pub struct Embed;
+
+#[async_trait]
+impl Processor for Embed {
+    fn name(&self) -> &str { "embed" }
+
+    fn accepts(&self, ctx: &StageContext) -> bool {
+        let model = ctx.state.embedder.model_version();
+        ctx.text.is_some() && !ctx.chunks.is_empty() && ctx.embeddings.as_ref().is_none_or(|e| e.model_version != model)
+    }
+
+    async fn process(&self, ctx: &mut StageContext) -> Result<Value> {
+        let chars: Vec<char> = ctx.text.as_deref().unwrap_or_default().chars().collect();
+        let texts: Vec<String> = ctx.chunks.iter().map(|c| {
+            let start = (c.start_offset.max(0) as usize).min(chars.len());
+            let end = (c.end_offset.max(0) as usize).clamp(start, chars.len());
+            chars[start..end].iter().collect()
+        }).collect();
+        let provider = ctx.state.embedder.clone();
+        let vectors = embeddings::embed(provider.as_ref(), &texts).await?;
+        ctx.embeddings = Some(Embeddings { model_version: provider.model_version().to_string(), vectors });
+        Ok(json!({ "provider": provider.name(), "model_version": provider.model_version(), "vectors": texts.len() }))
+    }
+}
+
+/// Stores chunk rows, re-indexes FTS by chunk when there is text, and replaces
+/// the event's vectors when the chunks were embedded.
+pub struct IndexChunks;
+
+#[async_trait]
+impl Processor for IndexChunks {
+    fn name(&self) -> &str { "index_chunks" }
+
+    fn accepts(&self, ctx: &StageContext) -> bool {
+        !ctx.chunks.is_empty()
+    }
+
+    async fn process(&self, ctx: &mut StageContext) -> Result<Value> {
+        let (stored, indexed) = {
+            let conn = ctx.state.db.lock().unwrap();
+            let stored = db::insert_chunks(&conn, &ctx.chunks)?;
+            let indexed = match ctx.text.as_deref() {
+                Some(t) => fts::index_chunks(&conn, &ctx.event.event_id, t, &ctx.chunks)?,
+                None => 0,
+            };
+            (stored, indexed)
+        };
+        let mut vectors = 0;
+        if let Some(emb) = ctx.embeddings.as_ref().filter(|e| e.vectors.len() == ctx.chunks.len()) {
+            let index = &ctx.state.vectors;
+            index.remove_events(std::slice::from_ref(&ctx.event.event_id));
+            for (chunk, vector) in ctx.chunks.iter().zip(&emb.vectors) {
+                index.add(&chunk.chunk_id, &chunk.event_id, vector, &emb.model_version)?;
+                vectors += 1;
+            }
+        }
+        Ok(json!({ "chunks": stored, "fts_rows": indexed, "vectors": vectors }))
+    }
+}
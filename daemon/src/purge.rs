@@ -0,0 +1,312 @@
+//! Purging events from every layer, inline or as a background job, and
+//! sweeping the trash.
+//!
+//! `purge` deletes the matching rows in one transaction, then drops the
+//! events' vectors from the daemon index and asks every intel worker to
+//! forget them. A background job first snapshots the matching event ids and
+//! purges them in batches of `batch_size`, so progress can be reported and a
+//! cancel takes effect between batches; events stored after the job started
+//! are not part of it.
+//!
+//! A purge request without `skip_trash` only moves events to the trash (see
+//! `storage::trash`); the trash sweeper purges them once their restore window
+//! has ended.
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{error, info, warn};
+
+use crate::state::AppState;
+use crate::storage::db::{self, PurgeCriteria, TrashScope};
+use crate::storage::{trash, Result};
+
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_TRASH_DAYS: u64 = 30;
+const DEFAULT_TRASH_SWEEP: Duration = Duration::from_secs(3600);
+/// Finished jobs kept for polling; the oldest are forgotten first.
+const MAX_FINISHED_JOBS: usize = 50;
+
+/// Per-layer counts of what a purge deleted.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PurgeSummary {
+    pub deleted_events: u64,
+    pub deleted_blobs: u64,
+    pub deleted_chunks: u64,
+    pub deleted_fts_rows: u64,
+    pub deleted_stage_runs: u64,
+    /// Processing status and dead-letter rows.
+    pub deleted_status_rows: u64,
+    pub deleted_ingest_errors: u64,
+    pub deleted_vectors: u64,
+    pub deleted_intel_vectors: u64,
+    pub deleted_intel_files: u64,
+    /// Matching events left alone because they are on hold.
+    pub skipped_held: u64,
+    /// Intel workers that could not purge their artifacts.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub intel_errors: Vec<String>,
+}
+
+impl PurgeSummary {
+    pub(crate) fn add(&mut self, other: PurgeSummary) {
+        self.deleted_events += other.deleted_events;
+        self.deleted_blobs += other.deleted_blobs;
+        self.deleted_chunks += other.deleted_chunks;
+        self.deleted_fts_rows += other.deleted_fts_rows;
+        self.deleted_stage_runs += other.deleted_stage_runs;
+        self.deleted_status_rows += other.deleted_status_rows;
+        self.deleted_ingest_errors += other.deleted_ingest_errors;
+        self.deleted_vectors += other.deleted_vectors;
+        self.deleted_intel_vectors += other.deleted_intel_vectors;
+        self.deleted_intel_files += other.deleted_intel_files;
+        self.skipped_held += other.skipped_held;
+        for e in other.intel_errors {
+            if !self.intel_errors.contains(&e) { self.intel_errors.push(e); }
+        }
+    }
+}
+
+/// Deletes the events matching `criteria` and everything derived from them.
+/// Unreachable intel workers are reported in `intel_errors`, not as a failure:
+/// by then the rows are already gone.
+pub async fn purge(app: &Arc<AppState>, criteria: PurgeCriteria) -> Result<PurgeSummary> {
+    let report = {
+        let mut conn = app.db.lock().unwrap();
+        db::purge_events(&mut conn, criteria)?
+    };
+    let mut summary = PurgeSummary {
+        deleted_events: report.events,
+        deleted_blobs: report.blobs,
+        deleted_chunks: report.chunks,
+        deleted_fts_rows: report.fts_rows,
+        deleted_stage_runs: report.stage_runs,
+        deleted_status_rows: report.event_status + report.dead_letters,
+        deleted_ingest_errors: report.ingest_errors,
+        skipped_held: report.skipped_held,
+        ..PurgeSummary::default()
+    };
+    if report.event_ids.is_empty() { return Ok(summary); }
+
+    // Compacting and rewriting the index is slow on a large one.
+    let (vectors, event_ids) = (app.vectors.clone(), report.event_ids.clone());
+    summary.deleted_vectors = tokio::task::spawn_blocking(move || vectors.purge_events(&event_ids)).await?
+        .map_err(|e| format!("vector index: {}", e))? as u64;
+    for result in app.intel.purge(&report.event_ids, &report.chunk_ids, &report.job_ids).await {
+        match result {
+            Ok(v) => {
+                summary.deleted_intel_vectors += v["vectors"].as_u64().unwrap_or(0);
+                summary.deleted_intel_files += v["files"].as_u64().unwrap_or(0);
+            }
+            Err(e) => summary.intel_errors.push(e.to_string()),
+        }
+    }
+    if !summary.intel_errors.is_empty() {
+        warn!(errors = ?summary.intel_errors, "intel workers could not purge their artifacts");
+    }
+    Ok(summary)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// A job as reported to clients.
+#[derive(Debug, Clone, Serialize)]
+pub struct PurgeJobStatus {
+    pub job_id: String,
+    pub state: JobState,
+    /// Events matched when the job started.
+    pub total: u64,
+    /// Events handled so far, whether or not they still existed.
+    pub processed: u64,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+    pub error: Option<String>,
+    /// Totals so far; final once the job has finished.
+    pub summary: PurgeSummary,
+}
+
+pub struct PurgeJob {
+    status: Mutex<PurgeJobStatus>,
+    cancel: AtomicBool,
+}
+
+impl PurgeJob {
+    pub fn status(&self) -> PurgeJobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Asks a running job to stop before its next batch; batches already
+    /// purged stay purged. Returns false if the job had already finished.
+    pub fn cancel(&self) -> bool {
+        let running = self.status.lock().unwrap().state == JobState::Running;
+        if running { self.cancel.store(true, Ordering::Relaxed); }
+        running
+    }
+
+    fn finish(&self, state: JobState, error: Option<String>) {
+        let mut status = self.status.lock().unwrap();
+        status.state = state;
+        status.error = error;
+        status.finished_at = Some(now());
+    }
+}
+
+/// Background purge jobs, by id.
+pub struct PurgeJobs {
+    jobs: Mutex<HashMap<String, Arc<PurgeJob>>>,
+    finished: Mutex<VecDeque<String>>,
+    /// Events purged per batch.
+    pub batch_size: usize,
+}
+
+impl Default for PurgeJobs {
+    fn default() -> Self {
+        Self { jobs: Mutex::new(HashMap::new()), finished: Mutex::new(VecDeque::new()), batch_size: DEFAULT_BATCH_SIZE }
+    }
+}
+
+impl PurgeJobs {
+    /// Starts purging `event_ids` in the background. `skipped_held` seeds the
+    /// summary with the held events left out of `event_ids`; `include_held`
+    /// purges listed events even if they are put on hold meanwhile.
+    pub fn start(app: &Arc<AppState>, event_ids: Vec<String>, include_held: bool, skipped_held: u64) -> Arc<PurgeJob> {
+        let job = Arc::new(PurgeJob {
+            status: Mutex::new(PurgeJobStatus {
+                job_id: uuid::Uuid::new_v4().to_string(),
+                state: JobState::Running,
+                total: event_ids.len() as u64,
+                processed: 0,
+                created_at: now(),
+                finished_at: None,
+                error: None,
+                summary: PurgeSummary { skipped_held, ..PurgeSummary::default() },
+            }),
+            cancel: AtomicBool::new(false),
+        });
+        let id = job.status().job_id;
+        app.purge_jobs.jobs.lock().unwrap().insert(id.clone(), job.clone());
+        info!(job_id = %id, events = event_ids.len(), "purge job started");
+        let (app, running) = (app.clone(), job.clone());
+        tokio::spawn(async move {
+            let (state, error) = run(&app, &running, event_ids, include_held).await;
+            running.finish(state, error);
+            info!(job_id = %id, state = ?state, "purge job finished");
+            app.purge_jobs.retire(id);
+        });
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<PurgeJob>> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    /// Every known job, newest first.
+    pub fn list(&self) -> Vec<PurgeJobStatus> {
+        let mut all: Vec<PurgeJobStatus> = self.jobs.lock().unwrap().values().map(|j| j.status()).collect();
+        all.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        all
+    }
+
+    fn retire(&self, id: String) {
+        let mut finished = self.finished.lock().unwrap();
+        finished.push_back(id);
+        while finished.len() > MAX_FINISHED_JOBS {
+            if let Some(old) = finished.pop_front() { self.jobs.lock().unwrap().remove(&old); }
+        }
+    }
+}
+
+async fn run(app: &Arc<AppState>, job: &PurgeJob, event_ids: Vec<String>, include_held: bool) -> (JobState, Option<String>) {
+    for batch in event_ids.chunks(app.purge_jobs.batch_size.max(1)) {
+        if job.cancel.load(Ordering::Relaxed) { return (JobState::Cancelled, None); }
+        let criteria = PurgeCriteria { event_ids: Some(batch.to_vec()), trash: TrashScope::All, include_held, ..PurgeCriteria::default() };
+        match purge(app, criteria).await {
+            Ok(summary) => {
+                let mut status = job.status.lock().unwrap();
+                status.processed += batch.len() as u64;
+                status.summary.add(summary);
+            }
+            Err(e) => return (JobState::Failed, Some(e.to_string())),
+        }
+    }
+    (JobState::Completed, None)
+}
+
+/// How long trashed events can be restored, and how often the sweeper runs.
+#[derive(Debug, Clone, Copy)]
+pub struct TrashConfig {
+    pub retention: Duration,
+    pub sweep_interval: Duration,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention: Duration::from_secs(DEFAULT_TRASH_DAYS * 86_400), sweep_interval: DEFAULT_TRASH_SWEEP }
+    }
+}
+
+impl TrashConfig {
+    /// `VYASOAI_TRASH_DAYS` (0 expires events at the next sweep) and
+    /// `VYASOAI_TRASH_SWEEP_SECS` override the defaults.
+    pub fn from_env() -> Self {
+        let num = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        let d = Self::default();
+        Self {
+            retention: num("VYASOAI_TRASH_DAYS").map(|days| Duration::from_secs(days * 86_400)).unwrap_or(d.retention),
+            sweep_interval: num("VYASOAI_TRASH_SWEEP_SECS").filter(|&s| s > 0).map(Duration::from_secs).unwrap_or(d.sweep_interval),
+        }
+    }
+}
+
+/// Purges every trashed event whose restore window has ended. An event
+/// restored while the sweep runs is left alone, and held events stay in the
+/// trash until their hold is cleared.
+pub async fn sweep_trash(app: &Arc<AppState>) -> Result<PurgeSummary> {
+    let now = time::OffsetDateTime::now_utc();
+    let expired = {
+        let conn = app.db.lock().unwrap();
+        trash::expired(&conn, now)?
+    };
+    let mut summary = PurgeSummary::default();
+    for batch in expired.chunks(app.purge_jobs.batch_size.max(1)) {
+        let criteria = PurgeCriteria {
+            event_ids: Some(batch.to_vec()),
+            trash: TrashScope::ExpiredBy(trash::timestamp(now)),
+            ..PurgeCriteria::default()
+        };
+        summary.add(purge(app, criteria).await?);
+    }
+    if summary.deleted_events > 0 {
+        info!(events = summary.deleted_events, blobs = summary.deleted_blobs, "trash sweep purged expired events");
+    }
+    Ok(summary)
+}
+
+/// Sweeps the trash every `sweep_interval` in the background, starting now.
+/// Only holds the state weakly, so it stops once the daemon has dropped it and
+/// never keeps the ingest queue open past shutdown.
+pub fn start_trash_sweeper(app: &Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    let weak = Arc::downgrade(app);
+    let interval = app.trash.sweep_interval;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let Some(app) = weak.upgrade() else { break };
+            if let Err(e) = sweep_trash(&app).await { error!(%e, "trash sweep failed"); }
+        }
+    })
+}
+
+fn now() -> String {
+    time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap_or_default()
+}
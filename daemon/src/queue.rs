@@ -1,25 +1,143 @@
-use tokio::{sync::mpsc::Receiver, time::{Duration, Instant}};
-use tracing::{info, error};
-use std::path::PathBuf;
+use tokio::{sync::{mpsc::Receiver, Notify}, time::{Duration, Instant}};
+use tracing::{info, error, warn};
 
 use crate::handlers::{EventEnvelope, PrivacyFlag};
-use crate::storage::{db, blobs, Result as StorageResult};
-use crate::state::AppState;
-use std::sync::Arc;
-use tokio::process::Command;
-use serde_json::json;
-use uuid::Uuid;
+use crate::storage::{db, blobs, fts, processing::{self, ProcessingState}, Result as StorageResult};
+use crate::state::{AppState, IngestJob};
+use crate::feed::FeedKind;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
 
 const BATCH_SIZE: usize = 64;
 const FLUSH_INTERVAL_MS: u64 = 100;
+/// Drain rate is averaged over this trailing window.
+const DRAIN_WINDOW: Duration = Duration::from_secs(30);
+/// Retry-After bounds, in seconds; the upper bound also covers a stalled worker.
+const RETRY_AFTER_MIN: u64 = 1;
+const RETRY_AFTER_MAX: u64 = 60;
 
-pub fn start_worker(mut rx: Receiver<EventEnvelope>, state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
-    tokio::spawn(async move {
-        let mut buf: Vec<EventEnvelope> = Vec::with_capacity(BATCH_SIZE);
+/// Admission and drain counters for the ingest queue.
+#[derive(Default)]
+pub struct QueueStats {
+    accepted: AtomicU64,
+    dropped: AtomicU64,
+    drained: AtomicU64,
+    recent: Mutex<VecDeque<(Instant, u64)>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    pub depth: usize,
+    pub capacity: usize,
+    /// Events settled per second over the last 30 s.
+    pub drain_rate: f64,
+    pub accepted: u64,
+    /// Events refused with 429 because the queue was full.
+    pub dropped: u64,
+    pub drained: u64,
+    /// Accepted events not yet settled, including those waiting in the queue.
+    pub journal_pending: u64,
+}
+
+impl QueueStats {
+    pub fn record_accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_drained(&self, n: u64) {
+        self.drained.fetch_add(n, Ordering::Relaxed);
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        recent.push_back((now, n));
+        while recent.front().is_some_and(|(t, _)| now.duration_since(*t) > DRAIN_WINDOW) { recent.pop_front(); }
+    }
+
+    pub fn drain_rate(&self) -> f64 {
+        let now = Instant::now();
+        let recent = self.recent.lock().unwrap();
+        let n: u64 = recent.iter().filter(|(t, _)| now.duration_since(*t) <= DRAIN_WINDOW).map(|(_, n)| n).sum();
+        n as f64 / DRAIN_WINDOW.as_secs_f64()
+    }
+
+    /// Seconds until a queue of `depth` events should have room again.
+    pub fn retry_after(&self, depth: usize) -> u64 {
+        let rate = self.drain_rate();
+        if rate <= 0.0 { return RETRY_AFTER_MAX; }
+        ((depth as f64 / rate).ceil() as u64).clamp(RETRY_AFTER_MIN, RETRY_AFTER_MAX)
+    }
+
+    pub fn status(&self, state: &AppState) -> QueueStatus {
+        let journal_pending = {
+            let conn = state.db.lock().unwrap();
+            crate::storage::journal::len(&conn).unwrap_or(0)
+        };
+        QueueStatus {
+            depth: queue_depth(state),
+            capacity: state.queue_tx.max_capacity(),
+            drain_rate: self.drain_rate(),
+            accepted: self.accepted.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            drained: self.drained.load(Ordering::Relaxed),
+            journal_pending,
+        }
+    }
+}
+
+/// Jobs sent but not yet received by the worker.
+pub fn queue_depth(state: &AppState) -> usize {
+    state.queue_tx.max_capacity() - state.queue_tx.capacity()
+}
+
+/// Shutdown budget for processing the events still queued; whatever is left
+/// stays in the journal and is replayed on the next start.
+pub const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The running ingest worker.
+pub struct IngestWorker {
+    handle: tokio::task::JoinHandle<()>,
+    stop: Arc<Notify>,
+}
+
+impl IngestWorker {
+    /// Closes the queue to new events, processes the ones already queued and
+    /// waits for the worker to exit, for at most `timeout`. Returns whether it
+    /// drained in time. Senders may still be alive; they just get refused.
+    pub async fn shutdown(self, timeout: Duration) -> bool {
+        self.stop.notify_one();
+        match tokio::time::timeout(timeout, self.handle).await {
+            Ok(_) => true,
+            Err(_) => {
+                warn!(?timeout, "ingest worker did not drain in time; the journal keeps the rest");
+                false
+            }
+        }
+    }
+}
+
+pub fn start_worker(mut rx: Receiver<IngestJob>, state: Arc<AppState>) -> IngestWorker {
+    let stop = Arc::new(Notify::new());
+    let stopped = stop.clone();
+    let handle = tokio::spawn(async move {
+        let mut buf: Vec<IngestJob> = Vec::with_capacity(BATCH_SIZE);
         let mut next_flush = Instant::now() + Duration::from_millis(FLUSH_INTERVAL_MS);
+        let mut closing = false;
         loop {
             let timeout = next_flush.saturating_duration_since(Instant::now());
-            let recv = tokio::time::timeout(timeout, rx.recv()).await;
+            let recv = tokio::select! {
+                r = tokio::time::timeout(timeout, rx.recv()) => r,
+                _ = stopped.notified(), if !closing => {
+                    // `recv` yields what is buffered, then `None`.
+                    rx.close();
+                    closing = true;
+                    continue;
+                }
+            };
             match recv {
                 Ok(Some(ev)) => {
                     buf.push(ev);
@@ -38,135 +156,231 @@ pub fn start_worker(mut rx: Receiver<EventEnvelope>, state: Arc<AppState>) -> to
                 }
             }
         }
-    })
+    });
+    IngestWorker { handle, stop }
 }
 
-async fn flush_batch(state: Arc<AppState>, buf: &mut Vec<EventEnvelope>) {
-    if buf.is_empty() { return; }
-    let events = std::mem::take(buf);
-    let _ = tokio::task::spawn_blocking(move || {
-        for ev in events {
-            let res = process_event(state.clone(), ev);
-            if let Err(e) = res { error!(%e); }
-        }
-        Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
-    }).await;
+/// Re-queues events that were accepted but not settled before the last shutdown
+/// or crash. Call after `start_worker`, before serving.
+pub async fn replay_journal(state: &AppState) -> StorageResult<usize> {
+    let pending = {
+        let conn = state.db.lock().unwrap();
+        crate::storage::journal::pending(&conn)?
+    };
+    let n = pending.len();
+    for (seq, envelope, content) in pending {
+        state.queue_tx.send(IngestJob { envelope, content, journal_seq: Some(seq) }).await
+            .map_err(|e| -> Box<dyn std::error::Error + Send + Sync> { e.to_string().into() })?;
+    }
+    if n > 0 { info!(replayed = n, "replayed ingest journal"); }
+    Ok(n)
 }
 
-fn process_event(state: Arc<AppState>, ev: EventEnvelope) -> StorageResult<()> {
-    match ev.privacy_flag {
-        PrivacyFlag::NeverStore => {
-            {
+async fn flush_batch(state: Arc<AppState>, buf: &mut Vec<IngestJob>) {
+    if buf.is_empty() { return; }
+    let jobs = std::mem::take(buf);
+    let settled = jobs.len() as u64;
+    let store_state = state.clone();
+    // Persist the whole batch first so slow intel jobs never delay storage.
+    let stored = tokio::task::spawn_blocking(move || {
+        let state = store_state;
+        let mut stored = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let outcome = store_event(&state, &job);
+            // Stored, duplicate or rejected: either way it is settled. Errors stay
+            // journaled and are retried on the next start.
+            if let (Ok(_), Some(seq)) = (&outcome, job.journal_seq) {
                 let conn = state.db.lock().unwrap();
-                db::insert_event(&conn, &ev)?;
+                if let Err(e) = crate::storage::journal::remove(&conn, seq) { error!(%e); }
+            }
+            match outcome {
+                Ok(true) => stored.push(job.envelope),
+                Ok(false) => {}
+                Err(e) => error!(%e),
             }
-            return Ok(());
         }
-        _ => {}
-    }
-    let pointer = PathBuf::from(ev.content_pointer.clone());
-    if pointer.exists() {
-        let bytes = std::fs::read(&pointer)?;
-        let _ = blobs::save_blob(&bytes, &ev.content_hash)?;
+        stored
+    }).await.unwrap_or_default();
+    // The intel pool bounds how many of these run at once.
+    let results = futures_util::future::join_all(stored.into_iter().map(|ev| process_event(state.clone(), ev))).await;
+    for res in results {
+        if let Err(e) = res { error!(%e); }
     }
-    {
+    state.queue_stats.record_drained(settled);
+}
+
+/// Stores the blob (inline upload, or the pointer when readable) and the event row.
+/// Returns whether the event should be handed to the intelligence layer.
+fn store_event(state: &AppState, job: &IngestJob) -> StorageResult<bool> {
+    let ev = &job.envelope;
+    // A retried envelope is a no-op; a reused event_id with other content is
+    // refused before any blob is written.
+    let existing = {
         let conn = state.db.lock().unwrap();
-        db::insert_event(&conn, &ev)?;
-    }
-    // Intelligence handoff
-    let job_id = Uuid::new_v4().to_string();
-    let in_dir = PathBuf::from("data/intel/in");
-    let out_dir = PathBuf::from("data/intel/out");
-    let log_dir = PathBuf::from("data/intel/logs");
-    std::fs::create_dir_all(&in_dir)?;
-    std::fs::create_dir_all(&out_dir)?;
-    std::fs::create_dir_all(&log_dir)?;
-
-    // Resolve blob path from db (authoritative path)
-    let blob_path = {
+        db::find_event_hash(&conn, &ev.event_id)?
+    };
+    match existing {
+        Some(h) if h == ev.content_hash => return Ok(false),
+        Some(h) => return reject(state, ev, "conflict", format!("event_id already stored with content_hash {}", h)),
+        None => {}
+    }
+    if let PrivacyFlag::NeverStore = ev.privacy_flag {
+        let outcome = {
+            let conn = state.db.lock().unwrap();
+            db::insert_event(&conn, ev)?
+        };
+        if outcome == db::InsertOutcome::Inserted {
+            mark_stored(state, ev)?;
+            state.feed.publish(FeedKind::Stored, ev, None);
+        }
+        return Ok(false);
+    }
+    let content = match &job.content {
+        Some(bytes) => Some(std::borrow::Cow::Borrowed(bytes.as_slice())),
+        None => match state.path_policy.resolve(&ev.content_pointer) {
+            // Read the resolved path so a symlink swapped after the check can't redirect the read.
+            Ok(Some(path)) => Some(std::borrow::Cow::Owned(std::fs::read(&path)?)),
+            Ok(None) => None,
+            Err(reason) => return reject(state, ev, "path_denied", reason),
+        },
+    };
+    // Never store bytes under a hash they don't have: that would corrupt dedup in blob_index.
+    if let Some(bytes) = content.as_deref() {
+        if let Err(mismatch) = verify_content(ev, bytes) {
+            error!(event_id=%ev.event_id, kind=%mismatch.kind, "rejecting event: {}", mismatch.message);
+            let conn = state.db.lock().unwrap();
+            db::record_ingest_error(&conn, &mismatch)?;
+            processing::set_state(&conn, &ev.event_id, ProcessingState::Failed, Some(&mismatch.message))?;
+            return Ok(false);
+        }
+    }
+    let mut text = None;
+    // A blob this call wrote, to remove again if no event ends up using it.
+    let mut written = None;
+    if let Some(bytes) = content {
+        let indexed = {
+            let conn = state.db.lock().unwrap();
+            db::get_blob_index(&conn, &ev.content_hash)?
+        };
+        // Content already on disk under its hash is shared, not written again.
+        if !indexed.is_some_and(|(path, _)| std::path::Path::new(&path).exists()) && !blobs::blob_path(&ev.content_hash).exists() {
+            written = Some(blobs::save_blob(&bytes, &ev.content_hash)?);
+        }
+        // Sensitive content is stored but never enters the full-text index.
+        if let PrivacyFlag::Default = ev.privacy_flag { text = fts::extract_text(&bytes); }
+    }
+    let outcome = {
         let conn = state.db.lock().unwrap();
-        match db::get_blob_index(&conn, &ev.content_hash)? { Some((p, _)) => p, None => ev.content_pointer.clone() }
+        let outcome = db::insert_event(&conn, ev);
+        if let Ok(db::InsertOutcome::Inserted) = outcome {
+            if let Some(t) = text.as_deref() { fts::index_document(&conn, &ev.event_id, t)?; }
+        }
+        outcome
     };
-    let in_path = in_dir.join(format!("{}.json", job_id));
-    let out_path = out_dir.join(format!("{}.json", job_id));
-    let log_path = log_dir.join(format!("{}.log", job_id));
-    let envelope = json!({
-        "job_id": job_id,
-        "event_id": ev.event_id,
-        "blob_path": blob_path,
-        "content_type": "prose",
-        "source": ev.source,
-        "params": {"backend": "mock"},
-        "created_at": time::OffsetDateTime::now_utc().to_string()
-    });
-    std::fs::write(&in_path, serde_json::to_string(&envelope)?)?;
-
-    // Spawn Python CLI subprocess with timeout and retries
-    let max_retries = if cfg!(test) { 0usize } else { 3usize };
-    let timeout_ms = if cfg!(test) { 100u64 } else { 15_000u64 };
-    let mut attempt = 0usize;
-    let mut success = false;
-    while attempt < max_retries {
-        attempt += 1;
-        let mut cmd = Command::new("python3");
-        cmd.arg("-m").arg("intelligence.cli").arg("process").arg(&ev.event_id)
-            .arg("--job").arg(&job_id)
-            .arg("--infile").arg(&in_path)
-            .arg("--outfile").arg(&out_path)
-            .kill_on_drop(true);
-        let fut = cmd.output();
-        let res = tokio::runtime::Handle::current().block_on(async {
-            tokio::time::timeout(Duration::from_millis(timeout_ms), fut).await
-        });
-        match res {
-            Ok(Ok(out)) => {
-                if !out.stderr.is_empty() {
-                    let _ = std::fs::write(&log_path, out.stderr);
-                }
-                if out.status.success() {
-                    success = true;
-                    break;
-                } else {
-                    // Non-zero exit; retry
-                    info!(attempt, job_id, "intel subprocess failed; retrying");
-                }
-            }
-            Ok(Err(e)) => {
-                error!(%e, attempt, job_id, "intel subprocess spawn error");
-            }
-            Err(_) => {
-                info!(attempt, job_id, "intel subprocess timed out");
-            }
+    if let Some(path) = written {
+        if !matches!(outcome, Ok(db::InsertOutcome::Inserted)) { let _ = std::fs::remove_file(path); }
+    }
+    match outcome? {
+        db::InsertOutcome::Inserted => {
+            mark_stored(state, ev)?;
+            state.feed.publish(FeedKind::Stored, ev, None);
+            Ok(true)
+        }
+        db::InsertOutcome::Duplicate => Ok(false),
+        db::InsertOutcome::Conflict { existing_hash } => {
+            reject(state, ev, "conflict", format!("event_id already stored with content_hash {}", existing_hash))
         }
     }
+}
 
-    if success {
-        if let Ok(text) = std::fs::read_to_string(&out_path) {
-            if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
-                if v.get("status").and_then(|s| s.as_str()) == Some("ok") {
-                    if let (Some(event_id), Some(chunks_val)) = (v.get("event_id"), v.get("chunks")) {
-                        let eid = event_id.as_str().unwrap_or("").to_string();
-                        if let Some(arr) = chunks_val.as_array() {
-                            let rows: Vec<crate::storage::db::ChunkRow> = arr.iter().filter_map(|c| {
-                                Some(crate::storage::db::ChunkRow{
-                                    chunk_id: c.get("id")?.as_str()?.to_string(),
-                                    event_id: eid.clone(),
-                                    start_offset: c.get("start")?.as_i64()?,
-                                    end_offset: c.get("end")?.as_i64()?,
-                                    content_type: c.get("type")?.as_str()?.to_string(),
-                                })
-                            }).collect();
-                            {
-                                let conn = state.db.lock().unwrap();
-                                let _ = db::insert_chunks(&conn, &rows);
-                            }
-                        }
-                    }
-                }
-            }
+fn mark_stored(state: &AppState, ev: &EventEnvelope) -> StorageResult<()> {
+    let conn = state.db.lock().unwrap();
+    processing::set_state(&conn, &ev.event_id, ProcessingState::Stored, None)
+}
+
+/// Records why an event was not stored; always returns `Ok(false)` for `store_event`.
+fn reject(state: &AppState, ev: &EventEnvelope, kind: &str, message: String) -> StorageResult<bool> {
+    error!(event_id=%ev.event_id, kind, "rejecting event: {}", message);
+    let rejected = db::IngestError {
+        event_id: ev.event_id.clone(),
+        app: ev.app.clone(),
+        source: ev.source.clone(),
+        kind: kind.to_string(),
+        message,
+        declared_hash: ev.content_hash.clone(),
+        declared_size: ev.size_bytes as i64,
+        ..Default::default()
+    };
+    let conn = state.db.lock().unwrap();
+    db::record_ingest_error(&conn, &rejected)?;
+    processing::set_state(&conn, &ev.event_id, ProcessingState::Failed, Some(&rejected.message))?;
+    Ok(false)
+}
+
+/// Checks content bytes against the envelope's `size_bytes` and `content_hash`.
+/// The error describes the mismatch and is ready for `db::record_ingest_error`.
+pub fn verify_content(ev: &EventEnvelope, bytes: &[u8]) -> Result<(), Box<db::IngestError>> {
+    let mismatch = |kind: &str, message: String, actual_hash: Option<String>| Box::new(db::IngestError {
+        event_id: ev.event_id.clone(),
+        app: ev.app.clone(),
+        source: ev.source.clone(),
+        kind: kind.to_string(),
+        message,
+        declared_hash: ev.content_hash.clone(),
+        actual_hash,
+        declared_size: ev.size_bytes as i64,
+        actual_size: Some(bytes.len() as i64),
+        ..Default::default()
+    });
+    if bytes.len() as u64 != ev.size_bytes {
+        return Err(mismatch("size_mismatch", format!("content is {} bytes but size_bytes is {}", bytes.len(), ev.size_bytes), None));
+    }
+    let actual = crate::storage::hash::compute_sha256(bytes);
+    if !actual.eq_ignore_ascii_case(&ev.content_hash) {
+        return Err(mismatch("hash_mismatch", "content does not match content_hash".to_string(), Some(actual)));
+    }
+    Ok(())
+}
+
+/// Runs the post-ingest pipeline for a stored event and records the outcome.
+async fn process_event(state: Arc<AppState>, ev: EventEnvelope) -> StorageResult<()> {
+    {
+        let conn = state.db.lock().unwrap();
+        processing::set_state(&conn, &ev.event_id, ProcessingState::Indexing, None)?;
+    }
+    let outcome = state.pipeline.run(&state, &ev).await?;
+    let conn = state.db.lock().unwrap();
+    processing::add_attempts(&conn, &ev.event_id, outcome.ctx.attempts)?;
+    match outcome.failed {
+        None => {
+            processing::set_state(&conn, &ev.event_id, ProcessingState::Indexed, None)?;
+            processing::remove_dead_letter(&conn, &ev.event_id)?;
+            drop(conn);
+            state.feed.publish(FeedKind::Indexed, &ev, Some(outcome.ctx.chunks.len() as u64));
+        }
+        Some((stage, e)) => {
+            let e = format!("{}: {}", stage, e);
+            error!(event_id=%ev.event_id, error=%e, "processing failed; dead-lettering event");
+            processing::set_state(&conn, &ev.event_id, ProcessingState::Failed, Some(&e))?;
+            processing::dead_letter(&conn, &ev.event_id, &e, outcome.ctx.attempts)?;
         }
-    } else {
-        error!(job_id, event_id=%ev.event_id, "intel processing failed; marking event as failed and continuing");
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Re-runs the pipeline for stored events, one after another, off the
+/// ingest worker. Ids that no longer name a stored event drop their dead letter.
+pub fn reprocess(state: Arc<AppState>, event_ids: Vec<String>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        for id in event_ids {
+            let ev = {
+                let conn = state.db.lock().unwrap();
+                match db::get_event(&conn, &id) {
+                    Ok(ev) => ev,
+                    Err(_) => { let _ = processing::remove_dead_letter(&conn, &id); continue; }
+                }
+            };
+            if let Err(e) = process_event(state.clone(), ev).await { error!(%e, event_id=%id, "reprocessing failed"); }
+        }
+    })
+}
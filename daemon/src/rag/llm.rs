@@ -0,0 +1,114 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::storage::Result;
+
+/// Where an answer was generated; mirrors the app's `origin` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Origin {
+    Local,
+    Cloud,
+}
+
+/// A text-generation backend used to answer RAG queries.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    fn name(&self) -> &str;
+    fn origin(&self) -> Origin;
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String>;
+}
+
+/// Backend for any server exposing the OpenAI `/v1/chat/completions` API,
+/// such as llama.cpp server, Ollama or vLLM running on this machine.
+pub struct OpenAiCompatBackend {
+    base_url: String,
+    model: String,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatBackend {
+    pub fn new(base_url: &str, model: &str, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self { base_url: base_url.trim_end_matches('/').to_string(), model: model.to_string(), client })
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiCompatBackend {
+    fn name(&self) -> &str { "openai-compat" }
+
+    fn origin(&self) -> Origin {
+        let host = reqwest::Url::parse(&self.base_url).ok().and_then(|u| u.host_str().map(str::to_string));
+        match host.as_deref() {
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]") => Origin::Local,
+            _ => Origin::Cloud,
+        }
+    }
+
+    async fn complete(&self, system: &str, prompt: &str) -> Result<String> {
+        let body = json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+            "temperature": 0.2,
+            "stream": false,
+        });
+        let resp = self.client.post(format!("{}/v1/chat/completions", self.base_url)).json(&body).send().await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(format!("llm backend returned {}", status).into());
+        }
+        let v: serde_json::Value = resp.json().await?;
+        v["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "llm response missing choices[0].message.content".into())
+    }
+}
+
+/// Fallback used when no model is configured: answers with the retrieved
+/// excerpts themselves so the endpoint stays useful fully offline.
+pub struct ExtractiveBackend;
+
+#[async_trait]
+impl LlmBackend for ExtractiveBackend {
+    fn name(&self) -> &str { "extractive" }
+    fn origin(&self) -> Origin { Origin::Local }
+
+    async fn complete(&self, _system: &str, prompt: &str) -> Result<String> {
+        let context = prompt.split_once("\n\nQuestion:").map(|(c, _)| c).unwrap_or(prompt);
+        let excerpts: Vec<&str> = context
+            .lines()
+            .filter(|l| !l.starts_with("--- Chunk") && !l.trim().is_empty() && *l != "Context:")
+            .take(5)
+            .collect();
+        if excerpts.is_empty() {
+            return Ok("No matching memories found.".to_string());
+        }
+        Ok(format!("No language model is configured. Most relevant excerpts:\n{}", excerpts.iter().map(|e| format!("- {}", e.trim())).collect::<Vec<_>>().join("\n")))
+    }
+}
+
+/// Picks the backend from `VYASOAI_LLM_URL` / `VYASOAI_LLM_MODEL`, falling back
+/// to the extractive backend when no URL is set.
+pub fn backend_from_env() -> std::sync::Arc<dyn LlmBackend> {
+    let timeout_ms = std::env::var("VYASOAI_LLM_TIMEOUT_MS").ok().and_then(|v| v.parse().ok()).unwrap_or(60_000u64);
+    match std::env::var("VYASOAI_LLM_URL") {
+        Ok(url) if !url.trim().is_empty() => {
+            let model = std::env::var("VYASOAI_LLM_MODEL").unwrap_or_else(|_| "local".to_string());
+            match OpenAiCompatBackend::new(&url, &model, Duration::from_millis(timeout_ms)) {
+                Ok(b) => std::sync::Arc::new(b),
+                Err(e) => {
+                    tracing::error!(%e, "failed to build llm backend; using extractive fallback");
+                    std::sync::Arc::new(ExtractiveBackend)
+                }
+            }
+        }
+        _ => std::sync::Arc::new(ExtractiveBackend),
+    }
+}
@@ -0,0 +1,93 @@
+//! Retrieval-augmented answers served by the daemon.
+//!
+//! Mirrors `intelligence/rag/context_builder.py`: retrieved chunks are packed
+//! best-first into a word-count token budget, compiled into a context block and
+//! handed to a pluggable `LlmBackend`. Every chunk that made it into the
+//! context is returned as provenance.
+pub mod llm;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::fts::SearchHit;
+use crate::storage::Result;
+use llm::{LlmBackend, Origin};
+
+pub const DEFAULT_TOP_K: u32 = 5;
+pub const DEFAULT_MAX_TOKENS: usize = 2048;
+const EXCERPT_MAX_CHARS: usize = 280;
+const SYSTEM_PROMPT: &str = "You answer questions using only the user's own memories given as context. \
+If the context does not contain the answer, say so.";
+
+/// Source of one answer fragment; field names match the app's `Provenance` type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub doc_id: String,
+    pub chunk_id: Option<String>,
+    pub score: f64,
+    pub excerpt: String,
+    pub source: String,
+    pub timestamp: String,
+    pub start_offset: i64,
+    pub end_offset: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scores: Option<crate::retrieval::ScoreBreakdown>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagAnswer {
+    pub message: String,
+    pub provenance: Vec<Provenance>,
+    pub origin: Origin,
+}
+
+pub struct RagContext {
+    pub context: String,
+    pub used: Vec<SearchHit>,
+    pub tokens: usize,
+}
+
+/// Packs hits, highest score first, until `max_tokens` (whitespace words) is reached.
+pub fn build_context(mut hits: Vec<SearchHit>, max_tokens: usize) -> RagContext {
+    hits.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    let mut used = Vec::new();
+    let mut tokens = 0usize;
+    for h in hits {
+        let t = h.text.split_whitespace().count().max(1);
+        if tokens + t > max_tokens { break; }
+        tokens += t;
+        used.push(h);
+    }
+    let context = used
+        .iter()
+        .enumerate()
+        .map(|(i, h)| format!("--- Chunk {} (event_id: {}) ---\n{}\n", i + 1, h.event_id, h.text))
+        .collect::<String>();
+    RagContext { context, used, tokens }
+}
+
+pub async fn answer(backend: &dyn LlmBackend, query: &str, hits: Vec<SearchHit>, max_tokens: usize) -> Result<RagAnswer> {
+    let ctx = build_context(hits, max_tokens);
+    let prompt = format!("Context:\n{}\n\nQuestion: {}", ctx.context, query);
+    let message = backend.complete(SYSTEM_PROMPT, &prompt).await?;
+    let provenance = ctx.used.into_iter().map(|h| Provenance {
+        excerpt: excerpt(&h.text),
+        source: format!("{} — {}", h.app, h.source),
+        doc_id: h.event_id,
+        chunk_id: h.chunk_id,
+        score: h.score,
+        timestamp: h.timestamp,
+        start_offset: h.start_offset,
+        end_offset: h.end_offset,
+        scores: h.scores,
+    }).collect();
+    Ok(RagAnswer { message, provenance, origin: backend.origin() })
+}
+
+fn excerpt(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(EXCERPT_MAX_CHARS) {
+        Some((i, _)) => format!("{}…", &flat[..i]),
+        None => flat,
+    }
+}
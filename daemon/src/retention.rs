@@ -0,0 +1,123 @@
+//! Scheduled enforcement of retention rules.
+//!
+//! A sweep evaluates every enabled rule with a `keep_days` against one clock
+//! reading, purges the events it expires in batches through `purge::purge`
+//! (bypassing the trash) and records a `retention_runs` row per rule, also when
+//! the rule found nothing. Rules keeping events forever only protect events
+//! from other rules and are not reported. Held events are never expired; each
+//! run counts the ones its rule skipped.
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use tracing::{error, info};
+
+use crate::purge::PurgeSummary;
+use crate::state::AppState;
+use crate::storage::db::{self, PurgeCriteria};
+use crate::storage::retention::{self, RetentionRule, RuleRun};
+use crate::storage::{trash, Result};
+
+const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionConfig {
+    pub sweep_interval: Duration,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { sweep_interval: DEFAULT_SWEEP_INTERVAL }
+    }
+}
+
+impl RetentionConfig {
+    /// `VYASOAI_RETENTION_SWEEP_SECS` overrides the default hourly sweep.
+    pub fn from_env() -> Self {
+        let secs = std::env::var("VYASOAI_RETENTION_SWEEP_SECS").ok().and_then(|v| v.parse::<u64>().ok()).filter(|&s| s > 0);
+        Self { sweep_interval: secs.map(Duration::from_secs).unwrap_or(DEFAULT_SWEEP_INTERVAL) }
+    }
+}
+
+/// One sweep, rule by rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionReport {
+    pub run_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub rules: Vec<RuleRun>,
+}
+
+/// Applies every enabled rule now. A rule whose purge fails is reported with
+/// its `error` and the sweep moves on to the next rule.
+pub async fn sweep(app: &Arc<AppState>) -> Result<RetentionReport> {
+    let now = OffsetDateTime::now_utc();
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let started_at = trash::timestamp(now);
+    let rules: Vec<RetentionRule> = {
+        let conn = app.db.lock().unwrap();
+        retention::list_rules(&conn)?
+    };
+    let enabled: Vec<RetentionRule> = rules.into_iter().filter(|r| r.spec.enabled).collect();
+    let cutoff = |r: &RetentionRule| r.spec.keep_days.map(|d| trash::timestamp(now - Duration::from_secs(u64::from(d) * 86_400)));
+
+    let mut runs = Vec::new();
+    for rule in &enabled {
+        let Some(criteria) = retention::expiry_criteria(rule, &enabled, cutoff) else { continue };
+        let rule_started = trash::timestamp(OffsetDateTime::now_utc());
+        let (candidates, skipped_held) = {
+            let conn = app.db.lock().unwrap();
+            (db::purge_candidates(&conn, &criteria)?, db::count_held(&conn, &criteria)?)
+        };
+        let mut summary = PurgeSummary { skipped_held, ..PurgeSummary::default() };
+        let mut error = None;
+        for batch in candidates.chunks(app.purge_jobs.batch_size.max(1)) {
+            // The rule's filter still applies, so events kept since the
+            // candidates were listed are left alone.
+            let batch_criteria = PurgeCriteria { event_ids: Some(batch.to_vec()), ..criteria.clone() };
+            match crate::purge::purge(app, batch_criteria).await {
+                Ok(s) => summary.add(s),
+                Err(e) => {
+                    error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        let mut run = RuleRun {
+            id: 0,
+            run_id: run_id.clone(),
+            rule_id: rule.rule_id.clone(),
+            cutoff: criteria.end.clone().unwrap_or_default(),
+            matched: candidates.len() as u64,
+            summary: serde_json::json!(summary),
+            error,
+            started_at: rule_started,
+            finished_at: trash::timestamp(OffsetDateTime::now_utc()),
+        };
+        run.id = {
+            let conn = app.db.lock().unwrap();
+            retention::record_run(&conn, &run)?
+        };
+        if run.matched > 0 || run.error.is_some() {
+            info!(rule_id = %run.rule_id, matched = run.matched, deleted = summary.deleted_events, error = ?run.error, "retention rule applied");
+        }
+        runs.push(run);
+    }
+    Ok(RetentionReport { run_id, started_at, finished_at: trash::timestamp(OffsetDateTime::now_utc()), rules: runs })
+}
+
+/// Sweeps every `sweep_interval` in the background, starting now. Like the
+/// trash sweeper, it stops once the daemon has dropped its state.
+pub fn start_sweeper(app: &Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    let weak = Arc::downgrade(app);
+    let interval = app.retention.sweep_interval;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let Some(app) = weak.upgrade() else { break };
+            if let Err(e) = sweep(&app).await { error!(%e, "retention sweep failed"); }
+        }
+    })
+}
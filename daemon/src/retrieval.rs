@@ -0,0 +1,223 @@
+//! Hybrid retrieval over the full-text and vector indexes.
+//!
+//! A query runs against FTS5 (BM25) and the HNSW vector index in parallel. The
+//! two ranked lists are fused with reciprocal-rank fusion: a hit scores
+//! `sum(1 / (k + rank))` over the lists it appears in, so BM25 and cosine
+//! scores never need to be put on one scale. The fused score is then scaled by
+//! a recency prior, `(1 - w) + w * 0.5^(age / half_life)`, where the age comes
+//! from `events.timestamp`; a weight of 0 turns the prior off.
+//!
+//! The query is embedded by the daemon's embedding provider. When that fails,
+//! its model isn't the one the index holds, or the index is empty, retrieval
+//! degrades to full-text only.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::warn;
+
+use crate::embeddings;
+use crate::index::VectorHit;
+use crate::state::AppState;
+use crate::storage::fts::{self, SearchHit, SearchQuery};
+use crate::storage::Result;
+
+pub const DEFAULT_RRF_K: f64 = 60.0;
+pub const DEFAULT_HALF_LIFE_DAYS: f64 = 30.0;
+pub const DEFAULT_RECENCY_WEIGHT: f64 = 0.3;
+/// Each ranker contributes this many candidates per requested hit, so hits that
+/// rank low in one list but well in the other still get fused.
+const CANDIDATES_PER_HIT: u32 = 4;
+const MIN_CANDIDATES: u32 = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Both rankers, fused.
+    #[default]
+    Hybrid,
+    /// Full-text only.
+    Lexical,
+    /// Vector similarity only.
+    Vector,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RetrievalConfig {
+    pub rrf_k: f64,
+    pub half_life_days: f64,
+    /// Share of the score subject to recency, in `[0, 1]`.
+    pub recency_weight: f64,
+}
+
+impl Default for RetrievalConfig {
+    fn default() -> Self {
+        Self { rrf_k: DEFAULT_RRF_K, half_life_days: DEFAULT_HALF_LIFE_DAYS, recency_weight: DEFAULT_RECENCY_WEIGHT }
+    }
+}
+
+/// Per-request overrides of the recency prior.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RecencyParams {
+    pub half_life_days: Option<f64>,
+    pub weight: Option<f64>,
+}
+
+impl RetrievalConfig {
+    /// `VYASOAI_RRF_K`, `VYASOAI_RECENCY_HALF_LIFE_DAYS` and `VYASOAI_RECENCY_WEIGHT`
+    /// override the defaults; invalid values are ignored.
+    pub fn from_env() -> Self {
+        let num = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<f64>().ok());
+        let d = Self::default();
+        let config = Self {
+            rrf_k: num("VYASOAI_RRF_K").unwrap_or(d.rrf_k),
+            half_life_days: num("VYASOAI_RECENCY_HALF_LIFE_DAYS").unwrap_or(d.half_life_days),
+            recency_weight: num("VYASOAI_RECENCY_WEIGHT").unwrap_or(d.recency_weight),
+        };
+        match config.validate() {
+            Ok(()) => config,
+            Err(e) => {
+                warn!(%e, "invalid retrieval settings; using defaults");
+                d
+            }
+        }
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if !(self.rrf_k.is_finite() && self.rrf_k >= 0.0) { return Err("rrf_k must be >= 0".to_string()); }
+        if !(self.half_life_days.is_finite() && self.half_life_days > 0.0) { return Err("half_life_days must be > 0".to_string()); }
+        if !(0.0..=1.0).contains(&self.recency_weight) { return Err("recency weight must be between 0 and 1".to_string()); }
+        Ok(())
+    }
+
+    /// This config with a request's overrides applied.
+    pub fn with(&self, params: Option<&RecencyParams>) -> std::result::Result<Self, String> {
+        let Some(p) = params else { return Ok(*self) };
+        let config = Self {
+            half_life_days: p.half_life_days.unwrap_or(self.half_life_days),
+            recency_weight: p.weight.unwrap_or(self.recency_weight),
+            ..*self
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Multiplier for a hit stored at `timestamp`; 1 for now (and the future),
+    /// tending to `1 - weight` with age. Unparseable timestamps get no penalty.
+    pub fn recency(&self, timestamp: &str, now: OffsetDateTime) -> f64 {
+        let Ok(ts) = OffsetDateTime::parse(timestamp, &Rfc3339) else { return 1.0 };
+        let age_days = ((now - ts).as_seconds_f64() / 86_400.0).max(0.0);
+        (1.0 - self.recency_weight) + self.recency_weight * 0.5f64.powf(age_days / self.half_life_days)
+    }
+}
+
+/// How a fused hit was scored. Ranks are 1-based positions in each ranker's list.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// Negated bm25 of the full-text match.
+    pub bm25: Option<f64>,
+    pub bm25_rank: Option<usize>,
+    /// Cosine similarity of the chunk to the query.
+    pub vector: Option<f32>,
+    pub vector_rank: Option<usize>,
+    pub rrf: f64,
+    pub recency: f64,
+    /// `rrf * recency`; also the hit's `score`.
+    pub fused: f64,
+}
+
+/// Fuses a full-text and a vector ranking (each best first) into the top
+/// `limit` hits. Hits are matched by chunk id, or by event for whole-document
+/// rows; `vector` pairs each hydrated hit with its cosine similarity.
+pub fn fuse(lexical: Vec<SearchHit>, vector: Vec<(SearchHit, f32)>, config: &RetrievalConfig, now: OffsetDateTime, limit: usize) -> Vec<SearchHit> {
+    let key = |h: &SearchHit| h.chunk_id.clone().unwrap_or_else(|| h.event_id.clone());
+    let mut fused: Vec<SearchHit> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    for (rank, mut hit) in lexical.into_iter().enumerate() {
+        if by_key.contains_key(&key(&hit)) { continue; }
+        hit.scores = Some(ScoreBreakdown { bm25: Some(hit.score), bm25_rank: Some(rank + 1), ..Default::default() });
+        by_key.insert(key(&hit), fused.len());
+        fused.push(hit);
+    }
+    for (rank, (hit, similarity)) in vector.into_iter().enumerate() {
+        let i = *by_key.entry(key(&hit)).or_insert_with(|| {
+            fused.push(SearchHit { scores: Some(ScoreBreakdown::default()), ..hit });
+            fused.len() - 1
+        });
+        let scores = fused[i].scores.get_or_insert_with(ScoreBreakdown::default);
+        if scores.vector_rank.is_none() {
+            scores.vector = Some(similarity);
+            scores.vector_rank = Some(rank + 1);
+        }
+    }
+    for hit in &mut fused {
+        let recency = config.recency(&hit.timestamp, now);
+        let scores = hit.scores.get_or_insert_with(ScoreBreakdown::default);
+        scores.rrf = [scores.bm25_rank, scores.vector_rank].iter().flatten()
+            .map(|&r| 1.0 / (config.rrf_k + r as f64))
+            .sum();
+        scores.recency = recency;
+        scores.fused = scores.rrf * recency;
+        hit.score = scores.fused;
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused.truncate(limit);
+    fused
+}
+
+/// Hits for a query, plus why the vector side was skipped when it was.
+pub struct Retrieval {
+    pub hits: Vec<SearchHit>,
+    pub vector_error: Option<String>,
+}
+
+/// Runs `q` in `mode` against the daemon's indexes; `q.limit` caps the fused hits.
+pub async fn retrieve(app: &Arc<AppState>, q: &SearchQuery, mode: Mode, config: &RetrievalConfig) -> Result<Retrieval> {
+    let limit = q.limit as usize;
+    let candidates = SearchQuery { limit: (q.limit * CANDIDATES_PER_HIT).max(MIN_CANDIDATES), ..q.clone() };
+    let lexical = async {
+        if mode == Mode::Vector { return Ok(Vec::new()); }
+        let (app, cq) = (app.clone(), candidates.clone());
+        tokio::task::spawn_blocking(move || {
+            let conn = app.db.lock().unwrap();
+            fts::search(&conn, &cq)
+        }).await?
+    };
+    let vector = async {
+        if mode == Mode::Lexical || app.vectors.is_empty() { return Ok(Vec::new()); }
+        vector_candidates(app, &q.query, candidates.limit as usize).await
+    };
+    let (lexical, vector) = tokio::join!(lexical, vector);
+    let lexical = lexical?;
+    let (vector_hits, vector_error) = match vector {
+        Ok(h) => (h, None),
+        Err(e) => {
+            warn!(%e, "vector retrieval failed; using full-text results only");
+            (Vec::new(), Some(e.to_string()))
+        }
+    };
+    // Vector hits carry ids only; fetch their rows, dropping those the filters exclude.
+    let vector = if vector_hits.is_empty() { Vec::new() } else {
+        let ids: Vec<String> = vector_hits.iter().map(|h| h.chunk_id.clone()).collect();
+        let mut rows = {
+            let conn = app.db.lock().unwrap();
+            fts::chunk_rows(&conn, &ids, &candidates)?
+        };
+        vector_hits.into_iter().filter_map(|h| rows.remove(&h.chunk_id).map(|row| (row, h.score))).collect()
+    };
+    Ok(Retrieval { hits: fuse(lexical, vector, config, OffsetDateTime::now_utc(), limit), vector_error })
+}
+
+/// Embeds the query with the daemon's provider and searches the vector index.
+async fn vector_candidates(app: &Arc<AppState>, query: &str, k: usize) -> Result<Vec<VectorHit>> {
+    let model = app.embedder.model_version();
+    let indexed = app.vectors.status().model_version.unwrap_or_default();
+    if model != indexed {
+        return Err(format!("queries are embedded with {} but the index holds {}", model, indexed).into());
+    }
+    let vector = embeddings::embed(app.embedder.as_ref(), &[query.to_string()]).await?
+        .pop().ok_or("embedding provider returned no vector")?;
+    Ok(app.vectors.search(&vector, k))
+}
@@ -1,13 +1,47 @@
-use axum::{routing::{get, post}, Router};
+use axum::{extract::DefaultBodyLimit, routing::{get, post}, Router};
 
-use crate::handlers::{health, post_event, get_mem, purge};
+use crate::handlers::{health, post_event, post_events_verb, get_mem, get_mem_content, get_mem_status, get_mem_stages, similar_memories, UPLOAD_MAX_BYTES, purge, list_purge_jobs, get_purge_job, cancel_purge_job, list_trash, restore_trash, sweep_trash, set_hold, clear_hold, list_holds, list_retention_rules, create_retention_rule, get_retention_rule, update_retention_rule, delete_retention_rule, run_retention, list_retention_runs, timeline, search_memories, index_status, vector_search, rag_query, pause_capture, resume_capture, capture_status, queue_status, intel_status, ingest_errors, list_dead_letters, retry_dead_letters, retry_dead_letter, feed_sse, feed_ws};
 use crate::state::AppState;
 
 pub fn router(app_state: std::sync::Arc<AppState>) -> Router {
     Router::new()
         .route("/v1/health", get(health))
         .route("/v1/events", post(post_event))
+        // Matches `/v1/events:batch` and `/v1/events:upload`.
+        .route("/v1/events:verb", post(post_events_verb).layer(DefaultBodyLimit::max(UPLOAD_MAX_BYTES)))
         .route("/v1/mem/:id", get(get_mem))
+        .route("/v1/mem/:id/content", get(get_mem_content))
+        .route("/v1/mem/:id/status", get(get_mem_status))
+        .route("/v1/mem/:id/stages", get(get_mem_stages))
+        .route("/v1/mem/:id/similar", get(similar_memories))
+        .route("/v1/mem/:id/hold", post(set_hold).delete(clear_hold))
+        .route("/v1/holds", get(list_holds))
         .route("/v1/purge", post(purge))
+        .route("/v1/purge/jobs", get(list_purge_jobs))
+        .route("/v1/purge/jobs/:id", get(get_purge_job))
+        .route("/v1/purge/jobs/:id/cancel", post(cancel_purge_job))
+        .route("/v1/trash", get(list_trash))
+        .route("/v1/trash/restore", post(restore_trash))
+        .route("/v1/trash/sweep", post(sweep_trash))
+        .route("/v1/retention/rules", get(list_retention_rules).post(create_retention_rule))
+        .route("/v1/retention/rules/:id", get(get_retention_rule).put(update_retention_rule).delete(delete_retention_rule))
+        .route("/v1/retention/run", post(run_retention))
+        .route("/v1/retention/runs", get(list_retention_runs))
+        .route("/v1/timeline", get(timeline))
+        .route("/v1/memories/search", post(search_memories))
+        .route("/v1/index/status", get(index_status))
+        .route("/v1/index/search", post(vector_search))
+        .route("/v1/rag/query", post(rag_query))
+        .route("/v1/pause", post(pause_capture))
+        .route("/v1/resume", post(resume_capture))
+        .route("/v1/capture/status", get(capture_status))
+        .route("/v1/queue/status", get(queue_status))
+        .route("/v1/intel/status", get(intel_status))
+        .route("/v1/ingest/errors", get(ingest_errors))
+        .route("/v1/dead-letters", get(list_dead_letters))
+        .route("/v1/dead-letters/retry", post(retry_dead_letters))
+        .route("/v1/dead-letters/:event_id/retry", post(retry_dead_letter))
+        .route("/v1/feed", get(feed_sse))
+        .route("/v1/feed/ws", get(feed_ws))
         .with_state(app_state)
 }
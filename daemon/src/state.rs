@@ -6,10 +6,71 @@ pub struct AppState {
     pub db: Arc<Mutex<rusqlite::Connection>>, 
     pub queue_tx: Sender<IngestJob>,
     pub key_manager: Option<KeyManager>,
+    pub llm: Arc<dyn crate::rag::llm::LlmBackend>,
+    pub capture: Arc<Mutex<crate::capture::CaptureState>>,
+    pub path_policy: Arc<crate::path_policy::PathPolicy>,
+    pub feed: Arc<crate::feed::Feed>,
+    pub queue_stats: Arc<crate::queue::QueueStats>,
+    pub intel: Arc<crate::intel::IntelPool>,
+    pub pipeline: Arc<crate::pipeline::Pipeline>,
+    /// Chunk embeddings; in memory unless replaced with a persisted index.
+    pub vectors: Arc<crate::index::VectorIndex>,
+    /// Embeds chunks in the `embed` stage and queries for hybrid retrieval.
+    pub embedder: Arc<dyn crate::embeddings::EmbeddingProvider>,
+    /// Fusion and recency defaults for hybrid search and RAG.
+    pub retrieval: crate::retrieval::RetrievalConfig,
+    pub purge_jobs: Arc<crate::purge::PurgeJobs>,
+    pub trash: crate::purge::TrashConfig,
+    pub retention: crate::retention::RetentionConfig,
 }
 
-pub type IngestJob = crate::handlers::EventEnvelope;
+impl AppState {
+    /// Builds state around an open connection; optional services are configured from the environment.
+    pub fn new(conn: rusqlite::Connection, queue_tx: Sender<IngestJob>, key_manager: Option<KeyManager>) -> Self {
+        let capture = crate::capture::CaptureState::load(&conn).unwrap_or_default();
+        let path_policy = crate::path_policy::PathPolicy::from_env().unwrap_or_else(|e| {
+            // Fail closed: a bad VYASOAI_CAPTURE_DENY glob must not widen what can be read.
+            tracing::error!(%e, "invalid capture path policy; refusing all content pointers");
+            crate::path_policy::PathPolicy::default()
+        });
+        Self {
+            db: Arc::new(Mutex::new(conn)),
+            queue_tx,
+            key_manager,
+            llm: crate::rag::llm::backend_from_env(),
+            capture: Arc::new(Mutex::new(capture)),
+            path_policy: Arc::new(path_policy),
+            feed: Arc::new(crate::feed::Feed::new()),
+            queue_stats: Arc::new(crate::queue::QueueStats::default()),
+            intel: Arc::new(crate::intel::IntelPool::from_env()),
+            pipeline: Arc::new(crate::pipeline::Pipeline::from_env()),
+            vectors: Arc::new(crate::index::VectorIndex::in_memory()),
+            embedder: crate::embeddings::provider_from_env(),
+            retrieval: crate::retrieval::RetrievalConfig::from_env(),
+            purge_jobs: Arc::new(crate::purge::PurgeJobs::default()),
+            trash: crate::purge::TrashConfig::from_env(),
+            retention: crate::retention::RetentionConfig::from_env(),
+        }
+    }
+}
+
+/// An admitted event plus, for uploads, its content bytes. Without inline
+/// content the worker reads the blob from `envelope.content_pointer`.
+#[derive(Debug, Clone)]
+pub struct IngestJob {
+    pub envelope: crate::handlers::EventEnvelope,
+    pub content: Option<Vec<u8>>,
+    /// `ingest_journal` row to clear once the event is persisted.
+    pub journal_seq: Option<i64>,
+}
+
+impl From<crate::handlers::EventEnvelope> for IngestJob {
+    fn from(envelope: crate::handlers::EventEnvelope) -> Self {
+        Self { envelope, content: None, journal_seq: None }
+    }
+}
 
 #[derive(Clone)]
 pub struct KeyManager { pub key: [u8; 32] }
-impl KeyManager { pub fn new() -> Self { Self { key: crate::storage::crypto::derive_key() } } }
\ No newline at end of file
+impl KeyManager { pub fn new() -> Self { Self { key: crate::storage::crypto::derive_key() } } }
+impl Default for KeyManager { fn default() -> Self { Self::new() } }
\ No newline at end of file
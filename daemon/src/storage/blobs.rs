@@ -4,20 +4,24 @@ use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
+/// Where `save_blob` stores content with `hash` today.
+pub fn blob_path(hash: &str) -> PathBuf {
+    let now = time::OffsetDateTime::now_utc();
+    let (y, m, d) = (now.year(), now.month() as u8, now.day());
+    PathBuf::from(format!("data/blobs/{:04}/{:02}/{:02}/{}.zst.enc", y, m, d, hash))
+}
+
 /// Save a blob with zstd compression then AES-256-GCM encryption.
 /// Path is deterministic based on current date and the provided `hash`.
 /// Returns the filesystem path to the stored blob.
 pub fn save_blob(content: &[u8], hash: &str) -> Result<PathBuf> {
-    let now = time::OffsetDateTime::now_utc();
-    let (y, m, d) = (now.year(), now.month() as u8, now.day());
-    let dir = PathBuf::from(format!("data/blobs/{:04}/{:02}/{:02}", y, m, d));
-    fs::create_dir_all(&dir)?;
+    let path = blob_path(hash);
+    if let Some(dir) = path.parent() { fs::create_dir_all(dir)?; }
 
     // Compress then encrypt
     let compressed = zstd::stream::encode_all(std::io::Cursor::new(content), 3)?; // level 3: good balance
     let cipher = encrypt_bytes(&compressed)?;
 
-    let path = dir.join(format!("{}.zst.enc", hash));
     let mut f = fs::File::create(&path)?;
     f.write_all(&cipher)?;
     Ok(path)
@@ -33,6 +37,55 @@ pub fn load_blob(path: &Path) -> Result<Vec<u8>> {
     Ok(decompressed)
 }
 
+/// Load the first `max_bytes` of a blob as text, or `None` if the content is
+/// not UTF-8. Only that much is decompressed; a character cut at the limit is
+/// dropped.
+pub fn load_text_prefix(path: &Path, max_bytes: usize) -> Result<Option<String>> {
+    let decrypted = decrypt_bytes(&fs::read(path)?)?;
+    let mut prefix = Vec::new();
+    zstd::stream::Decoder::new(&decrypted[..])?.take(max_bytes as u64).read_to_end(&mut prefix)?;
+    let valid = match std::str::from_utf8(&prefix) {
+        Ok(_) => prefix.len(),
+        // Incomplete, not invalid: the limit split a character.
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => return Ok(None),
+    };
+    prefix.truncate(valid);
+    Ok(String::from_utf8(prefix).ok())
+}
+
+/// Best-effort media type from the leading bytes of decoded content.
+pub fn sniff_content_type(bytes: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+    if let Some((_, ct)) = MAGIC.iter().find(|(m, _)| bytes.starts_with(m)) {
+        return ct;
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => {
+            let head: String = text.trim_start().chars().take(15).collect::<String>().to_ascii_lowercase();
+            if head.starts_with("<!doctype html") || head.starts_with("<html") {
+                "text/html; charset=utf-8"
+            } else if (head.starts_with('{') || head.starts_with('[')) && serde_json::from_str::<serde::de::IgnoredAny>(text).is_ok() {
+                "application/json"
+            } else {
+                "text/plain; charset=utf-8"
+            }
+        }
+        Err(_) => "application/octet-stream",
+    }
+}
+
 /// Helper to ensure base directories exist.
 pub fn ensure_blob_base() -> Result<()> {
     fs::create_dir_all("data/blobs")?;
@@ -38,6 +38,118 @@ CREATE TABLE IF NOT EXISTS chunks (
   FOREIGN KEY(event_id) REFERENCES events(event_id)
 );
 
+CREATE VIRTUAL TABLE IF NOT EXISTS memory_fts USING fts5(
+  body,
+  event_id UNINDEXED,
+  chunk_id UNINDEXED,
+  start_offset UNINDEXED,
+  end_offset UNINDEXED,
+  tokenize = 'unicode61'
+);
+
+CREATE TABLE IF NOT EXISTS capture_state (
+  scope TEXT NOT NULL,
+  scope_value TEXT NOT NULL,
+  until TEXT,
+  updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (scope, scope_value)
+);
+
+CREATE TABLE IF NOT EXISTS ingest_errors (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  event_id TEXT,
+  app TEXT,
+  source TEXT,
+  kind TEXT,
+  message TEXT,
+  declared_hash TEXT,
+  actual_hash TEXT,
+  declared_size INTEGER,
+  actual_size INTEGER,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS event_status (
+  event_id TEXT PRIMARY KEY,
+  state TEXT NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_error TEXT,
+  updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS dead_letters (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  event_id TEXT NOT NULL UNIQUE,
+  error TEXT NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+  updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS stage_runs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  event_id TEXT NOT NULL,
+  stage TEXT NOT NULL,
+  status TEXT NOT NULL,
+  output TEXT,
+  error TEXT,
+  duration_ms INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_stage_runs_event ON stage_runs(event_id);
+
+CREATE TABLE IF NOT EXISTS ingest_journal (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  event_id TEXT NOT NULL,
+  envelope TEXT NOT NULL,
+  content BLOB,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS trash (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  event_id TEXT NOT NULL UNIQUE,
+  trash_id TEXT NOT NULL,
+  trashed_at TEXT NOT NULL,
+  expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_trash_expires_at ON trash(expires_at);
+
+CREATE TABLE IF NOT EXISTS holds (
+  event_id TEXT PRIMARY KEY,
+  reason TEXT,
+  held_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS retention_rules (
+  rule_id TEXT PRIMARY KEY,
+  name TEXT,
+  app TEXT,
+  source TEXT,
+  tag TEXT,
+  privacy_flag TEXT,
+  keep_days INTEGER,
+  enabled INTEGER NOT NULL DEFAULT 1,
+  created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+  updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS retention_runs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  run_id TEXT NOT NULL,
+  rule_id TEXT NOT NULL,
+  cutoff TEXT NOT NULL,
+  matched INTEGER NOT NULL DEFAULT 0,
+  summary TEXT,
+  error TEXT,
+  started_at TEXT NOT NULL,
+  finished_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_runs_rule ON retention_runs(rule_id);
+
 CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events(timestamp);
 CREATE INDEX IF NOT EXISTS idx_events_app ON events(app);
 CREATE INDEX IF NOT EXISTS idx_events_content_hash ON events(content_hash);
@@ -91,13 +203,39 @@ fn upsert_blob_index(conn: &Connection, content_hash: &str) -> Result<String> {
     }
 }
 
+/// Result of `insert_event`; only `Inserted` changes the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertOutcome {
+    Inserted,
+    /// The event_id is already stored with the same content_hash (a retry).
+    Duplicate,
+    /// The event_id is already stored with different content; the stored row wins.
+    Conflict { existing_hash: String },
+}
+
+/// The stored content_hash for `event_id`, if the event exists.
+pub fn find_event_hash(conn: &Connection, event_id: &str) -> Result<Option<String>> {
+    let hash = conn
+        .query_row("SELECT content_hash FROM events WHERE event_id = ?1", params![event_id], |r| r.get(0))
+        .optional()?;
+    Ok(hash)
+}
+
 /// Inserts event metadata and ensures blob_index ref_count is maintained.
-pub fn insert_event(conn: &Connection, env: &EventEnvelope) -> Result<()> {
+/// Idempotent per event_id: the existence check, refcount bump and insert run
+/// in one transaction, so a retried envelope never inflates `ref_count`.
+pub fn insert_event(conn: &Connection, env: &EventEnvelope) -> Result<InsertOutcome> {
+    let tx = conn.unchecked_transaction()?;
+    match find_event_hash(&tx, &env.event_id)? {
+        Some(h) if h == env.content_hash => return Ok(InsertOutcome::Duplicate),
+        Some(h) => return Ok(InsertOutcome::Conflict { existing_hash: h }),
+        None => {}
+    }
     // Ensure blob_index exists/up-to-date for FK safety
-    let blob_path = upsert_blob_index(conn, &env.content_hash)?;
+    let blob_path = upsert_blob_index(&tx, &env.content_hash)?;
 
     // Insert event row
-    conn.execute(
+    tx.execute(
         r#"INSERT INTO events (
             event_id, timestamp, source, app, content_pointer, content_hash,
             size_bytes, tags, privacy_flag
@@ -119,7 +257,8 @@ pub fn insert_event(conn: &Connection, env: &EventEnvelope) -> Result<()> {
             },
         ],
     )?;
-    Ok(())
+    tx.commit()?;
+    Ok(InsertOutcome::Inserted)
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -150,60 +289,44 @@ pub fn insert_chunks(conn: &Connection, chunks: &[ChunkRow]) -> Result<u64> {
     Ok(inserted)
 }
 
+/// Maps a row selected as `event_id, timestamp, source, app, content_pointer,
+/// content_hash, size_bytes, tags, privacy_flag` into an `EventEnvelope`.
+pub(crate) fn row_to_envelope(row: &rusqlite::Row<'_>) -> rusqlite::Result<EventEnvelope> {
+    let tags_json: String = row.get(7)?;
+    let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
+    let privacy: String = row.get(8)?;
+    let privacy_flag = match privacy.as_str() {
+        "sensitive" => crate::handlers::PrivacyFlag::Sensitive,
+        "never_store" => crate::handlers::PrivacyFlag::NeverStore,
+        _ => crate::handlers::PrivacyFlag::Default,
+    };
+    Ok(EventEnvelope {
+        event_id: row.get(0)?,
+        timestamp: row.get(1)?,
+        source: row.get(2)?,
+        app: row.get(3)?,
+        content_pointer: row.get(4)?,
+        content_hash: row.get(5)?,
+        size_bytes: row.get::<_, i64>(6)? as u64,
+        tags,
+        privacy_flag,
+    })
+}
+
 pub fn get_event(conn: &Connection, id: &str) -> Result<EventEnvelope> {
     let row = conn.query_row(
         "SELECT event_id, timestamp, source, app, content_pointer, content_hash, size_bytes, tags, privacy_flag FROM events WHERE event_id = ?1",
         params![id],
-        |row| {
-            let tags_json: String = row.get(7)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-            let privacy: String = row.get(8)?;
-            let privacy_flag = match privacy.as_str() {
-                "sensitive" => crate::handlers::PrivacyFlag::Sensitive,
-                "never_store" => crate::handlers::PrivacyFlag::NeverStore,
-                _ => crate::handlers::PrivacyFlag::Default,
-            };
-            Ok(EventEnvelope {
-                event_id: row.get(0)?,
-                timestamp: row.get(1)?,
-                source: row.get(2)?,
-                app: row.get(3)?,
-                content_pointer: row.get(4)?,
-                content_hash: row.get(5)?,
-                size_bytes: row.get::<_, i64>(6)? as u64,
-                tags,
-                privacy_flag,
-            })
-        },
+        row_to_envelope,
     )?;
     Ok(row)
 }
 
 pub fn query_events_by_timerange(conn: &Connection, start: &str, end: &str) -> Result<Vec<EventEnvelope>> {
     let mut stmt = conn.prepare(
-        "SELECT event_id, timestamp, source, app, content_pointer, content_hash, size_bytes, tags, privacy_flag FROM events WHERE timestamp BETWEEN ?1 AND ?2 ORDER BY timestamp"
+        "SELECT event_id, timestamp, source, app, content_pointer, content_hash, size_bytes, tags, privacy_flag FROM events WHERE timestamp BETWEEN ?1 AND ?2 AND event_id NOT IN (SELECT event_id FROM trash) ORDER BY timestamp"
     )?;
-    let rows = stmt.query_map(params![start, end], |row| {
-        let tags_json: String = row.get(7)?;
-        let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-        let privacy: String = row.get(8)?;
-        let privacy_flag = match privacy.as_str() {
-            "sensitive" => crate::handlers::PrivacyFlag::Sensitive,
-            "never_store" => crate::handlers::PrivacyFlag::NeverStore,
-            _ => crate::handlers::PrivacyFlag::Default,
-        };
-        Ok(EventEnvelope {
-            event_id: row.get(0)?,
-            timestamp: row.get(1)?,
-            source: row.get(2)?,
-            app: row.get(3)?,
-            content_pointer: row.get(4)?,
-            content_hash: row.get(5)?,
-            size_bytes: row.get::<_, i64>(6)? as u64,
-            tags,
-            privacy_flag,
-        })
-    })?;
+    let rows = stmt.query_map(params![start, end], row_to_envelope)?;
     let mut out = Vec::new();
     for r in rows { out.push(r?); }
     Ok(out)
@@ -211,35 +334,56 @@ pub fn query_events_by_timerange(conn: &Connection, start: &str, end: &str) -> R
 
 pub fn query_events_by_app_source(conn: &Connection, app: Option<&str>, source: Option<&str>) -> Result<Vec<EventEnvelope>> {
     let mut sql = String::from("SELECT event_id, timestamp, source, app, content_pointer, content_hash, size_bytes, tags, privacy_flag FROM events");
-    let mut clauses = Vec::new();
+    let mut clauses = vec![crate::storage::trash::NOT_TRASHED];
     let mut params_vec: Vec<String> = Vec::new();
     if let Some(a) = app { clauses.push("app = ?"); params_vec.push(a.to_string()); }
     if let Some(s) = source { clauses.push("source = ?"); params_vec.push(s.to_string()); }
-    if !clauses.is_empty() { sql.push_str(" WHERE "); sql.push_str(&clauses.join(" AND ")); }
+    sql.push_str(" WHERE "); sql.push_str(&clauses.join(" AND "));
     sql.push_str(" ORDER BY timestamp");
 
     let mut stmt = conn.prepare(&sql)?;
-    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
-        let tags_json: String = row.get(7)?;
-        let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-        let privacy: String = row.get(8)?;
-        let privacy_flag = match privacy.as_str() {
-            "sensitive" => crate::handlers::PrivacyFlag::Sensitive,
-            "never_store" => crate::handlers::PrivacyFlag::NeverStore,
-            _ => crate::handlers::PrivacyFlag::Default,
-        };
-        Ok(EventEnvelope {
-            event_id: row.get(0)?,
-            timestamp: row.get(1)?,
-            source: row.get(2)?,
-            app: row.get(3)?,
-            content_pointer: row.get(4)?,
-            content_hash: row.get(5)?,
-            size_bytes: row.get::<_, i64>(6)? as u64,
-            tags,
-            privacy_flag,
-        })
-    })?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), row_to_envelope)?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// Filters for a timeline page. Results are ordered newest first; `before` is the
+/// `(timestamp, event_id)` keyset of the last item on the previous page.
+#[derive(Default, Debug, Clone)]
+pub struct TimelineQuery {
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub app: Option<String>,
+    pub source: Option<String>,
+    pub tag: Option<String>,
+    pub before: Option<(String, String)>,
+    pub limit: u32,
+}
+
+pub fn query_timeline(conn: &Connection, q: &TimelineQuery) -> Result<Vec<EventEnvelope>> {
+    let mut sql = String::from("SELECT event_id, timestamp, source, app, content_pointer, content_hash, size_bytes, tags, privacy_flag FROM events");
+    let mut clauses: Vec<&str> = vec![crate::storage::trash::NOT_TRASHED];
+    let mut params_vec: Vec<String> = Vec::new();
+    if let Some(s) = q.start.as_ref() { clauses.push("timestamp >= ?"); params_vec.push(s.clone()); }
+    if let Some(e) = q.end.as_ref() { clauses.push("timestamp <= ?"); params_vec.push(e.clone()); }
+    if let Some(a) = q.app.as_ref() { clauses.push("app = ?"); params_vec.push(a.clone()); }
+    if let Some(s) = q.source.as_ref() { clauses.push("source = ?"); params_vec.push(s.clone()); }
+    if let Some(t) = q.tag.as_ref() {
+        clauses.push("EXISTS (SELECT 1 FROM json_each(events.tags) WHERE json_each.value = ?)");
+        params_vec.push(t.clone());
+    }
+    if let Some((ts, id)) = q.before.as_ref() {
+        clauses.push("(timestamp < ? OR (timestamp = ? AND event_id < ?))");
+        params_vec.push(ts.clone());
+        params_vec.push(ts.clone());
+        params_vec.push(id.clone());
+    }
+    sql.push_str(" WHERE "); sql.push_str(&clauses.join(" AND "));
+    sql.push_str(&format!(" ORDER BY timestamp DESC, event_id DESC LIMIT {}", q.limit));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), row_to_envelope)?;
     let mut out = Vec::new();
     for r in rows { out.push(r?); }
     Ok(out)
@@ -256,7 +400,66 @@ pub fn get_blob_index(conn: &Connection, hash: &str) -> Result<Option<(String, i
     Ok(row)
 }
 
-#[derive(Default)]
+/// An event rejected during ingestion, e.g. content that does not match its envelope.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct IngestError {
+    #[serde(default)]
+    pub id: i64,
+    pub event_id: String,
+    pub app: String,
+    pub source: String,
+    /// `hash_mismatch`, `size_mismatch`, `path_denied` or `conflict`.
+    pub kind: String,
+    pub message: String,
+    pub declared_hash: String,
+    pub actual_hash: Option<String>,
+    pub declared_size: i64,
+    pub actual_size: Option<i64>,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+pub fn record_ingest_error(conn: &Connection, e: &IngestError) -> Result<i64> {
+    conn.execute(
+        r#"INSERT INTO ingest_errors (event_id, app, source, kind, message, declared_hash, actual_hash, declared_size, actual_size)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"#,
+        params![e.event_id, e.app, e.source, e.kind, e.message, e.declared_hash, e.actual_hash, e.declared_size, e.actual_size],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Newest first; `before` is the `id` of the last row on the previous page.
+pub fn list_ingest_errors(conn: &Connection, event_id: Option<&str>, before: Option<i64>, limit: u32) -> Result<Vec<IngestError>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT id, event_id, app, source, kind, message, declared_hash, actual_hash, declared_size, actual_size, created_at
+           FROM ingest_errors
+           WHERE (?1 IS NULL OR event_id = ?1) AND (?2 IS NULL OR id < ?2)
+           ORDER BY id DESC LIMIT ?3"#,
+    )?;
+    let rows = stmt.query_map(params![event_id, before, limit], |r| {
+        Ok(IngestError {
+            id: r.get(0)?,
+            event_id: r.get(1)?,
+            app: r.get(2)?,
+            source: r.get(3)?,
+            kind: r.get(4)?,
+            message: r.get(5)?,
+            declared_hash: r.get(6)?,
+            actual_hash: r.get(7)?,
+            declared_size: r.get(8)?,
+            actual_size: r.get(9)?,
+            created_at: r.get(10)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// `(blob_path, ref_count)` as stored in `blob_index`.
+type BlobIndexRow = (String, i64);
+
+#[derive(Default, Debug, Clone)]
 pub struct PurgeCriteria {
     pub event_ids: Option<Vec<String>>,
     pub start: Option<String>,
@@ -264,48 +467,229 @@ pub struct PurgeCriteria {
     pub app: Option<String>,
     pub source: Option<String>,
     pub privacy_flag: Option<String>,
+    /// Events carrying this tag.
+    pub tag: Option<String>,
+    pub trash: TrashScope,
+    /// Events matching any of these are left alone, whatever their trash state.
+    pub excluding: Vec<PurgeCriteria>,
+    /// Also match events on hold, which are skipped otherwise.
+    pub include_held: bool,
 }
 
-pub fn purge_events(conn: &mut Connection, c: PurgeCriteria) -> Result<(u64, u64)> {
-    let mut where_clauses: Vec<String> = Vec::new();
-    let mut params_vec: Vec<String> = Vec::new();
+/// Which events a purge may match, with respect to the trash.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub enum TrashScope {
+    /// Events that are not in the trash.
+    #[default]
+    Live,
+    /// Live and trashed events alike.
+    All,
+    /// Trashed events whose restore window ended at or before this RFC3339 time.
+    ExpiredBy(String),
+}
+
+impl PurgeCriteria {
+    /// The `events` filter as a SQL condition and its parameters; `None`
+    /// without any criteria, so an empty request never matches everything.
+    pub fn where_sql(&self) -> Option<(String, Vec<String>)> {
+        let mut where_clauses: Vec<String> = Vec::new();
+        let mut params_vec: Vec<String> = Vec::new();
 
-    if let Some(ids) = c.event_ids.as_ref() {
-        if !ids.is_empty() {
-            let placeholders = std::iter::repeat("?").take(ids.len()).collect::<Vec<_>>().join(", ");
-            where_clauses.push(format!("event_id IN ({})", placeholders));
-            for id in ids { params_vec.push(id.clone()); }
+        if let Some(ids) = self.event_ids.as_ref() {
+            if !ids.is_empty() {
+                let placeholders = vec!["?"; ids.len()].join(", ");
+                where_clauses.push(format!("event_id IN ({})", placeholders));
+                for id in ids { params_vec.push(id.clone()); }
+            }
         }
+        if let (Some(s), Some(e)) = (self.start.as_ref(), self.end.as_ref()) {
+            where_clauses.push("timestamp BETWEEN ? AND ?".to_string());
+            params_vec.push(s.clone());
+            params_vec.push(e.clone());
+        } else {
+            if let Some(s) = self.start.as_ref() { where_clauses.push("timestamp >= ?".to_string()); params_vec.push(s.clone()); }
+            if let Some(e) = self.end.as_ref() { where_clauses.push("timestamp <= ?".to_string()); params_vec.push(e.clone()); }
+        }
+        if let Some(a) = self.app.as_ref() { where_clauses.push("app = ?".to_string()); params_vec.push(a.clone()); }
+        if let Some(s) = self.source.as_ref() { where_clauses.push("source = ?".to_string()); params_vec.push(s.clone()); }
+        if let Some(p) = self.privacy_flag.as_ref() { where_clauses.push("privacy_flag = ?".to_string()); params_vec.push(p.clone()); }
+        if let Some(t) = self.tag.as_ref() {
+            where_clauses.push("EXISTS (SELECT 1 FROM json_each(tags) WHERE json_each.value = ?)".to_string());
+            params_vec.push(t.clone());
+        }
+
+        if where_clauses.is_empty() { return None; }
+        if !self.include_held { where_clauses.push(format!("NOT {}", crate::storage::holds::HELD)); }
+        for keep in &self.excluding {
+            match (PurgeCriteria { trash: TrashScope::All, include_held: true, ..keep.clone() }).where_sql() {
+                Some((sql, p)) => { where_clauses.push(format!("NOT ({})", sql)); params_vec.extend(p); }
+                // An exclusion without criteria keeps everything.
+                None => where_clauses.push("0".to_string()),
+            }
+        }
+        match &self.trash {
+            TrashScope::Live => where_clauses.push(crate::storage::trash::NOT_TRASHED.to_string()),
+            TrashScope::All => {}
+            TrashScope::ExpiredBy(t) => {
+                where_clauses.push("event_id IN (SELECT event_id FROM trash WHERE expires_at <= ?)".to_string());
+                params_vec.push(t.clone());
+            }
+        }
+        Some((where_clauses.join(" AND "), params_vec))
     }
-    if let (Some(s), Some(e)) = (c.start.as_ref(), c.end.as_ref()) {
-        where_clauses.push("timestamp BETWEEN ? AND ?".to_string());
-        params_vec.push(s.clone());
-        params_vec.push(e.clone());
-    } else {
-        if let Some(s) = c.start.as_ref() { where_clauses.push("timestamp >= ?".to_string()); params_vec.push(s.clone()); }
-        if let Some(e) = c.end.as_ref() { where_clauses.push("timestamp <= ?".to_string()); params_vec.push(e.clone()); }
-    }
-    if let Some(a) = c.app.as_ref() { where_clauses.push("app = ?".to_string()); params_vec.push(a.clone()); }
-    if let Some(s) = c.source.as_ref() { where_clauses.push("source = ?".to_string()); params_vec.push(s.clone()); }
-    if let Some(p) = c.privacy_flag.as_ref() { where_clauses.push("privacy_flag = ?".to_string()); params_vec.push(p.clone()); }
+}
+
+/// What a purge would delete, without deleting anything.
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct PurgePreview {
+    pub events: u64,
+    /// Sum of the events' `size_bytes`.
+    pub bytes: u64,
+    /// Blobs no other event references, which the purge would delete.
+    pub blobs: u64,
+    pub chunks: u64,
+    /// Matching events on hold, which the purge would skip.
+    pub skipped_held: u64,
+    /// Newest matching events first.
+    pub sample: Vec<EventEnvelope>,
+}
+
+/// Events matching `c` but for their hold; 0 with `include_held`.
+pub fn count_held(conn: &Connection, c: &PurgeCriteria) -> Result<u64> {
+    if c.include_held { return Ok(0); }
+    let Some((where_sql, params_vec)) = (PurgeCriteria { include_held: true, ..c.clone() }).where_sql() else { return Ok(0) };
+    let n: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM events WHERE {} AND {}", where_sql, crate::storage::holds::HELD),
+        rusqlite::params_from_iter(params_vec.iter()),
+        |r| r.get(0),
+    )?;
+    Ok(n as u64)
+}
 
-    if where_clauses.is_empty() { return Ok((0, 0)); }
+pub fn purge_preview(conn: &Connection, c: &PurgeCriteria, sample_limit: u32) -> Result<PurgePreview> {
+    let Some((where_sql, params_vec)) = c.where_sql() else { return Ok(PurgePreview::default()) };
+    let args = || rusqlite::params_from_iter(params_vec.iter());
+    let (events, bytes): (i64, i64) = conn.query_row(
+        &format!("SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM events WHERE {}", where_sql),
+        args(),
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+    // A blob survives if any event outside the filter shares its hash.
+    let blobs: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(DISTINCT content_hash) FROM events m WHERE {w} AND NOT EXISTS \
+             (SELECT 1 FROM events o WHERE o.content_hash = m.content_hash AND o.event_id NOT IN (SELECT event_id FROM events WHERE {w}))",
+            w = where_sql
+        ),
+        rusqlite::params_from_iter(params_vec.iter().chain(params_vec.iter())),
+        |r| r.get(0),
+    )?;
+    let chunks: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM chunks WHERE event_id IN (SELECT event_id FROM events WHERE {})", where_sql),
+        args(),
+        |r| r.get(0),
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT event_id, timestamp, source, app, content_pointer, content_hash, size_bytes, tags, privacy_flag FROM events WHERE {} ORDER BY timestamp DESC, event_id LIMIT {}",
+        where_sql, sample_limit
+    ))?;
+    let rows = stmt.query_map(args(), row_to_envelope)?;
+    let mut sample = Vec::new();
+    for r in rows { sample.push(r?); }
+    let skipped_held = count_held(conn, c)?;
+    Ok(PurgePreview { events: events as u64, bytes: bytes as u64, blobs: blobs as u64, chunks: chunks as u64, skipped_held, sample })
+}
+
+/// Ids of the events matching `c`, oldest first.
+pub fn purge_candidates(conn: &Connection, c: &PurgeCriteria) -> Result<Vec<String>> {
+    let Some((where_sql, params_vec)) = c.where_sql() else { return Ok(Vec::new()) };
+    let mut stmt = conn.prepare(&format!("SELECT event_id FROM events WHERE {} ORDER BY timestamp, event_id", where_sql))?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |r| r.get(0))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// What a purge removed from the database, per table, plus the ids the
+/// caller needs to clean the layers outside it (vector index, intel files).
+#[derive(Debug, Default, Clone)]
+pub struct PurgeReport {
+    pub events: u64,
+    pub blobs: u64,
+    pub chunks: u64,
+    pub fts_rows: u64,
+    pub stage_runs: u64,
+    pub event_status: u64,
+    pub dead_letters: u64,
+    pub ingest_errors: u64,
+    pub trash: u64,
+    pub holds: u64,
+    /// Matching events left alone because they are on hold.
+    pub skipped_held: u64,
+    pub event_ids: Vec<String>,
+    pub chunk_ids: Vec<String>,
+    /// Intel worker jobs recorded by the `intel` stage.
+    pub job_ids: Vec<String>,
+}
+
+fn select_strings(tx: &rusqlite::Transaction, sql: &str, params_vec: &[String]) -> Result<Vec<String>> {
+    let mut stmt = tx.prepare(sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| row.get::<_, Option<String>>(0))?;
+    let mut out = Vec::new();
+    for r in rows { if let Some(s) = r? { out.push(s); } }
+    Ok(out)
+}
+
+/// Runs after the events are gone: `TrashScope::ExpiredBy` filters on these rows.
+fn delete_trash_rows(tx: &rusqlite::Transaction, event_ids: &[String]) -> Result<u64> {
+    let mut stmt = tx.prepare("DELETE FROM trash WHERE event_id = ?1")?;
+    let mut n = 0;
+    for id in event_ids { n += stmt.execute(params![id])? as u64; }
+    Ok(n)
+}
+
+/// Deletes the matching events and everything derived from them: chunks,
+/// full-text rows, pipeline state, ingest errors, trash entries, and blobs
+/// nothing else uses.
+pub fn purge_events(conn: &mut Connection, c: PurgeCriteria) -> Result<PurgeReport> {
+    let Some((where_sql, params_vec)) = c.where_sql() else { return Ok(PurgeReport::default()) };
 
     let tx = conn.transaction()?;
+    let in_events = format!("event_id IN (SELECT event_id FROM events WHERE {})", where_sql);
+    let mut report = PurgeReport {
+        skipped_held: count_held(&tx, &c)?,
+        event_ids: select_strings(&tx, &format!("SELECT event_id FROM events WHERE {}", where_sql), &params_vec)?,
+        chunk_ids: select_strings(&tx, &format!("SELECT chunk_id FROM chunks WHERE {}", in_events), &params_vec)?,
+        ..PurgeReport::default()
+    };
+    for output in select_strings(&tx, &format!("SELECT output FROM stage_runs WHERE stage = 'intel' AND {}", in_events), &params_vec)? {
+        let job = serde_json::from_str::<serde_json::Value>(&output).ok()
+            .and_then(|v| v.get("job_id").and_then(|j| j.as_str()).map(str::to_string));
+        if let Some(job) = job { report.job_ids.push(job); }
+    }
+    // Chunks reference events, so they have to go first.
+    for (table, count) in [
+        ("memory_fts", &mut report.fts_rows),
+        ("chunks", &mut report.chunks),
+        ("event_status", &mut report.event_status),
+        ("dead_letters", &mut report.dead_letters),
+        ("stage_runs", &mut report.stage_runs),
+        ("ingest_errors", &mut report.ingest_errors),
+        // Only with `include_held`; otherwise no held event matches.
+        ("holds", &mut report.holds),
+    ] {
+        *count = tx.execute(&format!("DELETE FROM {} WHERE {}", table, in_events), rusqlite::params_from_iter(params_vec.iter()))? as u64;
+    }
     // Purge matching events and update blob_index based on remaining references.
-    let mut select_hashes_sql = String::from("SELECT DISTINCT content_hash FROM events");
-    select_hashes_sql.push_str(" WHERE ");
-    select_hashes_sql.push_str(&where_clauses.join(" AND "));
+    let select_hashes_sql = format!("SELECT DISTINCT content_hash FROM events WHERE {}", where_sql);
     let impacted_hashes: Vec<String> = {
         let mut stmt = tx.prepare(&select_hashes_sql)?;
-        let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
-            Ok(row.get(0)?)
-        })?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| row.get(0))?;
         let mut v = Vec::new();
         for r in rows { v.push(r?); }
         v
     };
-    let mut pre_index: Vec<(String, Option<(String, i64)>, bool)> = Vec::new();
+    let mut pre_index: Vec<(String, Option<BlobIndexRow>, bool)> = Vec::new();
     for h in impacted_hashes.iter() {
         let row: Option<(String, i64)> = tx
             .query_row(
@@ -318,23 +702,13 @@ pub fn purge_events(conn: &mut Connection, c: PurgeCriteria) -> Result<(u64, u64
         pre_index.push((h.clone(), row, existed));
     }
 
-    let where_sql = where_clauses.join(" AND ");
-    let deleted_events: u64;
     // Prefer precise deletion when event_ids provided
-    if let Some(ids) = c.event_ids.as_ref() {
-        // Capture impacted hashes before deletion
-        let impacted_hashes: Vec<String> = {
-            let mut stmt = tx.prepare(&format!("SELECT DISTINCT content_hash FROM events WHERE {}", where_sql))?;
-            let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| Ok(row.get(0)?))?;
-            let mut v = Vec::new();
-            for r in rows { if let Ok(s) = r { v.push(s); } }
-            v
-        };
-        // Delete each event id individually
-        for id in ids {
-            let _ = tx.execute("DELETE FROM events WHERE event_id = ?1", params![id])?;
+    if c.event_ids.is_some() {
+        // Delete each matched event id individually; the other criteria and
+        // holds still apply to the requested ids.
+        for id in &report.event_ids {
+            report.events += tx.execute("DELETE FROM events WHERE event_id = ?1", params![id])? as u64;
         }
-        deleted_events = ids.len() as u64;
         // Recompute blob_index for impacted hashes
         for (hash, _pre_row, _existed) in pre_index.clone().into_iter() {
             let pre: Option<(String, i64)> = tx
@@ -359,12 +733,16 @@ pub fn purge_events(conn: &mut Connection, c: PurgeCriteria) -> Result<(u64, u64
                     tx.execute("DELETE FROM blob_index WHERE blob_hash = ?1", params![hash])?;
                 }
             }
+            // Blobs another event still uses are not deleted, so not counted.
+            if remaining <= 0 { report.blobs += 1; }
         }
+        report.trash = delete_trash_rows(&tx, &report.event_ids)?;
         tx.commit()?;
-        return Ok((deleted_events, impacted_hashes.len() as u64));
+        return Ok(report);
     } else {
         let delete_sql = format!("DELETE FROM events WHERE {}", where_sql);
-        deleted_events = tx.execute(&delete_sql, rusqlite::params_from_iter(params_vec.iter()))? as u64;
+        report.events = tx.execute(&delete_sql, rusqlite::params_from_iter(params_vec.iter()))? as u64;
+        report.trash = delete_trash_rows(&tx, &report.event_ids)?;
     }
 
     let mut deleted_blobs: u64 = 0;
@@ -396,7 +774,7 @@ pub fn purge_events(conn: &mut Connection, c: PurgeCriteria) -> Result<(u64, u64
                 .query_row(
                     "SELECT 1 FROM blob_index WHERE blob_hash = ?1",
                     params![hash],
-                    |row| Ok(row.get(0)?),
+                    |row| row.get(0),
                 )
                 .optional()?;
             if post_exists.is_none() && remaining <= 0 {
@@ -407,5 +785,6 @@ pub fn purge_events(conn: &mut Connection, c: PurgeCriteria) -> Result<(u64, u64
     }
 
     tx.commit()?;
-    Ok((deleted_events, deleted_blobs))
+    report.blobs = deleted_blobs;
+    Ok(report)
 }
\ No newline at end of file
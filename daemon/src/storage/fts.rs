@@ -0,0 +1,194 @@
+//! Full-text index over blob text and chunk spans (SQLite FTS5).
+//!
+//! Each event gets a whole-document row at ingest time. Once the intelligence
+//! layer produces `chunks` rows, the document row is replaced by one row per
+//! chunk so hits carry precise offsets. Offsets are character offsets, matching
+//! the Python chunkers.
+use crate::storage::db::ChunkRow;
+use crate::storage::Result;
+use rusqlite::{params, Connection};
+
+/// Returns the indexable text of a blob, or `None` for non-UTF-8 content.
+pub fn extract_text(bytes: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    if text.trim().is_empty() { return None; }
+    Some(text.to_string())
+}
+
+/// Indexes the full text of an event, replacing any rows it already has.
+pub fn index_document(conn: &Connection, event_id: &str, text: &str) -> Result<()> {
+    delete_event(conn, event_id)?;
+    conn.execute(
+        "INSERT INTO memory_fts (body, event_id, chunk_id, start_offset, end_offset) VALUES (?1, ?2, NULL, 0, ?3)",
+        params![text, event_id, text.chars().count() as i64],
+    )?;
+    Ok(())
+}
+
+/// Replaces the rows of an event with one row per chunk, sliced from `text`.
+pub fn index_chunks(conn: &Connection, event_id: &str, text: &str, chunks: &[ChunkRow]) -> Result<u64> {
+    if chunks.is_empty() { return Ok(0); }
+    let chars: Vec<char> = text.chars().collect();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM memory_fts WHERE event_id = ?1", params![event_id])?;
+    let mut inserted = 0u64;
+    for c in chunks {
+        let start = (c.start_offset.max(0) as usize).min(chars.len());
+        let end = (c.end_offset.max(0) as usize).clamp(start, chars.len());
+        let body: String = chars[start..end].iter().collect();
+        tx.execute(
+            "INSERT INTO memory_fts (body, event_id, chunk_id, start_offset, end_offset) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![body, event_id, c.chunk_id, c.start_offset, c.end_offset],
+        )?;
+        inserted += 1;
+    }
+    tx.commit()?;
+    Ok(inserted)
+}
+
+pub fn delete_event(conn: &Connection, event_id: &str) -> Result<u64> {
+    Ok(conn.execute("DELETE FROM memory_fts WHERE event_id = ?1", params![event_id])? as u64)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SearchQuery {
+    pub query: String,
+    pub app: Option<String>,
+    pub source: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub limit: u32,
+    /// Match rows containing any term instead of all terms.
+    pub any_term: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SearchHit {
+    pub event_id: String,
+    pub chunk_id: Option<String>,
+    pub start_offset: i64,
+    pub end_offset: i64,
+    /// Negated bm25, so higher is better.
+    pub score: f64,
+    /// Matching excerpt with terms wrapped in `<mark>`.
+    pub snippet: String,
+    pub timestamp: String,
+    pub source: String,
+    pub app: String,
+    /// Indexed text of the matched row; used for RAG context, not returned by search.
+    #[serde(skip)]
+    pub text: String,
+    /// How a hybrid retrieval arrived at `score`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scores: Option<crate::retrieval::ScoreBreakdown>,
+}
+
+/// Turns free text into an FTS5 expression that matches all terms, quoting
+/// each one so user input can never be parsed as query syntax.
+pub fn to_match_expr(query: &str) -> Option<String> {
+    to_match_expr_joined(query, " ")
+}
+
+fn to_match_expr_joined(query: &str, sep: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|t| t.replace('"', ""))
+        .filter(|t| !t.is_empty())
+        .map(|t| format!("\"{}\"", t))
+        .collect();
+    if terms.is_empty() { None } else { Some(terms.join(sep)) }
+}
+
+pub fn search(conn: &Connection, q: &SearchQuery) -> Result<Vec<SearchHit>> {
+    let expr = match to_match_expr_joined(&q.query, if q.any_term { " OR " } else { " " }) {
+        Some(e) => e,
+        None => return Ok(Vec::new()),
+    };
+    let mut sql = String::from(
+        r#"SELECT memory_fts.event_id, memory_fts.chunk_id, memory_fts.start_offset, memory_fts.end_offset,
+                  bm25(memory_fts), snippet(memory_fts, 0, '<mark>', '</mark>', '…', 16),
+                  e.timestamp, e.source, e.app, memory_fts.body
+           FROM memory_fts JOIN events e ON e.event_id = memory_fts.event_id
+           WHERE memory_fts MATCH ?"#,
+    );
+    let mut params_vec: Vec<String> = vec![expr];
+    push_filters(&mut sql, &mut params_vec, q);
+    sql.push_str(&format!(" ORDER BY bm25(memory_fts) LIMIT {}", q.limit));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
+        Ok(SearchHit {
+            event_id: row.get(0)?,
+            chunk_id: row.get(1)?,
+            start_offset: row.get(2)?,
+            end_offset: row.get(3)?,
+            score: -row.get::<_, f64>(4)?,
+            snippet: row.get(5)?,
+            timestamp: row.get(6)?,
+            source: row.get(7)?,
+            app: row.get(8)?,
+            text: row.get(9)?,
+            scores: None,
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+fn push_filters(sql: &mut String, params_vec: &mut Vec<String>, q: &SearchQuery) {
+    // Trashed events stay indexed until purged, so a restore needs no reindex.
+    sql.push_str(" AND e.event_id NOT IN (SELECT event_id FROM trash)");
+    if let Some(a) = q.app.as_ref() { sql.push_str(" AND e.app = ?"); params_vec.push(a.clone()); }
+    if let Some(s) = q.source.as_ref() { sql.push_str(" AND e.source = ?"); params_vec.push(s.clone()); }
+    if let Some(s) = q.start.as_ref() { sql.push_str(" AND e.timestamp >= ?"); params_vec.push(s.clone()); }
+    if let Some(e) = q.end.as_ref() { sql.push_str(" AND e.timestamp <= ?"); params_vec.push(e.clone()); }
+}
+
+/// Rows for the given chunks that pass the filters of `q` (its query text is
+/// ignored), keyed by chunk id. Used to hydrate vector hits; the snippet is the
+/// start of the chunk, since there are no matched terms to highlight.
+pub fn chunk_rows(conn: &Connection, chunk_ids: &[String], q: &SearchQuery) -> Result<std::collections::HashMap<String, SearchHit>> {
+    let mut out = std::collections::HashMap::new();
+    if chunk_ids.is_empty() { return Ok(out); }
+    let mut sql = format!(
+        r#"SELECT memory_fts.event_id, memory_fts.chunk_id, memory_fts.start_offset, memory_fts.end_offset,
+                  e.timestamp, e.source, e.app, memory_fts.body
+           FROM memory_fts JOIN events e ON e.event_id = memory_fts.event_id
+           WHERE memory_fts.chunk_id IN ({})"#,
+        vec!["?"; chunk_ids.len()].join(","),
+    );
+    let mut params_vec: Vec<String> = chunk_ids.to_vec();
+    push_filters(&mut sql, &mut params_vec, q);
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
+        let text: String = row.get(7)?;
+        Ok(SearchHit {
+            event_id: row.get(0)?,
+            chunk_id: row.get(1)?,
+            start_offset: row.get(2)?,
+            end_offset: row.get(3)?,
+            score: 0.0,
+            snippet: lead_snippet(&text),
+            timestamp: row.get(4)?,
+            source: row.get(5)?,
+            app: row.get(6)?,
+            text,
+            scores: None,
+        })
+    })?;
+    for r in rows {
+        let hit = r?;
+        if let Some(id) = hit.chunk_id.clone() { out.insert(id, hit); }
+    }
+    Ok(out)
+}
+
+/// The first words of a chunk, about as long as an FTS5 `snippet`.
+fn lead_snippet(text: &str) -> String {
+    const WORDS: usize = 16;
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut s = words.iter().take(WORDS).copied().collect::<Vec<_>>().join(" ");
+    if words.len() > WORDS { s.push('…'); }
+    s
+}
@@ -0,0 +1,52 @@
+//! Legal holds: events pinned against deletion.
+//!
+//! A held event is skipped by purges (unless the request sets `include_held`),
+//! by retention rules and by the trash sweeper; reads are unaffected. Purging
+//! a held event with the override also drops its hold.
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::storage::Result;
+
+/// SQL condition matching held events in a query on `events`.
+pub const HELD: &str = "event_id IN (SELECT event_id FROM holds)";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hold {
+    pub event_id: String,
+    pub reason: Option<String>,
+    pub held_at: String,
+}
+
+fn row_to_hold(r: &rusqlite::Row<'_>) -> rusqlite::Result<Hold> {
+    Ok(Hold { event_id: r.get(0)?, reason: r.get(1)?, held_at: r.get(2)? })
+}
+
+/// Places or updates the hold on `event_id`; `held_at` keeps its first value.
+pub fn set_hold(conn: &Connection, event_id: &str, reason: Option<&str>) -> Result<Hold> {
+    conn.execute(
+        "INSERT INTO holds (event_id, reason) VALUES (?1, ?2) ON CONFLICT(event_id) DO UPDATE SET reason = excluded.reason",
+        params![event_id, reason],
+    )?;
+    Ok(get_hold(conn, event_id)?.ok_or("hold vanished after insert")?)
+}
+
+/// Returns false if the event was not held.
+pub fn clear_hold(conn: &Connection, event_id: &str) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM holds WHERE event_id = ?1", params![event_id])? > 0)
+}
+
+pub fn get_hold(conn: &Connection, event_id: &str) -> Result<Option<Hold>> {
+    Ok(conn
+        .query_row("SELECT event_id, reason, held_at FROM holds WHERE event_id = ?1", params![event_id], row_to_hold)
+        .optional()?)
+}
+
+/// Most recently held first.
+pub fn list_holds(conn: &Connection) -> Result<Vec<Hold>> {
+    let mut stmt = conn.prepare("SELECT event_id, reason, held_at FROM holds ORDER BY rowid DESC")?;
+    let rows = stmt.query_map([], row_to_hold)?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
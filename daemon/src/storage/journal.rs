@@ -0,0 +1,54 @@
+//! Durable ingest journal.
+//!
+//! Every admitted event is appended here before the API answers 202, and
+//! removed once the worker has persisted (or deliberately rejected) it. Rows
+//! left behind by a crash are replayed into the queue at startup. Inline upload
+//! bytes are stored encrypted, like blobs.
+use rusqlite::{params, Connection};
+
+use crate::handlers::EventEnvelope;
+use crate::storage::crypto::{decrypt_bytes, encrypt_bytes};
+use crate::storage::Result;
+
+/// A journaled job: `(seq, envelope, inline content)`.
+pub type JournalEntry = (i64, EventEnvelope, Option<Vec<u8>>);
+
+/// Appends an admitted event; returns its sequence number.
+pub fn append(conn: &Connection, envelope: &EventEnvelope, content: Option<&[u8]>) -> Result<i64> {
+    let content = match content {
+        Some(c) => Some(encrypt_bytes(c)?),
+        None => None,
+    };
+    conn.execute(
+        "INSERT INTO ingest_journal (event_id, envelope, content) VALUES (?1, ?2, ?3)",
+        params![envelope.event_id, serde_json::to_string(envelope)?, content],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn remove(conn: &Connection, seq: i64) -> Result<()> {
+    conn.execute("DELETE FROM ingest_journal WHERE seq = ?1", params![seq])?;
+    Ok(())
+}
+
+/// All entries not yet acknowledged by the worker, oldest first.
+pub fn pending(conn: &Connection) -> Result<Vec<JournalEntry>> {
+    let mut stmt = conn.prepare("SELECT seq, envelope, content FROM ingest_journal ORDER BY seq")?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?, r.get::<_, Option<Vec<u8>>>(2)?)))?;
+    let mut out = Vec::new();
+    for r in rows {
+        let (seq, envelope, content) = r?;
+        let envelope: EventEnvelope = serde_json::from_str(&envelope)?;
+        let content = match content {
+            Some(c) => Some(decrypt_bytes(&c)?),
+            None => None,
+        };
+        out.push((seq, envelope, content));
+    }
+    Ok(out)
+}
+
+pub fn len(conn: &Connection) -> Result<u64> {
+    let n: i64 = conn.query_row("SELECT COUNT(*) FROM ingest_journal", [], |r| r.get(0))?;
+    Ok(n as u64)
+}
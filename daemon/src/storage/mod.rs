@@ -5,11 +5,23 @@
 //! - Insert/query functions returning strongly typed `EventEnvelope`
 //! - Blob store with zstd compression and AES-256-GCM dev-mode encryption
 //! - SHA-256 hashing and deduplication via `blob_index`
+//! - FTS5 full-text index over blob text and chunk spans
+//! - Durable ingest journal replayed after a crash
+//! - Per-event processing state, pipeline stage runs and the dead-letter table
+//! - Trash: soft-deleted events kept for a restore window
+//! - Retention rules and per-run retention reports
+//! - Legal holds protecting events from purges and retention
 //!
 //!
 pub mod db;
 pub mod blobs;
 pub mod crypto;
 pub mod hash;
+pub mod fts;
+pub mod journal;
+pub mod processing;
+pub mod trash;
+pub mod retention;
+pub mod holds;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
\ No newline at end of file
@@ -0,0 +1,207 @@
+//! Per-event processing state, pipeline stage runs and the dead-letter table.
+//!
+//! Every admitted event moves through `queued` -> `stored` -> `indexing` ->
+//! `indexed`, or ends in `failed` with the error that stopped it. Events whose
+//! pipeline fails also land in `dead_letters` until a retry succeeds or the
+//! event is purged.
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingState {
+    Queued,
+    Stored,
+    Indexing,
+    Indexed,
+    Failed,
+}
+
+impl ProcessingState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessingState::Queued => "queued",
+            ProcessingState::Stored => "stored",
+            ProcessingState::Indexing => "indexing",
+            ProcessingState::Indexed => "indexed",
+            ProcessingState::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "queued" => Some(ProcessingState::Queued),
+            "stored" => Some(ProcessingState::Stored),
+            "indexing" => Some(ProcessingState::Indexing),
+            "indexed" => Some(ProcessingState::Indexed),
+            "failed" => Some(ProcessingState::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventStatus {
+    pub event_id: String,
+    pub state: ProcessingState,
+    /// Intel attempts made so far, across retries.
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub event_id: String,
+    pub error: String,
+    pub attempts: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Records a newly admitted event. Leaves an existing status alone, so a
+/// resent envelope can't roll a stored event back to `queued`.
+pub fn mark_queued(conn: &Connection, event_id: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO event_status (event_id, state) VALUES (?1, 'queued') ON CONFLICT(event_id) DO NOTHING",
+        params![event_id],
+    )?;
+    Ok(())
+}
+
+/// Moves an event to `state`; `error` replaces `last_error` (cleared when `None`).
+pub fn set_state(conn: &Connection, event_id: &str, state: ProcessingState, error: Option<&str>) -> Result<()> {
+    conn.execute(
+        r#"INSERT INTO event_status (event_id, state, last_error) VALUES (?1, ?2, ?3)
+           ON CONFLICT(event_id) DO UPDATE SET state = excluded.state, last_error = excluded.last_error, updated_at = CURRENT_TIMESTAMP"#,
+        params![event_id, state.as_str(), error],
+    )?;
+    Ok(())
+}
+
+pub fn add_attempts(conn: &Connection, event_id: &str, n: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE event_status SET attempts = attempts + ?2, updated_at = CURRENT_TIMESTAMP WHERE event_id = ?1",
+        params![event_id, n],
+    )?;
+    Ok(())
+}
+
+pub fn get_status(conn: &Connection, event_id: &str) -> Result<Option<EventStatus>> {
+    let row = conn.query_row(
+        "SELECT event_id, state, attempts, last_error, updated_at FROM event_status WHERE event_id = ?1",
+        params![event_id],
+        |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, i64>(2)?, r.get::<_, Option<String>>(3)?, r.get::<_, String>(4)?)),
+    ).optional()?;
+    Ok(row.and_then(|(event_id, state, attempts, last_error, updated_at)| {
+        Some(EventStatus { event_id, state: ProcessingState::parse(&state)?, attempts, last_error, updated_at })
+    }))
+}
+
+/// Files (or refreshes) the dead letter for an event whose intel job failed.
+pub fn dead_letter(conn: &Connection, event_id: &str, error: &str, attempts: i64) -> Result<()> {
+    conn.execute(
+        r#"INSERT INTO dead_letters (event_id, error, attempts) VALUES (?1, ?2, ?3)
+           ON CONFLICT(event_id) DO UPDATE SET error = excluded.error, attempts = dead_letters.attempts + excluded.attempts,
+             updated_at = CURRENT_TIMESTAMP"#,
+        params![event_id, error, attempts],
+    )?;
+    Ok(())
+}
+
+pub fn remove_dead_letter(conn: &Connection, event_id: &str) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM dead_letters WHERE event_id = ?1", params![event_id])? > 0)
+}
+
+/// Newest first, skipping trashed events; page with `before` = the last `id` seen.
+pub fn list_dead_letters(conn: &Connection, before: Option<i64>, limit: u32) -> Result<Vec<DeadLetter>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT id, event_id, error, attempts, created_at, updated_at FROM dead_letters
+           WHERE (?1 IS NULL OR id < ?1) AND event_id NOT IN (SELECT event_id FROM trash)
+           ORDER BY id DESC LIMIT ?2"#,
+    )?;
+    let rows = stmt.query_map(params![before, limit], |r| {
+        Ok(DeadLetter {
+            id: r.get(0)?,
+            event_id: r.get(1)?,
+            error: r.get(2)?,
+            attempts: r.get(3)?,
+            created_at: r.get(4)?,
+            updated_at: r.get(5)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// Event ids of every dead letter outside the trash, oldest first.
+pub fn dead_letter_ids(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT event_id FROM dead_letters WHERE event_id NOT IN (SELECT event_id FROM trash) ORDER BY id")?;
+    let rows = stmt.query_map([], |r| r.get(0))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// One pipeline stage run, as recorded in `stage_runs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageRun {
+    pub event_id: String,
+    pub stage: String,
+    /// `ok`, `failed` or `skipped`.
+    pub status: String,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+impl StageRun {
+    pub fn ok(event_id: &str, stage: &str, output: &serde_json::Value, duration_ms: i64) -> Self {
+        Self::new(event_id, stage, "ok", Some(output.clone()), None, duration_ms)
+    }
+
+    pub fn failed(event_id: &str, stage: &str, error: &str, duration_ms: i64) -> Self {
+        Self::new(event_id, stage, "failed", None, Some(error.to_string()), duration_ms)
+    }
+
+    pub fn skipped(event_id: &str, stage: &str) -> Self {
+        Self::new(event_id, stage, "skipped", None, None, 0)
+    }
+
+    fn new(event_id: &str, stage: &str, status: &str, output: Option<serde_json::Value>, error: Option<String>, duration_ms: i64) -> Self {
+        Self { event_id: event_id.to_string(), stage: stage.to_string(), status: status.to_string(), output, error, duration_ms, created_at: String::new() }
+    }
+}
+
+pub fn record_stage_run(conn: &Connection, run: &StageRun) -> Result<()> {
+    let output = match &run.output { Some(v) => Some(serde_json::to_string(v)?), None => None };
+    conn.execute(
+        "INSERT INTO stage_runs (event_id, stage, status, output, error, duration_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![run.event_id, run.stage, run.status, output, run.error, run.duration_ms],
+    )?;
+    Ok(())
+}
+
+/// Every recorded stage run for an event, oldest first (retries append).
+pub fn list_stage_runs(conn: &Connection, event_id: &str) -> Result<Vec<StageRun>> {
+    let mut stmt = conn.prepare(
+        "SELECT event_id, stage, status, output, error, duration_ms, created_at FROM stage_runs WHERE event_id = ?1 ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![event_id], |r| {
+        Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, String>(2)?, r.get::<_, Option<String>>(3)?,
+            r.get::<_, Option<String>>(4)?, r.get::<_, i64>(5)?, r.get::<_, String>(6)?))
+    })?;
+    let mut out = Vec::new();
+    for r in rows {
+        let (event_id, stage, status, output, error, duration_ms, created_at) = r?;
+        let output = output.and_then(|o| serde_json::from_str(&o).ok());
+        out.push(StageRun { event_id, stage, status, output, error, duration_ms, created_at });
+    }
+    Ok(out)
+}
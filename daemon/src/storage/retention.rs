@@ -0,0 +1,172 @@
+//! Retention rules and the record of what each sweep deleted.
+//!
+//! A rule matches events by app, source, tag and privacy flag (unset fields
+//! match anything) and keeps them for `keep_days`, or forever without it.
+//! When several rules match an event the most generous one wins: an event
+//! only expires once every matching rule has stopped keeping it.
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::db::{PurgeCriteria, TrashScope};
+use crate::storage::Result;
+
+/// The user-editable part of a rule.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RuleSpec {
+    pub name: Option<String>,
+    pub app: Option<String>,
+    pub source: Option<String>,
+    pub tag: Option<String>,
+    pub privacy_flag: Option<String>,
+    /// Days to keep matching events; `None` keeps them forever.
+    pub keep_days: Option<u32>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRule {
+    pub rule_id: String,
+    #[serde(flatten)]
+    pub spec: RuleSpec,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl RetentionRule {
+    /// Events this rule matches, regardless of age.
+    pub fn matching(&self) -> PurgeCriteria {
+        PurgeCriteria {
+            app: self.spec.app.clone(),
+            source: self.spec.source.clone(),
+            tag: self.spec.tag.clone(),
+            privacy_flag: self.spec.privacy_flag.clone(),
+            ..PurgeCriteria::default()
+        }
+    }
+}
+
+/// The events `rule` expires: matched by it, stored at or before its cutoff,
+/// and not kept by any other enabled rule. `cutoff` gives the RFC3339 start
+/// of a rule's window, `None` for rules keeping events forever; `None` here if
+/// `rule` itself keeps everything.
+pub fn expiry_criteria(rule: &RetentionRule, rules: &[RetentionRule], cutoff: impl Fn(&RetentionRule) -> Option<String>) -> Option<PurgeCriteria> {
+    let own = cutoff(rule)?;
+    let excluding = rules.iter()
+        .filter(|r| r.spec.enabled && r.rule_id != rule.rule_id)
+        .map(|r| PurgeCriteria { start: cutoff(r), ..r.matching() })
+        .collect();
+    // Retention applies to trashed events too: they are past keeping either way.
+    Some(PurgeCriteria { end: Some(own), trash: TrashScope::All, excluding, ..rule.matching() })
+}
+
+const RULE_COLUMNS: &str = "rule_id, name, app, source, tag, privacy_flag, keep_days, enabled, created_at, updated_at";
+
+fn row_to_rule(r: &rusqlite::Row<'_>) -> rusqlite::Result<RetentionRule> {
+    Ok(RetentionRule {
+        rule_id: r.get(0)?,
+        spec: RuleSpec {
+            name: r.get(1)?,
+            app: r.get(2)?,
+            source: r.get(3)?,
+            tag: r.get(4)?,
+            privacy_flag: r.get(5)?,
+            keep_days: r.get(6)?,
+            enabled: r.get(7)?,
+        },
+        created_at: r.get(8)?,
+        updated_at: r.get(9)?,
+    })
+}
+
+pub fn create_rule(conn: &Connection, spec: &RuleSpec) -> Result<RetentionRule> {
+    let rule_id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO retention_rules (rule_id, name, app, source, tag, privacy_flag, keep_days, enabled) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![rule_id, spec.name, spec.app, spec.source, spec.tag, spec.privacy_flag, spec.keep_days, spec.enabled],
+    )?;
+    Ok(get_rule(conn, &rule_id)?.ok_or("rule vanished after insert")?)
+}
+
+/// Replaces the rule's spec; `None` if there is no such rule.
+pub fn update_rule(conn: &Connection, rule_id: &str, spec: &RuleSpec) -> Result<Option<RetentionRule>> {
+    let n = conn.execute(
+        r#"UPDATE retention_rules SET name = ?2, app = ?3, source = ?4, tag = ?5, privacy_flag = ?6, keep_days = ?7, enabled = ?8,
+           updated_at = CURRENT_TIMESTAMP WHERE rule_id = ?1"#,
+        params![rule_id, spec.name, spec.app, spec.source, spec.tag, spec.privacy_flag, spec.keep_days, spec.enabled],
+    )?;
+    if n == 0 { return Ok(None); }
+    get_rule(conn, rule_id)
+}
+
+/// Deletes the rule; its past runs stay in the report.
+pub fn delete_rule(conn: &Connection, rule_id: &str) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM retention_rules WHERE rule_id = ?1", params![rule_id])? > 0)
+}
+
+pub fn get_rule(conn: &Connection, rule_id: &str) -> Result<Option<RetentionRule>> {
+    Ok(conn
+        .query_row(&format!("SELECT {} FROM retention_rules WHERE rule_id = ?1", RULE_COLUMNS), params![rule_id], row_to_rule)
+        .optional()?)
+}
+
+/// In creation order.
+pub fn list_rules(conn: &Connection) -> Result<Vec<RetentionRule>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM retention_rules ORDER BY rowid", RULE_COLUMNS))?;
+    let rows = stmt.query_map([], row_to_rule)?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// What one rule deleted in one sweep.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleRun {
+    pub id: i64,
+    /// Shared by every rule evaluated in the same sweep.
+    pub run_id: String,
+    pub rule_id: String,
+    /// Events timestamped at or before this were past the rule's window.
+    pub cutoff: String,
+    /// Expired events found, including any a failed purge left behind.
+    pub matched: u64,
+    /// Per-layer counts, as returned by a purge.
+    pub summary: serde_json::Value,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+pub fn record_run(conn: &Connection, run: &RuleRun) -> Result<i64> {
+    conn.execute(
+        r#"INSERT INTO retention_runs (run_id, rule_id, cutoff, matched, summary, error, started_at, finished_at)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
+        params![run.run_id, run.rule_id, run.cutoff, run.matched as i64, run.summary.to_string(), run.error, run.started_at, run.finished_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Newest first; page with `before` = the last `id` seen.
+pub fn list_runs(conn: &Connection, rule_id: Option<&str>, before: Option<i64>, limit: u32) -> Result<Vec<RuleRun>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT id, run_id, rule_id, cutoff, matched, summary, error, started_at, finished_at FROM retention_runs
+           WHERE (?1 IS NULL OR rule_id = ?1) AND (?2 IS NULL OR id < ?2)
+           ORDER BY id DESC LIMIT ?3"#,
+    )?;
+    let rows = stmt.query_map(params![rule_id, before, limit], |r| {
+        let summary: Option<String> = r.get(5)?;
+        Ok(RuleRun {
+            id: r.get(0)?,
+            run_id: r.get(1)?,
+            rule_id: r.get(2)?,
+            cutoff: r.get(3)?,
+            matched: r.get::<_, i64>(4)? as u64,
+            summary: summary.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+            error: r.get(6)?,
+            started_at: r.get(7)?,
+            finished_at: r.get(8)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
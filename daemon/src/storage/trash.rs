@@ -0,0 +1,136 @@
+//! Soft-deleted events.
+//!
+//! A purge normally moves events to the trash: a `trash` row hides the event
+//! from every read API while its chunks, index entries and blob stay in place,
+//! so a restore only has to drop the row. Once `expires_at` has passed, the
+//! trash sweeper purges the event for good. Timestamps are RFC3339 UTC with
+//! whole seconds, so they compare correctly as text.
+use std::collections::HashSet;
+use std::time::Duration;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::handlers::EventEnvelope;
+use crate::storage::db::{row_to_envelope, PurgeCriteria};
+use crate::storage::Result;
+
+/// SQL condition keeping trashed events out of a query on `events`.
+pub const NOT_TRASHED: &str = "event_id NOT IN (SELECT event_id FROM trash)";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashBatch {
+    /// Identifies the events trashed by one purge, for restoring them together.
+    pub trash_id: String,
+    pub trashed_events: u64,
+    /// Matching events on hold, which were not trashed.
+    pub skipped_held: u64,
+    pub trashed_at: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashedEvent {
+    pub id: i64,
+    pub trash_id: String,
+    pub trashed_at: String,
+    pub expires_at: String,
+    pub event: EventEnvelope,
+}
+
+pub fn timestamp(t: OffsetDateTime) -> String {
+    t.replace_nanosecond(0).unwrap_or(t).format(&Rfc3339).unwrap_or_default()
+}
+
+/// Moves the live events matching `c` to the trash until `now + retention`.
+pub fn trash_events(conn: &Connection, c: &PurgeCriteria, retention: Duration, now: OffsetDateTime) -> Result<TrashBatch> {
+    let batch = TrashBatch {
+        trash_id: uuid::Uuid::new_v4().to_string(),
+        trashed_events: 0,
+        skipped_held: 0,
+        trashed_at: timestamp(now),
+        expires_at: timestamp(now + retention),
+    };
+    let live = PurgeCriteria { trash: Default::default(), ..c.clone() };
+    let Some((where_sql, where_params)) = live.where_sql() else { return Ok(batch) };
+    // The filter's bare `?`s are numbered after `?3`.
+    let mut params_vec = vec![batch.trash_id.clone(), batch.trashed_at.clone(), batch.expires_at.clone()];
+    params_vec.extend(where_params);
+    let trashed = conn.execute(
+        &format!(
+            "INSERT INTO trash (event_id, trash_id, trashed_at, expires_at) SELECT event_id, ?1, ?2, ?3 FROM events WHERE {} ORDER BY timestamp",
+            where_sql
+        ),
+        rusqlite::params_from_iter(params_vec.iter()),
+    )?;
+    Ok(TrashBatch { trashed_events: trashed as u64, skipped_held: crate::storage::db::count_held(conn, &live)?, ..batch })
+}
+
+/// Takes events out of the trash, by batch and/or id; returns the restored ids.
+pub fn restore(conn: &Connection, trash_id: Option<&str>, event_ids: Option<&[String]>) -> Result<Vec<String>> {
+    let mut clauses: Vec<String> = Vec::new();
+    let mut params_vec: Vec<String> = Vec::new();
+    if let Some(t) = trash_id { clauses.push("trash_id = ?".to_string()); params_vec.push(t.to_string()); }
+    if let Some(ids) = event_ids {
+        clauses.push(format!("event_id IN ({})", vec!["?"; ids.len()].join(", ")));
+        params_vec.extend(ids.iter().cloned());
+    }
+    if clauses.is_empty() || event_ids.is_some_and(|ids| ids.is_empty()) { return Ok(Vec::new()); }
+    let tx = conn.unchecked_transaction()?;
+    let restored: Vec<String> = {
+        let mut stmt = tx.prepare(&format!("SELECT event_id FROM trash WHERE {} ORDER BY id", clauses.join(" AND ")))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params_vec.iter()), |r| r.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    tx.execute(&format!("DELETE FROM trash WHERE {}", clauses.join(" AND ")), rusqlite::params_from_iter(params_vec.iter()))?;
+    tx.commit()?;
+    Ok(restored)
+}
+
+/// Newest first; page with `before` = the last `id` seen.
+pub fn list(conn: &Connection, trash_id: Option<&str>, before: Option<i64>, limit: u32) -> Result<Vec<TrashedEvent>> {
+    let mut stmt = conn.prepare(
+        r#"SELECT e.event_id, e.timestamp, e.source, e.app, e.content_pointer, e.content_hash, e.size_bytes, e.tags, e.privacy_flag,
+                  t.id, t.trash_id, t.trashed_at, t.expires_at
+           FROM trash t JOIN events e ON e.event_id = t.event_id
+           WHERE (?1 IS NULL OR t.trash_id = ?1) AND (?2 IS NULL OR t.id < ?2)
+           ORDER BY t.id DESC LIMIT ?3"#,
+    )?;
+    let rows = stmt.query_map(params![trash_id, before, limit], |r| {
+        Ok(TrashedEvent {
+            id: r.get(9)?,
+            trash_id: r.get(10)?,
+            trashed_at: r.get(11)?,
+            expires_at: r.get(12)?,
+            event: row_to_envelope(r)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// Trashed events whose restore window ended at or before `now`, oldest first.
+pub fn expired(conn: &Connection, now: OffsetDateTime) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT event_id FROM trash WHERE expires_at <= ?1 ORDER BY expires_at, id")?;
+    let rows = stmt.query_map(params![timestamp(now)], |r| r.get(0))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+pub fn is_trashed(conn: &Connection, event_id: &str) -> Result<bool> {
+    Ok(conn.prepare("SELECT 1 FROM trash WHERE event_id = ?1")?.exists(params![event_id])?)
+}
+
+/// The trashed events among `event_ids`.
+pub fn trashed_among(conn: &Connection, event_ids: &[String]) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT 1 FROM trash WHERE event_id = ?1")?;
+    let mut out = HashSet::new();
+    for id in event_ids {
+        if stmt.exists(params![id])? { out.insert(id.clone()); }
+    }
+    Ok(out)
+}
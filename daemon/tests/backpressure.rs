@@ -0,0 +1,70 @@
+#![cfg(test)]
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, queue, state, storage::db};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+fn envelope() -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-06-01T10:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: String::new(),
+        content_hash: "b".repeat(64),
+        size_bytes: 0,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+#[tokio::test]
+async fn full_queue_answers_429_with_retry_after() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_backpressure.db")).unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(2);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let client = Client::new();
+    let post = |body: serde_json::Value, verb: &str| client.post(format!("http://{}/v1/events{}", addr, verb))
+        .header("X-Vyaso-Local-Client", "vscode").json(&body).send();
+    for _ in 0..2 {
+        assert_eq!(post(serde_json::json!(envelope()), "").await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+    }
+
+    // Nothing is draining: the third event is refused at once instead of hanging.
+    let resp = tokio::time::timeout(std::time::Duration::from_secs(2), post(serde_json::json!(envelope()), "")).await
+        .expect("admission must not block").unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let retry: u64 = resp.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!((1..=60).contains(&retry));
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "queue_full");
+
+    let batch: serde_json::Value = post(serde_json::json!([envelope()]), ":batch").await.unwrap().json().await.unwrap();
+    assert_eq!(batch["rejected"], 1);
+    assert_eq!(batch["results"][0]["code"], 429);
+    assert!(batch["results"][0]["retry_after_secs"].is_u64());
+
+    let status: serde_json::Value = client.get(format!("http://{}/v1/queue/status", addr)).header("X-Vyaso-Local-Client", "vscode")
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["depth"], 2);
+    assert_eq!(status["capacity"], 2);
+    assert_eq!(status["accepted"], 2);
+    assert_eq!(status["dropped"], 2);
+
+    // Once the worker drains the queue, events are admitted again and the rate shows up.
+    queue::start_worker(rx, app_state.clone());
+    for _ in 0..100 {
+        if app_state.queue_stats.status(&app_state).drained >= 2 { break; }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(app_state.queue_stats.drain_rate() > 0.0);
+    assert_eq!(post(serde_json::json!(envelope()), "").await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+}
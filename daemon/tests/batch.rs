@@ -0,0 +1,91 @@
+#![cfg(test)]
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, state, storage::db};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+fn envelope(app: &str) -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-04-01T08:00:00Z".to_string(),
+        source: "browser-extension".to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: "a".repeat(64),
+        size_bytes: 0,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+async fn start() -> (String, mpsc::Receiver<state::IngestJob>) {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_batch.db")).unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(64);
+    let app = routes::router(Arc::new(state::AppState::new(conn, tx, None)));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    (format!("http://{}", addr), rx)
+}
+
+#[tokio::test]
+async fn json_array_batch_reports_per_event_status() {
+    let (base, mut rx) = start().await;
+    let good = envelope("chrome");
+    let mut bad = envelope("chrome");
+    bad.timestamp = "yesterday".to_string();
+    let body = serde_json::json!([good, bad, { "event_id": "not-an-envelope" }]);
+
+    let resp = Client::new().post(format!("{}/v1/events:batch", base)).header("X-Vyaso-Local-Client", "browser-extension")
+        .json(&body).send().await.unwrap();
+    assert!(resp.status().is_success());
+    let v: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(v["accepted"], 1);
+    assert_eq!(v["rejected"], 2);
+    let results = v["results"].as_array().unwrap();
+    assert_eq!(results[0]["status"], "accepted");
+    assert_eq!(results[0]["event_id"], good.event_id);
+    assert_eq!(results[1]["status"], "rejected");
+    assert_eq!(results[1]["code"], 400);
+    assert_eq!(results[1]["error"], "timestamp must be RFC3339");
+    assert_eq!(results[2]["event_id"], "not-an-envelope");
+
+    assert_eq!(rx.recv().await.unwrap().envelope.event_id, good.event_id);
+    assert!(rx.try_recv().is_err());
+
+    let not_array = Client::new().post(format!("{}/v1/events:batch", base)).header("X-Vyaso-Local-Client", "browser-extension")
+        .json(&good).send().await.unwrap();
+    assert_eq!(not_array.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // Only `:batch` and `:upload`, spelled exactly, are verbs.
+    for path in ["/v1/events:bulk", "/v1/eventsbatch", "/v1/events::batch", "/v1/events:upload:batch"] {
+        let unknown = Client::new().post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "browser-extension")
+            .json(&body).send().await.unwrap();
+        assert_eq!(unknown.status(), reqwest::StatusCode::NOT_FOUND, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn ndjson_batch_streams_lines_into_the_queue() {
+    let (base, mut rx) = start().await;
+    let events: Vec<EventEnvelope> = (0..3).map(|_| envelope("firefox")).collect();
+    let mut body = String::new();
+    for ev in &events {
+        body.push_str(&serde_json::to_string(ev).unwrap());
+        body.push('\n');
+    }
+    body.push_str("{ truncated\n\n");
+
+    let v: serde_json::Value = Client::new().post(format!("{}/v1/events:batch", base)).header("X-Vyaso-Local-Client", "browser-extension")
+        .header("Content-Type", "application/x-ndjson").body(body).send().await.unwrap().json().await.unwrap();
+    assert_eq!(v["accepted"], 3);
+    assert_eq!(v["rejected"], 1);
+    assert_eq!(v["results"][3]["index"], 3);
+    assert!(v["results"][3]["event_id"].is_null());
+    for ev in &events {
+        assert_eq!(rx.recv().await.unwrap().envelope.event_id, ev.event_id);
+    }
+}
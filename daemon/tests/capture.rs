@@ -0,0 +1,89 @@
+#![cfg(test)]
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, state, storage::db};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+fn envelope(app: &str, source: &str) -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: source.to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: "0".repeat(64),
+        size_bytes: 0,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+async fn start(db_path: &std::path::Path) -> (String, mpsc::Receiver<state::IngestJob>) {
+    let conn = db::init_db(db_path).unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(64);
+    let app = routes::router(Arc::new(state::AppState::new(conn, tx, None)));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    (format!("http://{}", addr), rx)
+}
+
+#[tokio::test]
+async fn pause_blocks_events_and_survives_restart() {
+    let db_path = std::path::PathBuf::from("data/vyaso_test_capture.db");
+    let (base, _rx) = start(&db_path).await;
+    let client = Client::new();
+    let post = |path: &str| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "desktop-app");
+
+    // The tray posts without a body.
+    assert!(post("/v1/resume").send().await.unwrap().status().is_success());
+    assert_eq!(post("/v1/events").json(&envelope("chrome", "browser-extension")).send().await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+
+    let v: serde_json::Value = post("/v1/pause").send().await.unwrap().json().await.unwrap();
+    assert_eq!(v["state"], "paused");
+    let resp = post("/v1/events").json(&envelope("chrome", "browser-extension")).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::LOCKED);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["error"], "capture_paused");
+    assert_eq!(body["paused"]["scope"], "global");
+
+    // A second daemon on the same database starts paused.
+    let (base2, _rx2) = start(&db_path).await;
+    let status: serde_json::Value = client.get(format!("{}/v1/capture/status", base2)).header("X-Vyaso-Local-Client", "desktop-app")
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(status["state"], "paused");
+
+    assert!(post("/v1/resume").send().await.unwrap().status().is_success());
+    assert_eq!(post("/v1/events").json(&envelope("chrome", "browser-extension")).send().await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+}
+
+#[tokio::test]
+async fn scoped_and_timed_pauses() {
+    let (base, _rx) = start(std::path::Path::new("data/vyaso_test_capture_scoped.db")).await;
+    let client = Client::new();
+    let post = |path: &str| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode");
+    assert!(post("/v1/resume").send().await.unwrap().status().is_success());
+
+    let v: serde_json::Value = post("/v1/pause").json(&serde_json::json!({ "app": "slack", "duration_secs": 600 })).send().await.unwrap().json().await.unwrap();
+    assert_eq!(v["state"], "resumed");
+    assert_eq!(v["scopes"][0]["value"], "slack");
+    assert_eq!(v["scopes"][0]["state"], "paused_until");
+
+    let resp = post("/v1/events").json(&envelope("slack", "manual")).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::LOCKED);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["paused"]["scope"], "app");
+    assert!(body["paused"]["until"].is_string());
+    assert_eq!(post("/v1/events").json(&envelope("vscode", "manual")).send().await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+
+    assert!(post("/v1/resume").json(&serde_json::json!({ "app": "slack" })).send().await.unwrap().status().is_success());
+    assert_eq!(post("/v1/events").json(&envelope("slack", "manual")).send().await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+
+    let past = post("/v1/pause").json(&serde_json::json!({ "until": "2000-01-01T00:00:00Z" })).send().await.unwrap();
+    assert_eq!(past.status(), reqwest::StatusCode::BAD_REQUEST);
+    let both = post("/v1/pause").json(&serde_json::json!({ "app": "a", "source": "b" })).send().await.unwrap();
+    assert_eq!(both.status(), reqwest::StatusCode::BAD_REQUEST);
+}
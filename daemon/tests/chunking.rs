@@ -0,0 +1,175 @@
+#![cfg(test)]
+use reqwest::{multipart, Client};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use vyasoai_daemon::chunking::{chunk_text, make_chunk_id, strategy_for};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::pipeline::Pipeline;
+use vyasoai_daemon::{queue, routes, state, storage::{blobs, db, hash}};
+
+/// Cases written by `python3 -m intelligence.tests.golden_chunks`.
+fn golden() -> Vec<Value> {
+    let raw = std::fs::read_to_string("tests/fixtures/chunking_golden.json").unwrap();
+    let v: Value = serde_json::from_str(&raw).unwrap();
+    v["cases"].as_array().unwrap().clone()
+}
+
+#[test]
+fn chunks_match_python_goldens() {
+    let cases = golden();
+    assert!(cases.len() >= 20);
+    for case in cases {
+        let name = case["name"].as_str().unwrap();
+        let content = case["content"].as_str().unwrap();
+        let chunks = chunk_text(content, case["content_type"].as_str().unwrap(), case["event_id"].as_str());
+        let expected = case["chunks"].as_array().unwrap();
+        let got: Vec<(i64, i64)> = chunks.iter().map(|c| (c.start_offset as i64, c.end_offset as i64)).collect();
+        let want: Vec<(i64, i64)> = expected.iter().map(|c| (c["start"].as_i64().unwrap(), c["end"].as_i64().unwrap())).collect();
+        assert_eq!(got, want, "spans for {}", name);
+        for (c, e) in chunks.iter().zip(expected) {
+            assert_eq!(c.chunk_id, e["chunk_id"].as_str().unwrap(), "chunk id for {}", name);
+            assert_eq!(c.text, e["text"].as_str().unwrap(), "text for {}", name);
+            assert_eq!(c.content_type, e["type"].as_str().unwrap(), "type for {}", name);
+            let row = c.to_row();
+            assert_eq!((row.event_id.as_str(), row.start_offset, row.end_offset), (case["event_id"].as_str().unwrap(), e["start"].as_i64().unwrap(), e["end"].as_i64().unwrap()));
+        }
+    }
+}
+
+#[test]
+fn chunk_ids_are_random_without_an_event() {
+    assert_eq!(make_chunk_id(Some("ev"), 0, 5), make_chunk_id(Some("ev"), 0, 5));
+    let (a, b) = (make_chunk_id(None, 0, 5), make_chunk_id(Some(""), 0, 5));
+    assert_eq!(a.len(), 32);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn strategy_follows_extension_then_mime_type() {
+    assert_eq!(strategy_for("text/plain; charset=utf-8", Some("/home/me/notes/README.md")), "markdown");
+    assert_eq!(strategy_for("text/plain; charset=utf-8", Some("src/main.RS")), "rust");
+    assert_eq!(strategy_for("text/plain", Some("app/index.tsx")), "typescript");
+    assert_eq!(strategy_for("text/html; charset=utf-8", None), "html");
+    assert_eq!(strategy_for("text/html; charset=utf-8", Some("page.txt")), "html");
+    assert_eq!(strategy_for("text/x-python", None), "python");
+    assert_eq!(strategy_for("text/plain; charset=utf-8", None), "prose");
+    assert_eq!(strategy_for("application/json", Some("")), "prose");
+}
+
+#[tokio::test]
+async fn pipeline_chunks_without_intel_workers() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_chunking.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    let mut pipeline = Pipeline::with_builtin_stages();
+    pipeline.configure("extract_text,chunk,index_chunks");
+    app_state.pipeline = Arc::new(pipeline);
+    let app_state = Arc::new(app_state);
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+
+    let content = format!("First paragraph about {}. It has two sentences.\n\nSecond paragraph.", uuid::Uuid::new_v4());
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-08-02T10:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(content.as_bytes()),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let form = multipart::Form::new()
+        .text("envelope", serde_json::to_string(&env).unwrap())
+        .part("content", multipart::Part::bytes(content.clone().into_bytes()));
+    let resp = client.post(format!("{}/v1/events:upload", base)).header("X-Vyaso-Local-Client", "vscode").multipart(form).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+
+    let mut state = Value::Null;
+    for _ in 0..100 {
+        state = client.get(format!("{}/v1/mem/{}/status", base, env.event_id)).header("X-Vyaso-Local-Client", "vscode")
+            .send().await.unwrap().json().await.unwrap();
+        if state["state"] == "indexed" || state["state"] == "failed" { break; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(state["state"], "indexed", "{}", state);
+
+    let expected: Vec<(String, i64, i64)> = chunk_text(&content, "prose", Some(&env.event_id)).iter()
+        .map(|c| (c.chunk_id.clone(), c.start_offset as i64, c.end_offset as i64)).collect();
+    assert_eq!(expected.len(), 2);
+    let conn = app_state.db.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT chunk_id, start_offset, end_offset FROM chunks WHERE event_id = ?1 ORDER BY start_offset").unwrap();
+    let stored: Vec<(String, i64, i64)> = stmt.query_map([&env.event_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?))).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(stored, expected);
+}
+
+#[tokio::test]
+async fn default_pipeline_keeps_native_chunks_when_intel_is_unavailable() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_chunking_default.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    // The built-in stages, including `intel`, whose package is not importable from here.
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+
+    let content = format!("# Notes {}\n\nFirst paragraph.\n\n## Next steps\n\n- ship the beta\n- write docs\n", uuid::Uuid::new_v4());
+    let path = std::env::temp_dir().join(format!("vyaso_chunking_{}.md", uuid::Uuid::new_v4().simple()));
+    std::fs::write(&path, &content).unwrap();
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-08-02T11:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: path.to_string_lossy().into_owned(),
+        content_hash: hash::compute_sha256(content.as_bytes()),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let resp = client.post(format!("{}/v1/events", base)).header("X-Vyaso-Local-Client", "vscode").json(&env).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+
+    let get = |path: String| client.get(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode").send();
+    let mut state = Value::Null;
+    for _ in 0..200 {
+        state = get(format!("/v1/mem/{}/status", env.event_id)).await.unwrap().json().await.unwrap();
+        if state["state"] == "indexed" || state["state"] == "failed" { break; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(state["state"], "indexed", "{}", state);
+    let stages: Value = get(format!("/v1/mem/{}/stages", env.event_id)).await.unwrap().json().await.unwrap();
+    let runs = stages["stages"].as_array().unwrap();
+    let run = |name: &str| runs.iter().find(|r| r["stage"] == name).unwrap().clone();
+    assert_eq!(run("chunk")["output"]["content_type"], "markdown");
+    assert_eq!(run("intel")["status"], "ok");
+    assert!(run("intel")["output"]["error"].is_string(), "{}", stages);
+
+    let expected: Vec<(String, String)> = chunk_text(&content, "markdown", Some(&env.event_id)).iter()
+        .map(|c| (c.chunk_id.clone(), c.content_type.clone())).collect();
+    assert!(expected.len() > 1);
+    {
+        let conn = app_state.db.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT chunk_id, content_type FROM chunks WHERE event_id = ?1 ORDER BY start_offset").unwrap();
+        let stored: Vec<(String, String)> = stmt.query_map([&env.event_id], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(stored, expected);
+    }
+    assert_eq!(app_state.vectors.vectors_for_event(&env.event_id).len(), expected.len());
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,76 @@
+#![cfg(test)]
+use reqwest::{header, Client, StatusCode};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+fn store(conn: &rusqlite::Connection, body: &[u8], privacy_flag: PrivacyFlag) -> String {
+    let content_hash = hash::compute_sha256(body);
+    blobs::save_blob(body, &content_hash).unwrap();
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-06-01T12:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "editor".to_string(),
+        content_pointer: String::new(),
+        content_hash,
+        size_bytes: body.len() as u64,
+        tags: vec![],
+        privacy_flag,
+    };
+    db::insert_event(conn, &env).unwrap();
+    env.event_id
+}
+
+#[tokio::test]
+async fn content_is_decoded_with_ranges_and_privacy_checks() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_content.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let text = format!("0123456789 plain text {}", uuid::Uuid::new_v4());
+    let plain = store(&conn, text.as_bytes(), PrivacyFlag::Default);
+    let secret = store(&conn, format!("sensitive {}", uuid::Uuid::new_v4()).as_bytes(), PrivacyFlag::Sensitive);
+    let never = store(&conn, b"never", PrivacyFlag::NeverStore);
+
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let app = routes::router(Arc::new(state::AppState::new(conn, tx, None)));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let client = Client::new();
+    let get = |id: &str| client.get(format!("http://{}/v1/mem/{}/content", addr, id)).header("X-Vyaso-Local-Client", "desktop-app");
+
+    let resp = get(&plain).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/plain; charset=utf-8");
+    assert_eq!(resp.headers()[header::ACCEPT_RANGES], "bytes");
+    assert_eq!(resp.text().await.unwrap(), text);
+
+    let resp = get(&plain).header(header::RANGE, "bytes=2-5").send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.headers()[header::CONTENT_RANGE], format!("bytes 2-5/{}", text.len()));
+    assert_eq!(resp.text().await.unwrap(), "2345");
+
+    let resp = get(&plain).header(header::RANGE, "bytes=-4").send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.text().await.unwrap(), &text[text.len() - 4..]);
+
+    let resp = get(&plain).header(header::RANGE, format!("bytes={}-", text.len())).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(resp.headers()[header::CONTENT_RANGE], format!("bytes */{}", text.len()));
+
+    // Multiple ranges are not supported and fall back to the whole body.
+    let resp = get(&plain).header(header::RANGE, "bytes=0-1,4-5").send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    assert_eq!(get(&secret).send().await.unwrap().status(), StatusCode::FORBIDDEN);
+    let resp = get(&secret).header("X-Vyaso-Confirm-Sensitive", "true").send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.text().await.unwrap().starts_with("sensitive "));
+
+    assert_eq!(get(&never).send().await.unwrap().status(), StatusCode::NOT_FOUND);
+    assert_eq!(get(&uuid::Uuid::new_v4().to_string()).send().await.unwrap().status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,75 @@
+#![cfg(test)]
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+async fn status_of(client: &Client, base: &str, id: &str) -> serde_json::Value {
+    client.get(format!("{}/v1/mem/{}/status", base, id)).header("X-Vyaso-Local-Client", "desktop-app")
+        .send().await.unwrap().json().await.unwrap()
+}
+
+async fn wait_for(client: &Client, base: &str, id: &str, pred: impl Fn(&serde_json::Value) -> bool) -> serde_json::Value {
+    for _ in 0..200 {
+        let v = status_of(client, base, id).await;
+        if pred(&v) { return v; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("event {} never reached the expected state", id);
+}
+
+#[tokio::test]
+async fn failed_intel_jobs_are_dead_lettered_and_retryable() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_dead_letters.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    // The intel package is not importable from the daemon's working directory, so every attempt fails.
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-07-01T12:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: String::new(),
+        content_hash: "c".repeat(64),
+        size_bytes: 0,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let client = Client::new();
+    let post = |path: String| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "desktop-app");
+    assert_eq!(post("/v1/events".into()).json(&env).send().await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+
+    let failed = wait_for(&client, &base, &env.event_id, |v| v["state"] == "failed").await;
+    let attempts = failed["attempts"].as_i64().unwrap();
+    assert!(attempts >= 1);
+    assert!(!failed["last_error"].as_str().unwrap().is_empty());
+
+    let list: serde_json::Value = client.get(format!("{}/v1/dead-letters?limit=200", base)).header("X-Vyaso-Local-Client", "desktop-app")
+        .send().await.unwrap().json().await.unwrap();
+    let letter = list["dead_letters"].as_array().unwrap().iter().find(|l| l["event_id"] == env.event_id.as_str()).expect("dead-lettered");
+    assert_eq!(letter["attempts"], attempts);
+
+    let resp = post(format!("/v1/dead-letters/{}/retry", env.event_id)).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    let retried = wait_for(&client, &base, &env.event_id, |v| v["state"] == "failed" && v["attempts"].as_i64() > Some(attempts)).await;
+    assert_eq!(retried["attempts"], attempts * 2);
+
+    let bulk: serde_json::Value = post("/v1/dead-letters/retry".into()).json(&serde_json::json!({ "event_ids": [env.event_id, "not-dead-lettered"] }))
+        .send().await.unwrap().json().await.unwrap();
+    assert_eq!(bulk["retrying"], 1);
+    wait_for(&client, &base, &env.event_id, |v| v["state"] == "failed" && v["attempts"].as_i64() == Some(attempts * 3)).await;
+
+    let missing = post(format!("/v1/dead-letters/{}/retry", uuid::Uuid::new_v4())).send().await.unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+}
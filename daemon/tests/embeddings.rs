@@ -0,0 +1,162 @@
+#![cfg(test)]
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use reqwest::{multipart, Client};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use vyasoai_daemon::embeddings::{embed, EmbeddingProvider, MockEmbedder, OpenAiCompatEmbedder};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::pipeline::Pipeline;
+use vyasoai_daemon::{queue, routes, state, storage::{blobs, db, hash}};
+
+async fn serve(app: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    format!("http://{}", addr)
+}
+
+#[tokio::test]
+async fn mock_embedder_matches_the_python_adapter() {
+    let texts = ["launch plan".to_string(), String::new(), "héllo wörld — 🚀".to_string()];
+    let script = "import json, sys\n\
+                  from intelligence.embeddings.mock import MockEmbeddingAdapter\n\
+                  a = MockEmbeddingAdapter(dim=128)\n\
+                  print(json.dumps([a.embed(t) for t in json.loads(sys.argv[1])]))";
+    let out = std::process::Command::new("python3")
+        .args(["-c", script, &serde_json::to_string(&texts).unwrap()])
+        .current_dir(std::fs::canonicalize("..").unwrap())
+        .output().unwrap();
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    let expected: Vec<Vec<f64>> = serde_json::from_slice(&out.stdout).unwrap();
+
+    let mock = MockEmbedder::default();
+    assert_eq!(mock.model_version(), "mock-emb-128");
+    let got = embed(&mock, &texts).await.unwrap();
+    for (g, e) in got.iter().zip(&expected) {
+        assert_eq!(g, &e.iter().map(|x| *x as f32).collect::<Vec<_>>());
+    }
+}
+
+#[derive(Default)]
+struct Stub {
+    requests: AtomicUsize,
+    batches: Mutex<Vec<usize>>,
+    /// Requests to fail with a 503 before answering.
+    failures: AtomicUsize,
+}
+
+/// An OpenAI-compatible `/v1/embeddings` stand-in: vectors are `[len, first byte]`
+/// and come back in reverse order, each tagged with its input index.
+async fn embeddings_route(State(stub): State<Arc<Stub>>, Json(body): Json<Value>) -> (StatusCode, Json<Value>) {
+    stub.requests.fetch_add(1, Ordering::SeqCst);
+    if body["model"] == "bad" {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": "unknown model" })));
+    }
+    if stub.failures.load(Ordering::SeqCst) > 0 {
+        stub.failures.fetch_sub(1, Ordering::SeqCst);
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(json!({ "error": "loading model" })));
+    }
+    let input = body["input"].as_array().unwrap();
+    stub.batches.lock().unwrap().push(input.len());
+    let data: Vec<Value> = input.iter().enumerate().rev().map(|(i, t)| {
+        let t = t.as_str().unwrap();
+        json!({ "object": "embedding", "index": i, "embedding": [t.len() as f64, t.bytes().next().unwrap_or(0) as f64] })
+    }).collect();
+    (StatusCode::OK, Json(json!({ "object": "list", "data": data, "model": body["model"] })))
+}
+
+#[tokio::test]
+async fn openai_compat_embedder_batches_and_retries() {
+    let stub = Arc::new(Stub { failures: AtomicUsize::new(1), ..Stub::default() });
+    let url = serve(Router::new().route("/v1/embeddings", post(embeddings_route)).with_state(stub.clone())).await;
+
+    let mut provider = OpenAiCompatEmbedder::new(&url, "nomic-embed-text", Duration::from_secs(5)).unwrap();
+    provider.batch_size = 2;
+    provider.retry_backoff = Duration::from_millis(10);
+    assert_eq!(provider.model_version(), "openai-compat:nomic-embed-text");
+    let texts: Vec<String> = ["a", "bb", "ccc", "dddd", "eeeee"].iter().map(|s| s.to_string()).collect();
+    let vectors = embed(&provider, &texts).await.unwrap();
+    assert_eq!(vectors, [[1.0, 97.0], [2.0, 98.0], [3.0, 99.0], [4.0, 100.0], [5.0, 101.0]]);
+    assert_eq!(*stub.batches.lock().unwrap(), [2, 2, 1]);
+    assert_eq!(stub.requests.load(Ordering::SeqCst), 4, "the 503 is retried once");
+
+    // Retries run out.
+    stub.failures.store(10, Ordering::SeqCst);
+    stub.requests.store(0, Ordering::SeqCst);
+    provider.max_retries = 2;
+    assert!(embed(&provider, &texts[..1]).await.is_err());
+    assert_eq!(stub.requests.load(Ordering::SeqCst), 3);
+
+    // Client errors are not retried.
+    stub.failures.store(0, Ordering::SeqCst);
+    stub.requests.store(0, Ordering::SeqCst);
+    let bad = OpenAiCompatEmbedder::new(&url, "bad", Duration::from_secs(5)).unwrap();
+    let err = embed(&bad, &texts[..1]).await.unwrap_err();
+    assert!(err.to_string().contains("400"), "{}", err);
+    assert_eq!(stub.requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn embed_stage_indexes_vectors_without_intel_workers() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_embeddings.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let stub = Arc::new(Stub::default());
+    let url = serve(Router::new().route("/v1/embeddings", post(embeddings_route)).with_state(stub.clone())).await;
+
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    let mut provider = OpenAiCompatEmbedder::new(&url, "local-test", Duration::from_secs(5)).unwrap();
+    provider.model_version = "local-test-v2".to_string();
+    app_state.embedder = Arc::new(provider);
+    let mut pipeline = Pipeline::with_builtin_stages();
+    pipeline.configure("extract_text,chunk,embed,index_chunks");
+    app_state.pipeline = Arc::new(pipeline);
+    let app_state = Arc::new(app_state);
+    queue::start_worker(rx, app_state.clone());
+    let base = serve(routes::router(app_state.clone())).await;
+    let client = Client::new();
+
+    let content = format!("Embedding notes {}.\n\nA second paragraph to embed.", uuid::Uuid::new_v4());
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-08-04T10:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(content.as_bytes()),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let form = multipart::Form::new()
+        .text("envelope", serde_json::to_string(&env).unwrap())
+        .part("content", multipart::Part::bytes(content.into_bytes()));
+    let resp = client.post(format!("{}/v1/events:upload", base)).header("X-Vyaso-Local-Client", "vscode").multipart(form).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+
+    let mut status = Value::Null;
+    for _ in 0..100 {
+        status = client.get(format!("{}/v1/mem/{}/status", base, env.event_id)).header("X-Vyaso-Local-Client", "vscode")
+            .send().await.unwrap().json().await.unwrap();
+        if status["state"] == "indexed" || status["state"] == "failed" { break; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(status["state"], "indexed", "{}", status);
+
+    let stages: Value = client.get(format!("{}/v1/mem/{}/stages", base, env.event_id)).header("X-Vyaso-Local-Client", "vscode")
+        .send().await.unwrap().json().await.unwrap();
+    let embed_run = stages["stages"].as_array().unwrap().iter().find(|r| r["stage"] == "embed").unwrap().clone();
+    assert_eq!(embed_run["status"], "ok");
+    assert_eq!(embed_run["output"]["model_version"], "local-test-v2");
+    assert_eq!(embed_run["output"]["provider"], "openai-compat");
+    let index_run = stages["stages"].as_array().unwrap().iter().find(|r| r["stage"] == "index_chunks").unwrap().clone();
+    assert_eq!(index_run["output"]["vectors"], embed_run["output"]["vectors"]);
+    assert!(!app_state.vectors.vectors_for_event(&env.event_id).is_empty());
+    assert_eq!(app_state.vectors.status().model_version.as_deref(), Some("local-test-v2"));
+    assert!(stub.requests.load(Ordering::SeqCst) >= 1);
+}
@@ -0,0 +1,112 @@
+#![cfg(test)]
+use futures_util::StreamExt;
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+fn envelope(app: &str, tags: &[&str]) -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: "vscode".to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: "b".repeat(64),
+        size_bytes: 0,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+async fn start() -> String {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_feed.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    format!("{}", addr)
+}
+
+async fn post(client: &Client, addr: &str, env: &EventEnvelope) {
+    let resp = client.post(format!("http://{}/v1/events", addr)).header("X-Vyaso-Local-Client", "vscode").json(env).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+}
+
+/// Reads SSE frames until `n` data payloads have arrived.
+async fn read_sse(resp: &mut reqwest::Response, n: usize) -> Vec<(String, serde_json::Value)> {
+    let mut buf = String::new();
+    let mut out = Vec::new();
+    while out.len() < n {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), resp.chunk()).await.expect("sse timed out").unwrap().unwrap();
+        buf.push_str(std::str::from_utf8(&chunk).unwrap());
+        while let Some(end) = buf.find("\n\n") {
+            let frame: String = buf.drain(..end + 2).collect();
+            let kind = frame.lines().find_map(|l| l.strip_prefix("event: ")).map(str::to_string);
+            let data = frame.lines().find_map(|l| l.strip_prefix("data: "));
+            if let (Some(kind), Some(data)) = (kind, data) {
+                out.push((kind, serde_json::from_str(data).unwrap()));
+            }
+        }
+    }
+    out
+}
+
+#[tokio::test]
+async fn sse_feed_filters_and_replays_from_last_event_id() {
+    let addr = start().await;
+    let client = Client::new();
+    let app_name = format!("feed-{}", uuid::Uuid::new_v4().simple());
+    let sse = |query: Vec<(&'static str, String)>| client.get(format!("http://{}/v1/feed", addr)).query(&query).header("X-Vyaso-Local-Client", "desktop-app");
+
+    let mut resp = sse(vec![("app", app_name.clone()), ("tags", "keep,also".to_string())]).send().await.unwrap();
+    assert_eq!(resp.headers()["content-type"], "text/event-stream");
+    let first = envelope(&app_name, &["keep"]);
+    post(&client, &addr, &envelope("some-other-app", &["keep"])).await;
+    post(&client, &addr, &envelope(&app_name, &["drop"])).await;
+    post(&client, &addr, &first).await;
+    let got = read_sse(&mut resp, 1).await;
+    assert_eq!(got[0].0, "stored");
+    assert_eq!(got[0].1["event_id"], first.event_id);
+    let first_id = got[0].1["id"].as_str().unwrap().to_string();
+    drop(resp);
+
+    // Published while no one is connected; replayed on reconnect.
+    let missed = envelope(&app_name, &["also"]);
+    post(&client, &addr, &missed).await;
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let mut resp = sse(vec![("app", app_name.clone())]).header("Last-Event-ID", first_id).send().await.unwrap();
+    let got = read_sse(&mut resp, 1).await;
+    assert_eq!(got[0].0, "stored");
+    assert_eq!(got[0].1["event_id"], missed.event_id);
+
+    // An id from another daemon run can't be replayed.
+    let mut resp = sse(vec![("last_event_id", "deadbeef-7".to_string())]).send().await.unwrap();
+    assert_eq!(read_sse(&mut resp, 1).await[0].0, "resync");
+}
+
+#[tokio::test]
+async fn websocket_feed_pushes_stored_events() {
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
+    let addr = start().await;
+    let app_name = format!("feed-ws-{}", uuid::Uuid::new_v4().simple());
+    let mut req = format!("ws://{}/v1/feed/ws?app={}", addr, app_name).into_client_request().unwrap();
+    req.headers_mut().insert("X-Vyaso-Local-Client", "vscode".parse().unwrap());
+    let (mut ws, _) = tokio_tungstenite::connect_async(req).await.unwrap();
+
+    let env = envelope(&app_name, &[]);
+    post(&Client::new(), &addr, &env).await;
+    let msg = tokio::time::timeout(Duration::from_secs(5), ws.next()).await.unwrap().unwrap().unwrap();
+    let Message::Text(text) = msg else { panic!("expected a text frame, got {:?}", msg) };
+    let v: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(v["kind"], "stored");
+    assert_eq!(v["event_id"], env.event_id);
+}
@@ -1,7 +1,7 @@
 #![cfg(test)]
 use reqwest::Client;
 use tokio::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use axum::Router;
 use tokio::net::TcpListener;
 
@@ -12,7 +12,7 @@ async fn health_endpoint_returns_ok() {
     // Build app with a small queue
     let conn = rusqlite::Connection::open_in_memory().unwrap();
     let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
-    let app_state = Arc::new(state::AppState { db: Arc::new(Mutex::new(conn)), queue_tx: tx.clone(), key_manager: None });
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
     let app: Router = routes::router(app_state.clone());
 
     // Bind to an ephemeral TCP port on loopback for test purposes
@@ -0,0 +1,109 @@
+#![cfg(test)]
+use axum::Router;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::{routes, state, storage::{db, hash}};
+
+fn stored_event(conn: &rusqlite::Connection, app: &str) -> String {
+    let body = format!("held {}", uuid::Uuid::new_v4());
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: (time::OffsetDateTime::now_utc() - time::Duration::days(10)).format(&Rfc3339).unwrap(),
+        source: "manual".to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(body.as_bytes()),
+        size_bytes: body.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    db::insert_event(conn, &env).unwrap();
+    env.event_id
+}
+
+#[tokio::test]
+async fn held_events_survive_purges_retention_and_trash_sweeps() {
+    // Retention rules are global, so each run gets a fresh database.
+    let db_path = std::env::temp_dir().join(format!("vyaso_test_holds_{}.db", uuid::Uuid::new_v4().simple()));
+    let conn = db::init_db(&db_path).unwrap();
+    let app_name = "design-notes";
+    let (held, e2, e3) = (stored_event(&conn, app_name), stored_event(&conn, app_name), stored_event(&conn, app_name));
+    let trashed_held = stored_event(&conn, "incidents");
+
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app: Router = routes::router(app_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+    let url = |path: &str| format!("{}{}", base, path);
+    let send = |req: reqwest::RequestBuilder| req.header("X-Vyaso-Local-Client", "desktop-app").send();
+    let post = |path: &str, body: Value| send(client.post(url(path)).json(&body));
+
+    let hold: Value = post(&format!("/v1/mem/{}/hold", held), json!({ "reason": "ADR-7" })).await.unwrap().json().await.unwrap();
+    assert_eq!(hold["event_id"], held.as_str());
+    assert_eq!(hold["reason"], "ADR-7");
+    assert_eq!(send(client.post(url(&format!("/v1/mem/{}/hold", e2)))).await.unwrap().status(), reqwest::StatusCode::OK, "the body is optional");
+    assert_eq!(send(client.delete(url(&format!("/v1/mem/{}/hold", e2)))).await.unwrap().status(), reqwest::StatusCode::OK);
+    assert_eq!(send(client.delete(url(&format!("/v1/mem/{}/hold", e2)))).await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+    let unknown = post(&format!("/v1/mem/{}/hold", uuid::Uuid::new_v4()), json!({})).await.unwrap();
+    assert_eq!(unknown.status(), reqwest::StatusCode::NOT_FOUND);
+    let mem: Value = send(client.get(url(&format!("/v1/mem/{}", held)))).await.unwrap().json().await.unwrap();
+    assert_eq!(mem["hold"]["reason"], "ADR-7");
+    let holds: Value = send(client.get(url("/v1/holds"))).await.unwrap().json().await.unwrap();
+    let ids: Vec<&str> = holds["holds"].as_array().unwrap().iter().map(|h| h["event_id"].as_str().unwrap()).collect();
+    assert_eq!(ids, [held.as_str()]);
+
+    let preview: Value = post("/v1/purge", json!({ "app": app_name, "dry_run": true })).await.unwrap().json().await.unwrap();
+    assert_eq!(preview["events"], 2);
+    assert_eq!(preview["skipped_held"], 1);
+
+    // A retention rule expires the rest of the app but not the held event.
+    let resp = post("/v1/retention/rules", json!({ "app": app_name, "keep_days": 1 })).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::CREATED);
+    let report: Value = post("/v1/retention/run", json!({})).await.unwrap().json().await.unwrap();
+    let run = &report["rules"][0];
+    assert_eq!(run["matched"], 2, "{}", report);
+    assert_eq!(run["summary"]["deleted_events"], 2);
+    assert_eq!(run["summary"]["skipped_held"], 1);
+    let exists = |id: &str| app_state.db.lock().unwrap()
+        .query_row("SELECT COUNT(*) FROM events WHERE event_id = ?1", [id], |r| r.get::<_, i64>(0)).unwrap() == 1;
+    assert!(exists(&held));
+    assert!(!exists(&e2) && !exists(&e3));
+
+    // Neither trashing nor an immediate purge touches it without the override.
+    let batch: Value = post("/v1/purge", json!({ "app": app_name })).await.unwrap().json().await.unwrap();
+    assert_eq!(batch["trashed_events"], 0);
+    assert_eq!(batch["skipped_held"], 1);
+    let purged: Value = post("/v1/purge", json!({ "event_ids": [held], "skip_trash": true })).await.unwrap().json().await.unwrap();
+    assert_eq!(purged["deleted_events"], 0);
+    assert_eq!(purged["skipped_held"], 1);
+    assert!(exists(&held));
+
+    let purged: Value = post("/v1/purge", json!({ "event_ids": [held], "skip_trash": true, "include_held": true })).await.unwrap().json().await.unwrap();
+    assert_eq!(purged["deleted_events"], 1, "{}", purged);
+    assert_eq!(purged["skipped_held"], 0);
+    let holds: Value = send(client.get(url("/v1/holds"))).await.unwrap().json().await.unwrap();
+    assert!(holds["holds"].as_array().unwrap().is_empty(), "purging a held event drops its hold");
+
+    // A held event in the trash outlives its restore window until released.
+    post(&format!("/v1/mem/{}/hold", trashed_held), json!({})).await.unwrap();
+    let batch: Value = post("/v1/purge", json!({ "event_ids": [trashed_held], "include_held": true })).await.unwrap().json().await.unwrap();
+    assert_eq!(batch["trashed_events"], 1);
+    app_state.db.lock().unwrap().execute("UPDATE trash SET expires_at = '2000-01-01T00:00:00Z' WHERE event_id = ?1", [&trashed_held]).unwrap();
+    let sweep: Value = post("/v1/trash/sweep", json!({})).await.unwrap().json().await.unwrap();
+    assert_eq!(sweep["deleted_events"], 0);
+    assert_eq!(sweep["skipped_held"], 1);
+    assert_eq!(send(client.delete(url(&format!("/v1/mem/{}/hold", trashed_held)))).await.unwrap().status(), reqwest::StatusCode::OK);
+    let sweep: Value = post("/v1/trash/sweep", json!({})).await.unwrap().json().await.unwrap();
+    assert_eq!(sweep["deleted_events"], 1);
+    assert!(!exists(&trashed_held));
+    let _ = std::fs::remove_file(&db_path);
+}
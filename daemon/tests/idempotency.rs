@@ -0,0 +1,132 @@
+#![cfg(test)]
+use reqwest::{Client, StatusCode};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::storage::db::InsertOutcome;
+
+fn envelope(body: &[u8], pointer: &str) -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: "browser-extension".to_string(),
+        app: "chrome".to_string(),
+        content_pointer: pointer.to_string(),
+        content_hash: hash::compute_sha256(body),
+        size_bytes: body.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+fn ref_count(conn: &rusqlite::Connection, h: &str) -> i64 {
+    db::get_blob_index(conn, h).unwrap().map(|(_, r)| r).unwrap_or(0)
+}
+
+#[test]
+fn repeated_insert_keeps_refcounts_exact() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_idempotency.db")).unwrap();
+    let body = format!("retried {}", uuid::Uuid::new_v4());
+    let env = envelope(body.as_bytes(), "");
+    assert_eq!(db::insert_event(&conn, &env).unwrap(), InsertOutcome::Inserted);
+    assert_eq!(db::insert_event(&conn, &env).unwrap(), InsertOutcome::Duplicate);
+    assert_eq!(ref_count(&conn, &env.content_hash), 1);
+
+    let mut changed = env.clone();
+    changed.content_hash = hash::compute_sha256(b"different content");
+    let before = ref_count(&conn, &changed.content_hash);
+    assert_eq!(db::insert_event(&conn, &changed).unwrap(), InsertOutcome::Conflict { existing_hash: env.content_hash.clone() });
+    assert_eq!(ref_count(&conn, &changed.content_hash), before);
+    assert_eq!(ref_count(&conn, &env.content_hash), 1);
+    assert_eq!(db::get_event(&conn, &env.event_id).unwrap().content_hash, env.content_hash);
+}
+
+#[tokio::test]
+async fn retried_envelopes_are_no_ops_and_conflicts_are_refused() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_idempotency_http.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let body = format!("page body {}", uuid::Uuid::new_v4());
+    let path = std::env::temp_dir().join(format!("vyaso_idem_{}.txt", uuid::Uuid::new_v4().simple()));
+    std::fs::write(&path, &body).unwrap();
+    let env = envelope(body.as_bytes(), &path.to_string_lossy());
+
+    // Two copies in flight at once: both pass admission, the worker keeps one.
+    tx.send(env.clone().into()).await.unwrap();
+    tx.send(env.clone().into()).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    {
+        let conn = app_state.db.lock().unwrap();
+        assert_eq!(ref_count(&conn, &env.content_hash), 1);
+        let fts_rows: i64 = conn.query_row("SELECT COUNT(*) FROM memory_fts WHERE event_id = ?1", [&env.event_id], |r| r.get(0)).unwrap();
+        assert_eq!(fts_rows, 1);
+        let events: i64 = conn.query_row("SELECT COUNT(*) FROM events WHERE event_id = ?1", [&env.event_id], |r| r.get(0)).unwrap();
+        assert_eq!(events, 1);
+    }
+
+    let client = Client::new();
+    let url = format!("http://{}/v1/events", addr);
+    let resp = client.post(&url).header("X-Vyaso-Local-Client", "browser-extension").json(&env).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let v: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(v["duplicate"], true);
+
+    let mut changed = env.clone();
+    changed.content_hash = hash::compute_sha256(b"edited page");
+    let resp = client.post(&url).header("X-Vyaso-Local-Client", "browser-extension").json(&changed).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let v: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(v["existing_hash"], env.content_hash);
+
+    let batch: serde_json::Value = client.post(format!("http://{}/v1/events:batch", addr)).header("X-Vyaso-Local-Client", "browser-extension")
+        .json(&serde_json::json!([env, changed])).send().await.unwrap().json().await.unwrap();
+    assert_eq!(batch["results"][0]["status"], "duplicate");
+    assert_eq!(batch["results"][1]["code"], 409);
+}
+
+#[tokio::test]
+async fn blobs_are_only_kept_for_stored_events() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_idempotency_blobs.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    // Stands in for any insert failure after the content was read.
+    conn.execute_batch("CREATE TRIGGER IF NOT EXISTS refuse_events BEFORE INSERT ON events WHEN NEW.app = 'refused' BEGIN SELECT RAISE(ABORT, 'refused'); END;").unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
+    let worker = queue::start_worker(rx, app_state.clone());
+
+    let write = |body: &str| {
+        let path = std::env::temp_dir().join(format!("vyaso_idem_{}.txt", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&path, body).unwrap();
+        path
+    };
+    let refused_body = format!("refused body {}", uuid::Uuid::new_v4());
+    let mut refused = envelope(refused_body.as_bytes(), &write(&refused_body).to_string_lossy());
+    refused.app = "refused".to_string();
+    let body = format!("shared body {}", uuid::Uuid::new_v4());
+    let path = write(&body);
+    let stored = envelope(body.as_bytes(), &path.to_string_lossy());
+    let twin = envelope(body.as_bytes(), &path.to_string_lossy());
+
+    tx.send(refused.clone().into()).await.unwrap();
+    tx.send(stored.clone().into()).await.unwrap();
+    tx.send(stored.clone().into()).await.unwrap();
+    tx.send(twin.clone().into()).await.unwrap();
+    assert!(worker.shutdown(std::time::Duration::from_secs(10)).await);
+
+    let conn = app_state.db.lock().unwrap();
+    assert!(db::get_event(&conn, &refused.event_id).is_err());
+    assert!(!blobs::blob_path(&refused.content_hash).exists(), "no event uses the refused event's blob");
+    assert_eq!(ref_count(&conn, &stored.content_hash), 2, "the retry is a no-op, the twin shares the blob");
+    let (blob_path, _) = db::get_blob_index(&conn, &stored.content_hash).unwrap().unwrap();
+    assert_eq!(blobs::load_blob(std::path::Path::new(&blob_path)).unwrap(), body.as_bytes());
+}
@@ -0,0 +1,73 @@
+#![cfg(test)]
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+#[tokio::test]
+async fn mismatched_pointer_content_is_rejected_and_listed() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_ingest_errors.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let run = uuid::Uuid::new_v4().simple().to_string();
+    let path = std::env::temp_dir().join(format!("vyaso_ingest_err_{}.txt", run));
+    let actual = format!("actual bytes {}", run);
+    std::fs::write(&path, &actual).unwrap();
+    let envelope = |declared: &str, size: usize| EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: "vscode".to_string(),
+        app: "editor".to_string(),
+        content_pointer: path.to_string_lossy().to_string(),
+        content_hash: hash::compute_sha256(declared.as_bytes()),
+        size_bytes: size as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    // Claims another blob's hash with the right size, then the right hash with the wrong size.
+    let forged = envelope(&format!("someone else's blob {}", run), actual.len());
+    let short = envelope(&actual, actual.len() - 1);
+
+    let client = Client::new();
+    let base = format!("http://{}", addr);
+    for env in [&forged, &short] {
+        let resp = client.post(format!("{}/v1/events", base)).header("X-Vyaso-Local-Client", "vscode").json(env).send().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    {
+        let conn = app_state.db.lock().unwrap();
+        assert!(db::get_event(&conn, &forged.event_id).is_err());
+        assert!(db::get_event(&conn, &short.event_id).is_err());
+        assert!(db::get_blob_index(&conn, &forged.content_hash).unwrap().is_none());
+    }
+
+    let v: serde_json::Value = client.get(format!("{}/v1/ingest/errors", base)).query(&[("event_id", forged.event_id.as_str())])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap().json().await.unwrap();
+    let errors = v["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0]["kind"], "hash_mismatch");
+    assert_eq!(errors[0]["declared_hash"], forged.content_hash);
+    assert_eq!(errors[0]["actual_hash"], hash::compute_sha256(actual.as_bytes()));
+
+    let v: serde_json::Value = client.get(format!("{}/v1/ingest/errors", base)).query(&[("event_id", short.event_id.as_str())])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap().json().await.unwrap();
+    assert_eq!(v["errors"][0]["kind"], "size_mismatch");
+    assert_eq!(v["errors"][0]["actual_size"], actual.len());
+
+    let page: serde_json::Value = client.get(format!("{}/v1/ingest/errors", base)).query(&[("limit", "1")])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap().json().await.unwrap();
+    assert_eq!(page["errors"].as_array().unwrap().len(), 1);
+    assert!(page["next_before"].is_i64());
+}
@@ -3,7 +3,7 @@ use axum::Router;
 use reqwest::Client;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use vyasoai_daemon::{routes, queue, storage::{db, hash, blobs}, state};
 use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
@@ -15,7 +15,7 @@ async fn end_to_end_ingestion_creates_rows_and_blobs() {
     blobs::ensure_blob_base().unwrap();
 
     let (tx, rx) = mpsc::channel::<state::IngestJob>(64);
-    let app_state = Arc::new(state::AppState { db: Arc::new(Mutex::new(conn)), queue_tx: tx.clone(), key_manager: Some(state::KeyManager::new()) });
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), Some(state::KeyManager::new())));
     queue::start_worker(rx, app_state.clone());
     let app: Router = routes::router(app_state.clone());
 
@@ -24,7 +24,7 @@ async fn end_to_end_ingestion_creates_rows_and_blobs() {
     tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
 
     let tmpdir = std::env::temp_dir();
-    let contents = vec![b"alpha".to_vec(), b"beta".to_vec(), b"gamma".to_vec()];
+    let contents = [b"alpha".to_vec(), b"beta".to_vec(), b"gamma".to_vec()];
     let mut envelopes = Vec::new();
     for (i, c) in contents.iter().enumerate() {
         let path = tmpdir.join(format!("vyaso_integ_{}.txt", i));
@@ -0,0 +1,143 @@
+#![cfg(test)]
+use std::time::Duration;
+
+use serde_json::json;
+use vyasoai_daemon::intel::{IntelConfig, IntelError, IntelPool};
+
+/// A worker speaking the same framing; the event id picks its behaviour.
+const FAKE_WORKER: &str = r#"
+import json, os, sys, time
+inp, out = sys.stdin.buffer, sys.stdout.buffer
+while True:
+    length = None
+    while True:
+        line = inp.readline()
+        if not line:
+            sys.exit(0)
+        if not line.strip():
+            break
+        name, _, value = line.decode().partition(":")
+        if name.lower() == "content-length":
+            length = int(value)
+    req = json.loads(inp.read(length))
+    ev = (req.get("params") or {}).get("event_id")
+    if ev == "crash":
+        os._exit(1)
+    if ev == "hang":
+        time.sleep(30)
+    if ev == "reject":
+        resp = {"jsonrpc": "2.0", "id": req["id"], "error": {"code": -32000, "message": "bad job"}}
+    else:
+        resp = {"jsonrpc": "2.0", "id": req["id"], "result": {"status": "ok", "pid": os.getpid(), "event_id": ev, "chunks": []}}
+    body = json.dumps(resp).encode()
+    out.write(b"Content-Length: %d\r\n\r\n" % len(body) + body)
+    out.flush()
+"#;
+
+fn fake_pool(workers: usize) -> IntelPool {
+    std::fs::create_dir_all("data").unwrap();
+    let script = std::path::PathBuf::from(format!("data/fake_intel_worker_{}.py", uuid::Uuid::new_v4().simple()));
+    std::fs::write(&script, FAKE_WORKER).unwrap();
+    IntelPool::new(IntelConfig {
+        program: "python3".to_string(),
+        args: vec![script.to_string_lossy().into_owned()],
+        workers,
+        request_timeout: Duration::from_millis(1500),
+        ..IntelConfig::default()
+    })
+}
+
+async fn pid(pool: &IntelPool) -> u64 {
+    pool.call("ping", json!({})).await.unwrap()["pid"].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn crashed_and_hung_workers_are_restarted() {
+    let pool = fake_pool(1);
+    let first = pid(&pool).await;
+    assert_eq!(pid(&pool).await, first, "workers are reused between requests");
+
+    assert!(matches!(pool.process(json!({ "event_id": "crash" })).await, Err(IntelError::Crashed(_))));
+    let second = pid(&pool).await;
+    assert_ne!(second, first);
+
+    let started = std::time::Instant::now();
+    assert!(matches!(pool.process(json!({ "event_id": "hang" })).await, Err(IntelError::Timeout(_))));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_ne!(pid(&pool).await, second);
+    assert_eq!(pool.status()[0].restarts, 2);
+
+    // An RPC error is an answer: the worker stays up.
+    let third = pid(&pool).await;
+    assert!(matches!(pool.process(json!({ "event_id": "reject" })).await, Err(IntelError::Rpc { .. })));
+    assert_eq!(pid(&pool).await, third);
+    assert_eq!(pool.status()[0].restarts, 2);
+}
+
+#[tokio::test]
+async fn health_check_replaces_a_dead_worker() {
+    let pool = fake_pool(1);
+    let first = pid(&pool).await;
+    std::process::Command::new("kill").arg("-9").arg(first.to_string()).status().unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    pool.check_health().await;
+    let status = &pool.status()[0];
+    assert_eq!(status.restarts, 1);
+    assert_eq!(status.state, "idle");
+    assert!(status.pid.is_some_and(|p| p as u64 != first));
+}
+
+#[tokio::test]
+async fn requests_wait_for_a_worker_busy_with_a_health_ping() {
+    let pool = fake_pool(1);
+    pid(&pool).await;
+    // The ping holds the only worker when the request arrives.
+    let (_, result) = tokio::join!(pool.check_health(), pool.process(json!({ "event_id": "e1" })));
+    assert_eq!(result.unwrap()["event_id"], "e1");
+}
+
+#[tokio::test]
+async fn status_reads_a_busy_worker_without_its_slot_lock() {
+    let pool = fake_pool(1);
+    let worker = pid(&pool).await;
+    let (_, status) = tokio::join!(pool.process(json!({ "event_id": "hang" })), async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        pool.status()[0].clone()
+    });
+    assert_eq!(status.state, "busy");
+    assert_eq!(status.pid.map(u64::from), Some(worker), "read from the slot's report, not its lock");
+    let status = &pool.status()[0];
+    assert_eq!(status.state, "stopped");
+    assert!(status.last_error.as_deref().is_some_and(|e| e.contains("timed out")), "{:?}", status.last_error);
+}
+
+#[tokio::test]
+async fn python_worker_chunks_a_blob() {
+    let dir = std::env::temp_dir().join(format!("vyaso_intel_{}", uuid::Uuid::new_v4().simple()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let blob = dir.join("note.txt");
+    std::fs::write(&blob, "Planning notes for the launch.\n\nWe agreed to ship the beta next week.").unwrap();
+    let repo_root = std::fs::canonicalize("..").unwrap();
+    let pool = IntelPool::new(IntelConfig {
+        args: ["-m", "intelligence.cli", "serve", "--index-dir"].iter().map(|s| s.to_string())
+            .chain([dir.join("index").to_string_lossy().into_owned()]).collect(),
+        workdir: Some(repo_root),
+        workers: 1,
+        request_timeout: Duration::from_secs(30),
+        ..IntelConfig::default()
+    });
+    let result = pool.process(json!({
+        "job_id": "j1",
+        "event_id": "ev-1",
+        "blob_path": blob.to_string_lossy(),
+        "content_type": "prose",
+        "source": "vscode",
+        "params": { "backend": "mock" },
+    })).await.unwrap();
+    assert_eq!(result["status"], "ok");
+    assert_eq!(result["job_id"], "j1");
+    assert!(!result["chunks"].as_array().unwrap().is_empty());
+    assert_eq!(result["chunks"][0]["vector"].as_array().unwrap().len(), 128);
+    assert_eq!(result["index"]["model_version"], "mock-emb-128");
+}
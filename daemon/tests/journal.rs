@@ -0,0 +1,64 @@
+#![cfg(test)]
+use reqwest::{multipart, Client};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs, hash, journal}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+const DB: &str = "data/vyaso_test_journal.db";
+
+#[tokio::test]
+async fn accepted_events_survive_a_crash_and_are_replayed() {
+    blobs::ensure_blob_base().unwrap();
+    // First daemon: accepts but its worker never runs, as if it crashed right after the 202.
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let crashed = Arc::new(state::AppState::new(db::init_db(std::path::Path::new(DB)).unwrap(), tx, None));
+    let app = routes::router(crashed.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let content = format!("journaled upload {}", uuid::Uuid::new_v4()).into_bytes();
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: "browser-extension".to_string(),
+        app: "chrome".to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(&content),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let form = multipart::Form::new()
+        .text("envelope", serde_json::to_string(&env).unwrap())
+        .part("content", multipart::Part::bytes(content.clone()));
+    let resp = Client::new().post(format!("http://{}/v1/events:upload", addr)).header("X-Vyaso-Local-Client", "browser-extension")
+        .multipart(form).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+
+    {
+        let conn = crashed.db.lock().unwrap();
+        let pending = journal::pending(&conn).unwrap();
+        let (_, journaled, bytes) = pending.iter().find(|(_, e, _)| e.event_id == env.event_id).expect("journaled before 202");
+        assert_eq!(journaled.content_hash, env.content_hash);
+        assert_eq!(bytes.as_deref(), Some(content.as_slice()));
+        // Inline bytes are encrypted at rest.
+        let raw: Vec<u8> = conn.query_row("SELECT content FROM ingest_journal WHERE event_id = ?1", [&env.event_id], |r| r.get(0)).unwrap();
+        assert!(!raw.windows(content.len()).any(|w| w == content.as_slice()));
+        assert!(db::get_event(&conn, &env.event_id).is_err());
+    }
+
+    // Restart: the replayed event is stored and its journal row cleared.
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(1024);
+    let restarted = Arc::new(state::AppState::new(db::init_db(std::path::Path::new(DB)).unwrap(), tx, None));
+    queue::start_worker(rx, restarted.clone());
+    assert!(queue::replay_journal(&restarted).await.unwrap() >= 1);
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let conn = restarted.db.lock().unwrap();
+    assert_eq!(db::get_event(&conn, &env.event_id).unwrap().content_hash, env.content_hash);
+    assert!(journal::pending(&conn).unwrap().iter().all(|(_, e, _)| e.event_id != env.event_id));
+}
@@ -2,7 +2,7 @@
 use axum::Router;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 use reqwest::Client;
 
 use vyasoai_daemon::{routes, state, storage::{db, blobs}};
@@ -15,7 +15,7 @@ async fn get_mem_returns_event_and_blob_metadata() {
     blobs::ensure_blob_base().unwrap();
 
     let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
-    let app_state = Arc::new(state::AppState { db: Arc::new(Mutex::new(conn)), queue_tx: tx.clone(), key_manager: None });
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
     vyasoai_daemon::queue::start_worker(rx, app_state.clone());
     let app: Router = routes::router(app_state.clone());
 
@@ -53,7 +53,7 @@ async fn get_mem_returns_event_and_blob_metadata() {
 async fn get_mem_404_for_missing() {
     let conn = rusqlite::Connection::open_in_memory().unwrap();
     let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
-    let app_state = Arc::new(state::AppState { db: Arc::new(Mutex::new(conn)), queue_tx: tx.clone(), key_manager: None });
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
     let app: Router = routes::router(app_state.clone());
 
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
@@ -0,0 +1,85 @@
+#![cfg(test)]
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::path_policy::{PathPolicy, DEFAULT_DENY};
+
+fn scratch() -> (PathBuf, PathBuf) {
+    let base = std::env::temp_dir().join(format!("vyaso_policy_{}", uuid::Uuid::new_v4().simple()));
+    let root = base.join("root");
+    std::fs::create_dir_all(root.join(".ssh")).unwrap();
+    std::fs::create_dir_all(base.join("outside")).unwrap();
+    std::fs::write(root.join("notes.txt"), b"notes").unwrap();
+    std::fs::write(root.join(".ssh/id_rsa"), b"PRIVATE KEY").unwrap();
+    std::fs::write(base.join("outside/secret.txt"), b"secret").unwrap();
+    (base, root)
+}
+
+fn default_deny() -> Vec<String> {
+    DEFAULT_DENY.iter().map(|s| s.to_string()).collect()
+}
+
+#[cfg(unix)]
+#[test]
+fn resolve_enforces_roots_denylist_and_symlinks() {
+    let (base, root) = scratch();
+    std::os::unix::fs::symlink(base.join("outside/secret.txt"), root.join("escape.txt")).unwrap();
+    std::os::unix::fs::symlink(root.join(".ssh/id_rsa"), root.join("innocent.txt")).unwrap();
+    let policy = PathPolicy::new(std::slice::from_ref(&root), &default_deny()).unwrap();
+    let p = |rel: &str| root.join(rel).to_string_lossy().to_string();
+
+    assert_eq!(policy.resolve(&p("notes.txt")).unwrap(), Some(std::fs::canonicalize(root.join("notes.txt")).unwrap()));
+    assert_eq!(policy.resolve("").unwrap(), None);
+    assert_eq!(policy.resolve(&p("missing.txt")).unwrap(), None);
+    assert!(policy.resolve(&p(".ssh/id_rsa")).unwrap_err().contains("**/.ssh/**"));
+    assert!(policy.resolve(&p("innocent.txt")).unwrap_err().contains("denied pattern"));
+    assert!(policy.resolve(&p("escape.txt")).unwrap_err().contains("outside the capture roots"));
+    assert!(policy.resolve(&base.join("outside/secret.txt").to_string_lossy()).unwrap_err().contains("outside"));
+    assert!(policy.resolve(&p(".")).unwrap_err().contains("regular file"));
+    assert!(policy.resolve("relative/notes.txt").unwrap_err().contains("absolute"));
+}
+
+#[tokio::test]
+async fn denied_pointers_are_refused_at_admission_and_in_the_worker() {
+    let (_base, root) = scratch();
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_path_policy.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let key = root.join(".ssh/id_rsa");
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: "vscode".to_string(),
+        app: "editor".to_string(),
+        content_pointer: key.to_string_lossy().to_string(),
+        content_hash: hash::compute_sha256(b"PRIVATE KEY"),
+        size_bytes: 11,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let resp = Client::new().post(format!("http://{}/v1/events", addr)).header("X-Vyaso-Local-Client", "vscode").json(&env).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::FORBIDDEN);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("denied pattern"));
+
+    // A job that reaches the worker without passing admission is still refused.
+    tx.send(env.clone().into()).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let conn = app_state.db.lock().unwrap();
+    assert!(db::get_event(&conn, &env.event_id).is_err());
+    assert!(db::get_blob_index(&conn, &env.content_hash).unwrap().is_none());
+    let errors = db::list_ingest_errors(&conn, Some(&env.event_id), None, 10).unwrap();
+    assert_eq!(errors[0].kind, "path_denied");
+}
@@ -0,0 +1,116 @@
+#![cfg(test)]
+use async_trait::async_trait;
+use reqwest::{multipart, Client};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::pipeline::{stages, Pipeline, Processor, StageContext};
+
+/// A team-provided stage: counts words in the extracted text.
+struct WordCount;
+
+#[async_trait]
+impl Processor for WordCount {
+    fn name(&self) -> &str { "word_count" }
+
+    async fn process(&self, ctx: &mut StageContext) -> vyasoai_daemon::storage::Result<Value> {
+        let text = ctx.text.as_deref().ok_or("no text extracted")?;
+        Ok(json!({ "words": text.split_whitespace().count() }))
+    }
+}
+
+fn pipeline() -> Pipeline {
+    let mut p = Pipeline::new();
+    p.register(Arc::new(stages::ExtractText));
+    p.register(Arc::new(WordCount));
+    p.configure("extract_text,word_count;application/json=extract_text;application/pdf=word_count");
+    p
+}
+
+#[test]
+fn stages_are_ordered_per_content_type() {
+    let names = |ct: &str| pipeline().stages_for(ct).iter().map(|s| s.name().to_string()).collect::<Vec<_>>();
+    assert_eq!(names("text/plain; charset=utf-8"), ["extract_text", "word_count"]);
+    assert_eq!(names("application/json"), ["extract_text"]);
+
+    let mut p = pipeline();
+    p.configure("word_count,no_such_stage,extract_text;image/=");
+    assert_eq!(p.stages_for("text/html").iter().map(|s| s.name()).collect::<Vec<_>>(), ["word_count", "extract_text"]);
+    assert!(p.stages_for("image/png").is_empty());
+    assert_eq!(Pipeline::with_builtin_stages().stage_names(), ["extract_text", "chunk", "intel", "embed", "index_chunks"]);
+}
+
+async fn upload(client: &Client, base: &str, content: &[u8]) -> String {
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-08-01T09:30:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(content),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let form = multipart::Form::new()
+        .text("envelope", serde_json::to_string(&env).unwrap())
+        .part("content", multipart::Part::bytes(content.to_vec()));
+    let resp = client.post(format!("{}/v1/events:upload", base)).header("X-Vyaso-Local-Client", "vscode").multipart(form).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    env.event_id
+}
+
+async fn settled(client: &Client, base: &str, id: &str) -> (Value, Value) {
+    let get = |path: String| client.get(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode").send();
+    for _ in 0..100 {
+        let status: Value = get(format!("/v1/mem/{}/status", id)).await.unwrap().json().await.unwrap();
+        if status["state"] == "indexed" || status["state"] == "failed" {
+            let stages: Value = get(format!("/v1/mem/{}/stages", id)).await.unwrap().json().await.unwrap();
+            return (status, stages);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("event {} never settled", id);
+}
+
+#[tokio::test]
+async fn custom_stages_run_and_record_outputs() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_pipeline.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.pipeline = Arc::new(pipeline());
+    let app_state = Arc::new(app_state);
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+
+    let text = format!("three words here {}", uuid::Uuid::new_v4());
+    let id = upload(&client, &base, text.as_bytes()).await;
+    let (status, stages) = settled(&client, &base, &id).await;
+    assert_eq!(status["state"], "indexed");
+    let runs = stages["stages"].as_array().unwrap();
+    assert_eq!(runs.iter().map(|r| r["stage"].as_str().unwrap()).collect::<Vec<_>>(), ["extract_text", "word_count"]);
+    assert!(runs.iter().all(|r| r["status"] == "ok" && r["duration_ms"].is_i64()));
+    assert_eq!(runs[1]["output"]["words"], 4);
+
+    // JSON content only gets text extraction.
+    let id = upload(&client, &base, format!("{{\"k\": \"{}\"}}", uuid::Uuid::new_v4()).as_bytes()).await;
+    let (_, stages) = settled(&client, &base, &id).await;
+    assert_eq!(stages["stages"].as_array().unwrap().len(), 1);
+
+    // A PDF runs word_count without extracted text: the stage fails and so does the event.
+    let id = upload(&client, &base, format!("%PDF-1.4 {}", uuid::Uuid::new_v4()).as_bytes()).await;
+    let (status, stages) = settled(&client, &base, &id).await;
+    assert_eq!(status["state"], "failed");
+    assert!(status["last_error"].as_str().unwrap().starts_with("word_count: no text extracted"));
+    assert_eq!(stages["stages"][0]["status"], "failed");
+}
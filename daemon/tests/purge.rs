@@ -2,9 +2,14 @@
 use axum::Router;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc;
-use std::sync::{Arc, Mutex};
-use reqwest::Client;
+use std::sync::Arc;
+use std::time::Duration;
+use reqwest::{multipart, Client};
+use serde_json::{json, Value};
 
+use vyasoai_daemon::index::VectorIndex;
+use vyasoai_daemon::intel::{IntelConfig, IntelPool};
+use vyasoai_daemon::purge::PurgeJobs;
 use vyasoai_daemon::{routes, state, storage::{db, blobs, hash}};
 use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag, PurgeRequest};
 
@@ -15,7 +20,7 @@ async fn purge_deletes_events_and_blobs_when_refcount_zero() {
     blobs::ensure_blob_base().unwrap();
 
     let (tx, rx) = mpsc::channel::<state::IngestJob>(64);
-    let app_state = Arc::new(state::AppState { db: Arc::new(Mutex::new(conn)), queue_tx: tx.clone(), key_manager: None });
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
     vyasoai_daemon::queue::start_worker(rx, app_state.clone());
     let app: Router = routes::router(app_state.clone());
 
@@ -49,10 +54,233 @@ async fn purge_deletes_events_and_blobs_when_refcount_zero() {
     let _ = client.post(format!("{}/v1/events", base)).header("X-Vyaso-Local-Client", "vscode").json(&env2).send().await.unwrap();
     tokio::time::sleep(std::time::Duration::from_millis(800)).await;
 
-    let req = PurgeRequest { event_ids: Some(vec![env1.event_id.clone(), env2.event_id.clone()]), start: None, end: None, app: None, source: None, privacy_flag: None };
+    let req = PurgeRequest { event_ids: Some(vec![env1.event_id.clone(), env2.event_id.clone()]), skip_trash: true, ..Default::default() };
     let resp = client.post(format!("{}/v1/purge", base)).header("X-Vyaso-Local-Client", "vscode").json(&req).send().await.unwrap();
     assert!(resp.status().is_success());
     let v: serde_json::Value = resp.json().await.unwrap();
     assert_eq!(v["deleted_events"].as_u64().unwrap(), 2);
     assert_eq!(v["deleted_blobs"].as_u64().unwrap(), 1);
 }
+
+#[tokio::test]
+async fn purge_cascades_through_chunks_indexes_and_intel_workers() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_purge_cascade.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let scratch = std::env::temp_dir().join(format!("vyaso_purge_{}", uuid::Uuid::new_v4().simple()));
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.intel = Arc::new(IntelPool::new(IntelConfig {
+        args: ["-m", "intelligence.cli", "serve", "--index-dir"].iter().map(|s| s.to_string())
+            .chain([scratch.join("intel").to_string_lossy().into_owned()]).collect(),
+        workdir: Some(std::fs::canonicalize("..").unwrap()),
+        workers: 1,
+        request_timeout: Duration::from_secs(30),
+        ..IntelConfig::default()
+    }));
+    app_state.vectors = Arc::new(VectorIndex::open(&scratch.join("index")).unwrap());
+    let app_state = Arc::new(app_state);
+    vyasoai_daemon::queue::start_worker(rx, app_state.clone());
+    let app: Router = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+
+    let token = format!("osprey{}", uuid::Uuid::new_v4().simple());
+    let content = format!("Private notes about {}.\n\nNothing of this may survive a purge.", token);
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-08-04T10:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "purge-cascade".to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(content.as_bytes()),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    let form = multipart::Form::new()
+        .text("envelope", serde_json::to_string(&env).unwrap())
+        .part("content", multipart::Part::bytes(content.into_bytes()));
+    let resp = client.post(format!("{}/v1/events:upload", base)).header("X-Vyaso-Local-Client", "vscode").multipart(form).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    let mut status = Value::Null;
+    for _ in 0..200 {
+        status = client.get(format!("{}/v1/mem/{}/status", base, env.event_id)).header("X-Vyaso-Local-Client", "vscode")
+            .send().await.unwrap().json().await.unwrap();
+        if status["state"] == "indexed" || status["state"] == "failed" { break; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(status["state"], "indexed", "{}", status);
+    let count = |table: &str| -> i64 {
+        app_state.db.lock().unwrap()
+            .query_row(&format!("SELECT COUNT(*) FROM {} WHERE event_id = ?1", table), [&env.event_id], |r| r.get(0)).unwrap()
+    };
+    let chunks = count("chunks");
+    assert!(chunks > 0);
+    assert_eq!(app_state.vectors.vectors_for_event(&env.event_id).len() as i64, chunks);
+    app_state.vectors.flush().unwrap();
+
+    let req = PurgeRequest { event_ids: Some(vec![env.event_id.clone()]), skip_trash: true, ..Default::default() };
+    let resp = client.post(format!("{}/v1/purge", base)).header("X-Vyaso-Local-Client", "vscode").json(&req).send().await.unwrap();
+    assert!(resp.status().is_success());
+    let v: Value = resp.json().await.unwrap();
+    assert_eq!(v["deleted_events"], 1, "{}", v);
+    assert_eq!(v["deleted_blobs"], 1);
+    assert_eq!(v["deleted_chunks"], chunks);
+    assert_eq!(v["deleted_fts_rows"], chunks);
+    assert!(v["deleted_stage_runs"].as_u64().unwrap() >= 4);
+    assert_eq!(v["deleted_status_rows"], 1);
+    assert_eq!(v["deleted_vectors"], chunks);
+    assert_eq!(v["deleted_intel_vectors"], chunks);
+    assert!(v.get("intel_errors").is_none(), "{}", v);
+
+    for table in ["events", "chunks", "memory_fts", "stage_runs", "event_status"] {
+        assert_eq!(count(table), 0, "{} rows survived", table);
+    }
+    assert!(app_state.vectors.vectors_for_event(&env.event_id).is_empty());
+    // The persisted index was rewritten, not left for the next periodic flush.
+    let reopened = VectorIndex::open(&scratch.join("index")).unwrap();
+    assert!(reopened.vectors_for_event(&env.event_id).is_empty());
+    assert_eq!(reopened.status().deleted, 0);
+    let found: Value = client.post(format!("{}/v1/memories/search", base)).header("X-Vyaso-Local-Client", "vscode")
+        .json(&serde_json::json!({ "query": token })).send().await.unwrap().json().await.unwrap();
+    assert!(found["hits"].as_array().unwrap().is_empty(), "{}", found);
+    let _ = std::fs::remove_dir_all(&scratch);
+}
+
+fn stored_event(conn: &rusqlite::Connection, app: &str, timestamp: &str, body: &str) -> EventEnvelope {
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: timestamp.to_string(),
+        source: "vscode".to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(body.as_bytes()),
+        size_bytes: body.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    db::insert_event(conn, &env).unwrap();
+    env
+}
+
+async fn serve(app_state: Arc<state::AppState>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app: Router = routes::router(app_state);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    base
+}
+
+#[tokio::test]
+async fn dry_run_previews_a_purge_without_deleting() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_purge_preview.db")).unwrap();
+    let app_name = format!("preview-{}", uuid::Uuid::new_v4().simple());
+    let shared = format!("shared {}", uuid::Uuid::new_v4());
+    let kept = format!("kept {}", uuid::Uuid::new_v4());
+    let a = stored_event(&conn, &app_name, "2025-08-01T09:00:00Z", &shared);
+    let b = stored_event(&conn, &app_name, "2025-08-02T09:00:00Z", &shared);
+    let c = stored_event(&conn, &app_name, "2025-08-03T09:00:00Z", &kept);
+    // Another app's event keeps `kept`'s blob alive.
+    stored_event(&conn, "elsewhere", "2025-08-03T09:00:00Z", &kept);
+    let row = db::ChunkRow { chunk_id: uuid::Uuid::new_v4().simple().to_string(), event_id: a.event_id.clone(), start_offset: 0, end_offset: 6, content_type: "prose".into() };
+    db::insert_chunks(&conn, &[row]).unwrap();
+
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    let base = serve(app_state.clone()).await;
+    let client = Client::new();
+    let resp = client.post(format!("{}/v1/purge", base)).header("X-Vyaso-Local-Client", "vscode")
+        .json(&json!({ "app": app_name, "dry_run": true, "sample_limit": 2 })).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let v: Value = resp.json().await.unwrap();
+    assert_eq!(v["dry_run"], true);
+    assert_eq!(v["events"], 3);
+    assert_eq!(v["bytes"], a.size_bytes + b.size_bytes + c.size_bytes);
+    assert_eq!(v["blobs"], 1, "{}", v);
+    assert_eq!(v["chunks"], 1);
+    let sample: Vec<&str> = v["sample"].as_array().unwrap().iter().map(|e| e["event_id"].as_str().unwrap()).collect();
+    assert_eq!(sample, [c.event_id.as_str(), b.event_id.as_str()]);
+
+    let remaining: i64 = app_state.db.lock().unwrap()
+        .query_row("SELECT COUNT(*) FROM events WHERE app = ?1", [&app_name], |r| r.get(0)).unwrap();
+    assert_eq!(remaining, 3);
+}
+
+#[tokio::test]
+async fn background_purge_jobs_report_progress_and_can_be_cancelled() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_purge_jobs.db")).unwrap();
+    let app_name = format!("jobs-{}", uuid::Uuid::new_v4().simple());
+    for i in 0..20 {
+        stored_event(&conn, &app_name, &format!("2025-08-01T09:{:02}:00Z", i), &format!("event {} {}", i, app_name));
+    }
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    let mut jobs = PurgeJobs::default();
+    jobs.batch_size = 1;
+    app_state.purge_jobs = Arc::new(jobs);
+    // A worker that never answers makes every batch take a while.
+    app_state.intel = Arc::new(IntelPool::new(IntelConfig {
+        program: "sh".to_string(),
+        args: vec!["-c".to_string(), "sleep 0.2".to_string()],
+        workers: 1,
+        ..IntelConfig::default()
+    }));
+    let app_state = Arc::new(app_state);
+    let base = serve(app_state.clone()).await;
+    let client = Client::new();
+    let get = |path: String| client.get(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode").send();
+    let post = |path: String, body: Value| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode").json(&body).send();
+    let remaining = || -> i64 {
+        app_state.db.lock().unwrap().query_row("SELECT COUNT(*) FROM events WHERE app = ?1", [&app_name], |r| r.get(0)).unwrap()
+    };
+
+    let resp = post("/v1/purge".into(), json!({ "app": app_name, "background": true, "skip_trash": true })).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    let job: Value = resp.json().await.unwrap();
+    assert_eq!((job["state"].as_str(), job["total"].as_u64()), (Some("running"), Some(20)));
+    let id = job["job_id"].as_str().unwrap().to_string();
+
+    let mut status = Value::Null;
+    for _ in 0..100 {
+        status = get(format!("/v1/purge/jobs/{}", id)).await.unwrap().json().await.unwrap();
+        if status["processed"].as_u64().unwrap() >= 1 { break; }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(status["state"], "running", "{}", status);
+    let resp = post(format!("/v1/purge/jobs/{}/cancel", id), json!({})).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    for _ in 0..100 {
+        status = get(format!("/v1/purge/jobs/{}", id)).await.unwrap().json().await.unwrap();
+        if status["state"] != "running" { break; }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(status["state"], "cancelled", "{}", status);
+    let processed = status["processed"].as_u64().unwrap();
+    assert!(processed < 20);
+    assert_eq!(status["summary"]["deleted_events"], processed);
+    assert!(!status["summary"]["intel_errors"].as_array().unwrap().is_empty());
+    assert!(status["finished_at"].is_string());
+    assert_eq!(remaining(), 20 - processed as i64);
+    let again = post(format!("/v1/purge/jobs/{}/cancel", id), json!({})).await.unwrap();
+    assert_eq!(again.status(), reqwest::StatusCode::CONFLICT);
+
+    // The rest, to completion.
+    let job: Value = post("/v1/purge".into(), json!({ "app": app_name, "background": true, "skip_trash": true })).await.unwrap().json().await.unwrap();
+    let id2 = job["job_id"].as_str().unwrap().to_string();
+    for _ in 0..200 {
+        status = get(format!("/v1/purge/jobs/{}", id2)).await.unwrap().json().await.unwrap();
+        if status["state"] != "running" { break; }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(status["state"], "completed", "{}", status);
+    assert_eq!(status["processed"], status["total"]);
+    assert_eq!(status["summary"]["deleted_events"].as_u64().unwrap() + processed, 20);
+    assert_eq!(remaining(), 0);
+
+    let list: Value = get("/v1/purge/jobs".into()).await.unwrap().json().await.unwrap();
+    let ids: Vec<&str> = list["jobs"].as_array().unwrap().iter().map(|j| j["job_id"].as_str().unwrap()).collect();
+    assert_eq!(ids, [id2.as_str(), id.as_str()]);
+    assert_eq!(get("/v1/purge/jobs/nope".into()).await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+}
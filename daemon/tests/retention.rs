@@ -0,0 +1,136 @@
+#![cfg(test)]
+use axum::Router;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use time::format_description::well_known::Rfc3339;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::{routes, state, storage::{db, hash}};
+
+fn stored_event(conn: &rusqlite::Connection, source: &str, app: &str, days_old: i64, privacy_flag: PrivacyFlag, tags: &[&str]) -> String {
+    let body = format!("retention {}", uuid::Uuid::new_v4());
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: (time::OffsetDateTime::now_utc() - time::Duration::days(days_old)).format(&Rfc3339).unwrap(),
+        source: source.to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(body.as_bytes()),
+        size_bytes: body.len() as u64,
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        privacy_flag,
+    };
+    db::insert_event(conn, &env).unwrap();
+    env.event_id
+}
+
+#[tokio::test]
+async fn retention_rules_expire_events_and_report_each_run() {
+    // Rules are global, so each run gets a fresh database.
+    let db_path = std::env::temp_dir().join(format!("vyaso_test_retention_{}.db", uuid::Uuid::new_v4().simple()));
+    let conn = db::init_db(&db_path).unwrap();
+    let old_browser = stored_event(&conn, "browser-extension", "chrome", 40, PrivacyFlag::Default, &[]);
+    let recent_browser = stored_event(&conn, "browser-extension", "chrome", 10, PrivacyFlag::Default, &[]);
+    let old_sensitive = stored_event(&conn, "system", "slack", 10, PrivacyFlag::Sensitive, &[]);
+    let recent_sensitive = stored_event(&conn, "system", "slack", 3, PrivacyFlag::Sensitive, &[]);
+    let old_vscode = stored_event(&conn, "vscode", "vscode", 400, PrivacyFlag::Default, &[]);
+    // Kept by the vscode rule, although the sensitive rule would expire it.
+    let old_vscode_sensitive = stored_event(&conn, "vscode", "vscode", 400, PrivacyFlag::Sensitive, &[]);
+    let scratch = stored_event(&conn, "manual", "notes", 2, PrivacyFlag::Default, &["scratch"]);
+
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app: Router = routes::router(app_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+    let url = |path: &str| format!("{}{}", base, path);
+    let send = |req: reqwest::RequestBuilder| req.header("X-Vyaso-Local-Client", "desktop-app").send();
+
+    let mut rule_ids = Vec::new();
+    for body in [
+        json!({ "name": "browser", "source": "browser-extension", "keep_days": 30 }),
+        json!({ "name": "sensitive", "privacy_flag": "sensitive", "keep_days": 7 }),
+        json!({ "name": "vscode", "app": "vscode" }),
+        json!({ "name": "scratch", "tag": "scratch", "keep_days": 1, "enabled": false }),
+    ] {
+        let resp = send(client.post(url("/v1/retention/rules")).json(&body)).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::CREATED);
+        let rule: Value = resp.json().await.unwrap();
+        assert_eq!(rule["name"], body["name"]);
+        rule_ids.push(rule["rule_id"].as_str().unwrap().to_string());
+    }
+    let bad = send(client.post(url("/v1/retention/rules")).json(&json!({ "app": "chrome", "keep_days": 0 }))).await.unwrap();
+    assert_eq!(bad.status(), reqwest::StatusCode::BAD_REQUEST);
+    let listed: Value = send(client.get(url("/v1/retention/rules"))).await.unwrap().json().await.unwrap();
+    assert_eq!(listed["rules"].as_array().unwrap().len(), 4);
+    let vscode_rule: Value = send(client.get(url(&format!("/v1/retention/rules/{}", rule_ids[2])))).await.unwrap().json().await.unwrap();
+    assert_eq!(vscode_rule["keep_days"], Value::Null);
+    assert_eq!(vscode_rule["enabled"], true);
+
+    let report: Value = send(client.post(url("/v1/retention/run"))).await.unwrap().json().await.unwrap();
+    let runs = report["rules"].as_array().unwrap();
+    assert_eq!(runs.len(), 2, "forever and disabled rules are not run: {}", report);
+    for (run, rule_id) in runs.iter().zip(&rule_ids) {
+        assert_eq!(&run["rule_id"], rule_id.as_str());
+        assert_eq!(run["run_id"], report["run_id"]);
+        assert_eq!(run["matched"], 1, "{}", run);
+        assert_eq!(run["summary"]["deleted_events"], 1);
+        assert_eq!(run["error"], Value::Null);
+    }
+    let exists = |id: &str| app_state.db.lock().unwrap()
+        .query_row("SELECT COUNT(*) FROM events WHERE event_id = ?1", [id], |r| r.get::<_, i64>(0)).unwrap() == 1;
+    for gone in [&old_browser, &old_sensitive] { assert!(!exists(gone), "{} should have expired", gone); }
+    for kept in [&recent_browser, &recent_sensitive, &old_vscode, &old_vscode_sensitive, &scratch] {
+        assert!(exists(kept), "{} should have been kept", kept);
+    }
+
+    // Enabling the scratch rule expires the tagged event on the next sweep.
+    let updated: Value = send(client.put(url(&format!("/v1/retention/rules/{}", rule_ids[3])))
+        .json(&json!({ "name": "scratch", "tag": "scratch", "keep_days": 1 }))).await.unwrap().json().await.unwrap();
+    assert_eq!(updated["enabled"], true);
+    let report: Value = send(client.post(url("/v1/retention/run"))).await.unwrap().json().await.unwrap();
+    assert_eq!(report["rules"].as_array().unwrap().len(), 3);
+    assert!(!exists(&scratch));
+
+    let runs: Value = send(client.get(url(&format!("/v1/retention/runs?rule_id={}", rule_ids[3])))).await.unwrap().json().await.unwrap();
+    let runs = runs["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0]["summary"]["deleted_events"], 1);
+    let browser_runs: Value = send(client.get(url(&format!("/v1/retention/runs?rule_id={}", rule_ids[0])))).await.unwrap().json().await.unwrap();
+    let counts: Vec<&Value> = browser_runs["runs"].as_array().unwrap().iter().map(|r| &r["summary"]["deleted_events"]).collect();
+    assert_eq!(counts, [&json!(0), &json!(1)], "newest run first");
+    let page: Value = send(client.get(url("/v1/retention/runs?limit=4"))).await.unwrap().json().await.unwrap();
+    assert_eq!(page["runs"].as_array().unwrap().len(), 4);
+    let rest: Value = send(client.get(url(&format!("/v1/retention/runs?before={}", page["next_before"])))).await.unwrap().json().await.unwrap();
+    assert_eq!(rest["runs"].as_array().unwrap().len(), 1);
+
+    let resp = send(client.delete(url(&format!("/v1/retention/rules/{}", rule_ids[3])))).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let resp = send(client.get(url(&format!("/v1/retention/rules/{}", rule_ids[3])))).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+    let runs: Value = send(client.get(url(&format!("/v1/retention/runs?rule_id={}", rule_ids[3])))).await.unwrap().json().await.unwrap();
+    assert_eq!(runs["runs"].as_array().unwrap().len(), 1, "past runs outlive the rule");
+    let _ = std::fs::remove_file(&db_path);
+}
+
+#[tokio::test]
+async fn retention_sweeper_does_not_outlive_the_state() {
+    let db_path = std::env::temp_dir().join(format!("vyaso_test_retention_{}.db", uuid::Uuid::new_v4().simple()));
+    let conn = db::init_db(&db_path).unwrap();
+    let (tx, mut rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.retention.sweep_interval = std::time::Duration::from_millis(10);
+    let app_state = Arc::new(app_state);
+    let sweeper = vyasoai_daemon::retention::start_sweeper(&app_state);
+    tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+    // Shutdown waits for the queue to close, which needs every sender gone.
+    drop(app_state);
+    assert!(tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv()).await.unwrap().is_none());
+    tokio::time::timeout(std::time::Duration::from_secs(5), sweeper).await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&db_path);
+}
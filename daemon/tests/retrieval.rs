@@ -0,0 +1,166 @@
+#![cfg(test)]
+use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::intel::{IntelConfig, IntelPool};
+use vyasoai_daemon::retrieval::{fuse, RecencyParams, RetrievalConfig};
+use vyasoai_daemon::storage::fts::SearchHit;
+use vyasoai_daemon::{routes, state, storage::{db, fts, hash}};
+
+fn hit(event_id: &str, chunk_id: &str, score: f64, timestamp: &str) -> SearchHit {
+    SearchHit {
+        event_id: event_id.to_string(),
+        chunk_id: Some(chunk_id.to_string()),
+        start_offset: 0,
+        end_offset: 10,
+        score,
+        snippet: String::new(),
+        timestamp: timestamp.to_string(),
+        source: "vscode".to_string(),
+        app: "notes".to_string(),
+        text: String::new(),
+        scores: None,
+    }
+}
+
+const NOW: &str = "2025-08-10T00:00:00Z";
+
+#[test]
+fn rrf_rewards_hits_found_by_both_rankers() {
+    let now = OffsetDateTime::parse(NOW, &time::format_description::well_known::Rfc3339).unwrap();
+    let config = RetrievalConfig { recency_weight: 0.0, ..RetrievalConfig::default() };
+    let lexical = vec![hit("ev-a", "a", 9.0, NOW), hit("ev-b", "b", 4.0, NOW)];
+    let vector = vec![(hit("ev-c", "c", 0.0, NOW), 0.95), (hit("ev-b", "b", 0.0, NOW), 0.9)];
+    let fused = fuse(lexical, vector, &config, now, 10);
+    let order: Vec<&str> = fused.iter().map(|h| h.chunk_id.as_deref().unwrap()).collect();
+    assert_eq!(order, ["b", "a", "c"]);
+
+    let b = fused[0].scores.as_ref().unwrap();
+    assert_eq!((b.bm25, b.bm25_rank, b.vector, b.vector_rank), (Some(4.0), Some(2), Some(0.9), Some(2)));
+    assert!((b.rrf - 2.0 / 62.0).abs() < 1e-12);
+    assert_eq!((b.recency, b.fused, fused[0].score), (1.0, b.rrf, b.rrf));
+    let c = fused[2].scores.as_ref().unwrap();
+    assert_eq!((c.bm25, c.vector_rank), (None, Some(1)));
+
+    assert_eq!(fuse(vec![hit("ev-a", "a", 1.0, NOW)], vec![], &config, now, 0).len(), 0);
+}
+
+#[test]
+fn recency_prior_breaks_ties_towards_newer_memories() {
+    let now = OffsetDateTime::parse(NOW, &time::format_description::well_known::Rfc3339).unwrap();
+    let config = RetrievalConfig { half_life_days: 10.0, recency_weight: 0.5, ..RetrievalConfig::default() };
+    assert_eq!(config.recency(NOW, now), 1.0);
+    assert_eq!(config.recency("2025-09-01T00:00:00Z", now), 1.0);
+    assert!((config.recency("2025-07-31T00:00:00Z", now) - 0.75).abs() < 1e-9);
+    assert_eq!(config.recency("not a time", now), 1.0);
+
+    // Same ranks in opposite lists: only recency separates them.
+    let old = hit("ev-old", "old", 5.0, "2025-01-01T00:00:00Z");
+    let new = hit("ev-new", "new", 0.0, "2025-08-09T00:00:00Z");
+    let fused = fuse(vec![old], vec![(new, 0.5)], &config, now, 10);
+    assert_eq!(fused[0].event_id, "ev-new");
+    assert!(fused[1].scores.as_ref().unwrap().recency < 0.51);
+
+    let strict = config.with(Some(&RecencyParams { half_life_days: Some(1.0), weight: Some(1.0) })).unwrap();
+    assert_eq!((strict.half_life_days, strict.recency_weight, strict.rrf_k), (1.0, 1.0, config.rrf_k));
+    assert!(config.with(Some(&RecencyParams { weight: Some(1.5), ..Default::default() })).is_err());
+    assert!(config.with(Some(&RecencyParams { half_life_days: Some(0.0), ..Default::default() })).is_err());
+    assert_eq!(config.with(None).unwrap(), config);
+}
+
+fn python_pool() -> IntelPool {
+    IntelPool::new(IntelConfig {
+        args: ["-m", "intelligence.cli", "serve", "--index-dir"].iter().map(|s| s.to_string())
+            .chain([std::env::temp_dir().join("vyaso_retrieval_index").to_string_lossy().into_owned()]).collect(),
+        workdir: Some(std::fs::canonicalize("..").unwrap()),
+        workers: 1,
+        request_timeout: Duration::from_secs(30),
+        ..IntelConfig::default()
+    })
+}
+
+fn store(conn: &rusqlite::Connection, app: &str, timestamp: &str, text: &str) -> (String, String) {
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: timestamp.to_string(),
+        source: "vscode".to_string(),
+        app: app.to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(text.as_bytes()),
+        size_bytes: text.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+    db::insert_event(conn, &env).unwrap();
+    let chunk_id = uuid::Uuid::new_v4().simple().to_string();
+    let row = db::ChunkRow { chunk_id: chunk_id.clone(), event_id: env.event_id.clone(), start_offset: 0, end_offset: text.chars().count() as i64, content_type: "prose".into() };
+    fts::index_chunks(conn, &env.event_id, text, &[row]).unwrap();
+    (env.event_id, chunk_id)
+}
+
+#[tokio::test]
+async fn search_and_rag_fuse_lexical_and_vector_hits() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_retrieval.db")).unwrap();
+    // Rows from earlier runs stay in the database; scope every query to this run's app.
+    let app_name = format!("notes-{}", uuid::Uuid::new_v4().simple());
+    let token = format!("heron{}", uuid::Uuid::new_v4().simple());
+    let (lexical_event, _) = store(&conn, &app_name, "2024-01-01T00:00:00Z", &format!("Meeting notes mention {} in passing.", token));
+    let (semantic_event, semantic_chunk) = store(&conn, &app_name, "2025-08-01T00:00:00Z", "The migration plan moved to the spring quarter.");
+    let (other_app_event, other_app_chunk) = store(&conn, "elsewhere", "2025-08-01T00:00:00Z", "Unrelated text.");
+
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.intel = Arc::new(python_pool());
+    app_state.llm = Arc::new(vyasoai_daemon::rag::llm::ExtractiveBackend);
+    let app_state = Arc::new(app_state);
+    // Stand in for a chunk embedding close to the query: the mock model embeds identical text identically.
+    let embedded = app_state.intel.embed(std::slice::from_ref(&token)).await.unwrap();
+    let query_vector: Vec<f32> = embedded["vectors"][0].as_array().unwrap().iter().map(|x| x.as_f64().unwrap() as f32).collect();
+    app_state.vectors.add(&semantic_chunk, &semantic_event, &query_vector, "mock-emb-128").unwrap();
+    app_state.vectors.add(&other_app_chunk, &other_app_event, &query_vector, "mock-emb-128").unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let app = routes::router(app_state.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+    let post = |path: &str, body: Value| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "desktop-app").json(&body).send();
+
+    let v: Value = post("/v1/memories/search", json!({ "query": token, "filters": { "app": app_name } })).await.unwrap().json().await.unwrap();
+    assert_eq!(v["mode"], "hybrid");
+    assert!(v.get("vector_error").is_none(), "{}", v);
+    let hits = v["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 2, "{}", v);
+    let by_event = |id: &str| hits.iter().find(|h| h["event_id"] == id).unwrap().clone();
+    let lexical = by_event(&lexical_event);
+    let semantic = by_event(&semantic_event);
+    assert!(lexical["scores"]["bm25"].as_f64().unwrap() > 0.0);
+    assert!(lexical["scores"]["vector"].is_null());
+    assert!(lexical["scores"]["recency"].as_f64().unwrap() < 1.0);
+    assert!((semantic["scores"]["vector"].as_f64().unwrap() - 1.0).abs() < 1e-5);
+    assert!(semantic["scores"]["bm25"].is_null());
+    assert_eq!(semantic["snippet"], "The migration plan moved to the spring quarter.");
+    // Both rank first in their list; the older memory loses on recency.
+    assert_eq!(hits[0]["event_id"], semantic_event);
+    assert_eq!(hits[0]["score"], hits[0]["scores"]["fused"]);
+
+    let v: Value = post("/v1/memories/search", json!({ "query": token, "filters": { "app": app_name }, "mode": "lexical" })).await.unwrap().json().await.unwrap();
+    assert_eq!(v["hits"].as_array().unwrap().len(), 1);
+    assert_eq!(v["hits"][0]["event_id"], lexical_event);
+    let v: Value = post("/v1/memories/search", json!({ "query": token, "filters": { "app": app_name }, "recency": { "weight": 0.0 } })).await.unwrap().json().await.unwrap();
+    assert!(v["hits"].as_array().unwrap().iter().all(|h| h["scores"]["recency"] == 1.0));
+    let bad = post("/v1/memories/search", json!({ "query": token, "recency": { "half_life_days": -1 } })).await.unwrap();
+    assert_eq!(bad.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let v: Value = post("/v1/rag/query", json!({ "query": token, "filters": { "app": app_name } })).await.unwrap().json().await.unwrap();
+    let prov = v["provenance"].as_array().unwrap();
+    assert_eq!(prov.len(), 2, "{}", v);
+    assert_eq!(prov[0]["docId"], semantic_event);
+    assert_eq!(prov[0]["scores"]["vector_rank"], 1);
+}
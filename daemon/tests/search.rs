@@ -0,0 +1,99 @@
+#![cfg(test)]
+use axum::Router;
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, state, storage::{db, blobs, fts, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag, PurgeRequest};
+
+fn envelope(path: &std::path::Path, body: &[u8], source: &str) -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: source.to_string(),
+        app: "search-app".to_string(),
+        content_pointer: path.to_string_lossy().to_string(),
+        content_hash: hash::compute_sha256(body),
+        size_bytes: body.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+#[tokio::test]
+async fn search_finds_ingested_text_and_purge_removes_it() {
+    let db_path = std::path::PathBuf::from("data/vyaso_test_search.db");
+    let conn = db::init_db(&db_path).unwrap();
+    blobs::ensure_blob_base().unwrap();
+
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
+    vyasoai_daemon::queue::start_worker(rx, app_state.clone());
+    let app: Router = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    // A per-run token keeps hits from earlier runs out of the assertions.
+    let token = format!("quasar{}", uuid::Uuid::new_v4().simple());
+    let body_a = format!("Design review notes mention {} twice: {}", token, token);
+    let body_b = format!("A browser page that mentions {} once", token);
+    let path_a = std::env::temp_dir().join(format!("vyaso_search_{}_a.txt", token));
+    let path_b = std::env::temp_dir().join(format!("vyaso_search_{}_b.txt", token));
+    std::fs::write(&path_a, &body_a).unwrap();
+    std::fs::write(&path_b, &body_b).unwrap();
+    let env_a = envelope(&path_a, body_a.as_bytes(), "vscode");
+    let env_b = envelope(&path_b, body_b.as_bytes(), "browser-extension");
+
+    let client = Client::new();
+    let base = format!("http://{}", addr);
+    for env in [&env_a, &env_b] {
+        let resp = client.post(format!("{}/v1/events", base)).header("X-Vyaso-Local-Client", "vscode").json(env).send().await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let v: serde_json::Value = client.post(format!("{}/v1/memories/search", base)).header("X-Vyaso-Local-Client", "desktop-app")
+        .json(&serde_json::json!({ "query": token })).send().await.unwrap().json().await.unwrap();
+    let hits = v["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0]["event_id"], env_a.event_id);
+    assert!(hits[0]["snippet"].as_str().unwrap().contains(&format!("<mark>{}</mark>", token)));
+    assert_eq!(hits[0]["start_offset"], 0);
+
+    let filtered: serde_json::Value = client.post(format!("{}/v1/memories/search", base)).header("X-Vyaso-Local-Client", "desktop-app")
+        .json(&serde_json::json!({ "query": token, "filters": { "source": "browser-extension" } })).send().await.unwrap().json().await.unwrap();
+    let hits = filtered["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["event_id"], env_b.event_id);
+
+    let req = PurgeRequest { event_ids: Some(vec![env_a.event_id.clone()]), ..Default::default() };
+    let resp = client.post(format!("{}/v1/purge", base)).header("X-Vyaso-Local-Client", "vscode").json(&req).send().await.unwrap();
+    assert!(resp.status().is_success());
+    let after: serde_json::Value = client.post(format!("{}/v1/memories/search", base)).header("X-Vyaso-Local-Client", "desktop-app")
+        .json(&serde_json::json!({ "query": token })).send().await.unwrap().json().await.unwrap();
+    assert_eq!(after["hits"].as_array().unwrap().len(), 1);
+
+    let empty = client.post(format!("{}/v1/memories/search", base)).header("X-Vyaso-Local-Client", "desktop-app")
+        .json(&serde_json::json!({ "query": "  \"  " })).send().await.unwrap();
+    assert_eq!(empty.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn chunk_rows_replace_document_row_with_offsets() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_search_chunks.db")).unwrap();
+    let event_id = uuid::Uuid::new_v4().to_string();
+    let text = "héllo first part. nebula second part.";
+    fts::index_document(&conn, &event_id, text).unwrap();
+    let chunks = vec![
+        db::ChunkRow { chunk_id: "c1".into(), event_id: event_id.clone(), start_offset: 0, end_offset: 17, content_type: "prose".into() },
+        db::ChunkRow { chunk_id: "c2".into(), event_id: event_id.clone(), start_offset: 18, end_offset: 37, content_type: "prose".into() },
+    ];
+    assert_eq!(fts::index_chunks(&conn, &event_id, text, &chunks).unwrap(), 2);
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM memory_fts WHERE event_id = ?1", [&event_id], |r| r.get(0)).unwrap();
+    assert_eq!(rows, 2);
+    let body: String = conn.query_row("SELECT body FROM memory_fts WHERE chunk_id = 'c2' AND event_id = ?1", [&event_id], |r| r.get(0)).unwrap();
+    assert_eq!(body, "nebula second part.");
+}
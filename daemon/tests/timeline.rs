@@ -0,0 +1,90 @@
+#![cfg(test)]
+use axum::Router;
+use reqwest::Client;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+#[tokio::test]
+async fn timeline_paginates_and_filters_with_previews() {
+    let db_path = std::path::PathBuf::from("data/vyaso_test_timeline.db");
+    let conn = db::init_db(&db_path).unwrap();
+    blobs::ensure_blob_base().unwrap();
+
+    // Unique app name keeps this run isolated from rows left by earlier runs.
+    let app_name = format!("timeline-{}", uuid::Uuid::new_v4());
+    for i in 0..5 {
+        let body = format!("Note {}\nsome body text for event {}", i, i);
+        let hash_hex = hash::compute_sha256(body.as_bytes());
+        blobs::save_blob(body.as_bytes(), &hash_hex).unwrap();
+        let env = EventEnvelope {
+            event_id: uuid::Uuid::new_v4().to_string(),
+            timestamp: format!("2025-03-0{}T10:00:00Z", i + 1),
+            source: if i % 2 == 0 { "browser-extension".to_string() } else { "vscode".to_string() },
+            app: app_name.clone(),
+            content_pointer: String::new(),
+            content_hash: hash_hex,
+            size_bytes: body.len() as u64,
+            tags: vec![if i == 4 { "pinned".to_string() } else { "t".to_string() }],
+            privacy_flag: PrivacyFlag::Default,
+        };
+        db::insert_event(&conn, &env).unwrap();
+    }
+
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx.clone(), None));
+    let app: Router = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let client = Client::new();
+    let base = format!("http://{}/v1/timeline", addr);
+    let resp = client.get(&base).query(&[("app", app_name.as_str()), ("limit", "3")])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap();
+    assert!(resp.status().is_success());
+    let page1: serde_json::Value = resp.json().await.unwrap();
+    let items = page1["items"].as_array().unwrap();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0]["title"], "Note 4");
+    assert!(items[0]["snippet"].as_str().unwrap().contains("body text for event 4"));
+    let cursor = page1["next_cursor"].as_str().unwrap().to_string();
+
+    let page2: serde_json::Value = client.get(&base).query(&[("app", app_name.as_str()), ("limit", "3"), ("cursor", cursor.as_str())])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap().json().await.unwrap();
+    let items2 = page2["items"].as_array().unwrap();
+    assert_eq!(items2.len(), 2);
+    assert_eq!(items2[1]["title"], "Note 0");
+    assert!(page2["next_cursor"].is_null());
+
+    let filtered: serde_json::Value = client.get(&base)
+        .query(&[("app", app_name.as_str()), ("source", "vscode"), ("start", "2025-03-03T00:00:00Z")])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap().json().await.unwrap();
+    let titles: Vec<&str> = filtered["items"].as_array().unwrap().iter().map(|i| i["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["Note 3"]);
+
+    let tagged: serde_json::Value = client.get(&base).query(&[("app", app_name.as_str()), ("tag", "pinned")])
+        .header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap().json().await.unwrap();
+    assert_eq!(tagged["items"].as_array().unwrap().len(), 1);
+
+    let bad = client.get(&base).query(&[("cursor", "!!")]).header("X-Vyaso-Local-Client", "desktop-app").send().await.unwrap();
+    assert_eq!(bad.status(), reqwest::StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn previews_decode_only_a_prefix_of_the_blob() {
+    blobs::ensure_blob_base().unwrap();
+    let body = format!("€uro {}\n{}", uuid::Uuid::new_v4(), "x".repeat(1 << 20));
+    let path = blobs::save_blob(body.as_bytes(), &hash::compute_sha256(body.as_bytes())).unwrap();
+    let prefix = |max| blobs::load_text_prefix(&path, max).unwrap().unwrap();
+    assert_eq!(prefix(4), "€u");
+    assert_eq!(prefix(2), "", "a character cut at the limit is dropped");
+    assert_eq!(prefix(16 * 1024).len(), 16 * 1024);
+
+    let binary = [0xffu8, 0xfe, 0x00, 0x01];
+    let path = blobs::save_blob(&binary, &hash::compute_sha256(&binary)).unwrap();
+    assert!(blobs::load_text_prefix(&path, 16).unwrap().is_none());
+}
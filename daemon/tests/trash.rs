@@ -0,0 +1,132 @@
+#![cfg(test)]
+use axum::Router;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::{queue, routes, state, storage::{blobs, db, hash}};
+
+fn envelope(path: &std::path::Path, body: &[u8], app: &str) -> EventEnvelope {
+    EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: "2025-08-05T12:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: app.to_string(),
+        content_pointer: path.to_string_lossy().to_string(),
+        content_hash: hash::compute_sha256(body),
+        size_bytes: body.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+#[tokio::test]
+async fn purged_events_go_to_the_trash_until_restored_or_swept() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_trash.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    queue::start_worker(rx, app_state.clone());
+    let app: Router = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    let client = Client::new();
+    let get = |path: String| client.get(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode").send();
+    let post = |path: &str, body: Value| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "vscode").json(&body).send();
+
+    let token = format!("trashable{}", uuid::Uuid::new_v4().simple());
+    let app_name = format!("trash-{}", uuid::Uuid::new_v4().simple());
+    let (body_a, body_b) = (format!("Notes about {}", token), format!("Other notes {}", uuid::Uuid::new_v4()));
+    let (path_a, path_b) = (std::env::temp_dir().join(format!("{}_a.txt", token)), std::env::temp_dir().join(format!("{}_b.txt", token)));
+    std::fs::write(&path_a, &body_a).unwrap();
+    std::fs::write(&path_b, &body_b).unwrap();
+    let a = envelope(&path_a, body_a.as_bytes(), &app_name);
+    let b = envelope(&path_b, body_b.as_bytes(), &app_name);
+    for env in [&a, &b] {
+        assert_eq!(post("/v1/events", json!(env)).await.unwrap().status(), reqwest::StatusCode::ACCEPTED);
+    }
+    let search = || async {
+        let v: Value = post("/v1/memories/search", json!({ "query": token, "mode": "lexical" })).await.unwrap().json().await.unwrap();
+        v["hits"].as_array().unwrap().len()
+    };
+    for _ in 0..50 {
+        if search().await > 0 { break; }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(search().await, 1);
+    let mem: Value = get(format!("/v1/mem/{}", a.event_id)).await.unwrap().json().await.unwrap();
+    let blob_path = std::path::PathBuf::from(mem["blob"]["path"].as_str().unwrap());
+    assert!(blob_path.exists());
+
+    let resp = post("/v1/purge", json!({ "event_ids": [a.event_id], "background": true })).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST, "trashing has no background mode");
+
+    // A plain purge only trashes: the event disappears from every read API.
+    let resp = post("/v1/purge", json!({ "event_ids": [a.event_id] })).await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    let batch: Value = resp.json().await.unwrap();
+    assert_eq!(batch["trashed_events"], 1, "{}", batch);
+    let trash_id = batch["trash_id"].as_str().unwrap().to_string();
+    assert!(batch["expires_at"].as_str().unwrap() > batch["trashed_at"].as_str().unwrap());
+    for path in [format!("/v1/mem/{}", a.event_id), format!("/v1/mem/{}/content", a.event_id), format!("/v1/mem/{}/status", a.event_id)] {
+        assert_eq!(get(path.clone()).await.unwrap().status(), reqwest::StatusCode::NOT_FOUND, "{}", path);
+    }
+    assert_eq!(search().await, 0);
+    let timeline: Value = get(format!("/v1/timeline?app={}", app_name)).await.unwrap().json().await.unwrap();
+    let ids: Vec<&str> = timeline["items"].as_array().unwrap().iter().map(|i| i["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, [b.event_id.as_str()]);
+    assert!(blob_path.exists(), "trashed content is kept for a restore");
+
+    let listed: Value = get(format!("/v1/trash?trash_id={}", trash_id)).await.unwrap().json().await.unwrap();
+    let items = listed["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["event"]["event_id"], a.event_id.as_str());
+    assert_eq!(items[0]["expires_at"], batch["expires_at"]);
+
+    // Restoring brings it back everywhere, without reprocessing.
+    assert_eq!(post("/v1/trash/restore", json!({})).await.unwrap().status(), reqwest::StatusCode::BAD_REQUEST);
+    let restored: Value = post("/v1/trash/restore", json!({ "trash_id": trash_id })).await.unwrap().json().await.unwrap();
+    assert_eq!(restored["restored"], 1);
+    assert_eq!(restored["event_ids"], json!([a.event_id]));
+    assert_eq!(get(format!("/v1/mem/{}", a.event_id)).await.unwrap().status(), reqwest::StatusCode::OK);
+    assert_eq!(search().await, 1);
+
+    // Once the restore window has passed, the sweeper purges for good.
+    let batch: Value = post("/v1/purge", json!({ "event_ids": [a.event_id] })).await.unwrap().json().await.unwrap();
+    let sweep: Value = post("/v1/trash/sweep", json!({})).await.unwrap().json().await.unwrap();
+    assert_eq!(sweep["deleted_events"], 0, "nothing has expired yet");
+    app_state.db.lock().unwrap()
+        .execute("UPDATE trash SET expires_at = '2000-01-01T00:00:00Z' WHERE trash_id = ?1", [batch["trash_id"].as_str().unwrap()]).unwrap();
+    let sweep: Value = post("/v1/trash/sweep", json!({})).await.unwrap().json().await.unwrap();
+    assert_eq!(sweep["deleted_events"], 1, "{}", sweep);
+    assert_eq!(sweep["deleted_blobs"], 1);
+    assert!(!blob_path.exists());
+    let restored: Value = post("/v1/trash/restore", json!({ "event_ids": [a.event_id] })).await.unwrap().json().await.unwrap();
+    assert_eq!(restored["restored"], 0);
+
+    // skip_trash deletes right away.
+    let purged: Value = post("/v1/purge", json!({ "event_ids": [b.event_id], "skip_trash": true })).await.unwrap().json().await.unwrap();
+    assert_eq!(purged["deleted_events"], 1, "{}", purged);
+    let listed: Value = get(format!("/v1/trash?trash_id={}", trash_id)).await.unwrap().json().await.unwrap();
+    assert!(listed["items"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn trash_sweeper_does_not_outlive_the_state() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_trash_sweeper.db")).unwrap();
+    let (tx, mut rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.trash.sweep_interval = Duration::from_millis(10);
+    let app_state = Arc::new(app_state);
+    let sweeper = vyasoai_daemon::purge::start_trash_sweeper(&app_state);
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    // Shutdown waits for the queue to close, which needs every sender gone.
+    drop(app_state);
+    assert!(tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.unwrap().is_none());
+    tokio::time::timeout(Duration::from_secs(5), sweeper).await.unwrap().unwrap();
+}
@@ -0,0 +1,66 @@
+#![cfg(test)]
+use reqwest::{multipart, Client};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::sync::Arc;
+
+use vyasoai_daemon::{routes, queue, state, storage::{db, blobs, hash}};
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+
+fn form(env: &EventEnvelope, content: &[u8]) -> multipart::Form {
+    multipart::Form::new()
+        .text("envelope", serde_json::to_string(env).unwrap())
+        .part("content", multipart::Part::bytes(content.to_vec()).mime_str("application/octet-stream").unwrap())
+}
+
+#[tokio::test]
+async fn upload_stores_verified_content_without_a_pointer() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_upload.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    queue::start_worker(rx, app_state.clone());
+    let app = routes::router(app_state.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+
+    let content = format!("page text uploaded inline {}", uuid::Uuid::new_v4()).into_bytes();
+    let env = EventEnvelope {
+        event_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: time::OffsetDateTime::now_utc().format(&time::format_description::well_known::Rfc3339).unwrap(),
+        source: "browser-extension".to_string(),
+        app: "chrome".to_string(),
+        // A pointer the daemon cannot read; the upload must not depend on it.
+        content_pointer: "/nonexistent/extension/tab.html".to_string(),
+        content_hash: hash::compute_sha256(&content),
+        size_bytes: content.len() as u64,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    };
+
+    let client = Client::new();
+    let url = format!("http://{}/v1/events:upload", addr);
+    let resp = client.post(&url).header("X-Vyaso-Local-Client", "browser-extension").multipart(form(&env, &content)).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let path = {
+        let conn = app_state.db.lock().unwrap();
+        db::get_event(&conn, &env.event_id).unwrap();
+        db::get_blob_index(&conn, &env.content_hash).unwrap().unwrap().0
+    };
+    assert_eq!(blobs::load_blob(std::path::Path::new(&path)).unwrap(), content);
+
+    let mut tampered = env.clone();
+    tampered.event_id = uuid::Uuid::new_v4().to_string();
+    let resp = client.post(&url).header("X-Vyaso-Local-Client", "browser-extension").multipart(form(&tampered, b"something else")).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+    let missing = multipart::Form::new().text("envelope", serde_json::to_string(&env).unwrap());
+    let resp = client.post(&url).header("X-Vyaso-Local-Client", "browser-extension").multipart(missing).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let resp = client.post(&url).header("X-Vyaso-Local-Client", "browser-extension").json(&env).send().await.unwrap();
+    assert_eq!(resp.status(), reqwest::StatusCode::BAD_REQUEST);
+}
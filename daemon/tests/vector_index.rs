@@ -0,0 +1,273 @@
+#![cfg(test)]
+use rand::Rng;
+use reqwest::{multipart, Client};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use vyasoai_daemon::handlers::{EventEnvelope, PrivacyFlag};
+use vyasoai_daemon::index::VectorIndex;
+use vyasoai_daemon::intel::{IntelConfig, IntelPool};
+use vyasoai_daemon::{queue, routes, state, storage::{blobs, db, hash}};
+
+fn random_vector(dim: usize) -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    (0..dim).map(|_| rng.gen_range(-1.0..1.0)).collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (norm(a) * norm(b))
+}
+
+fn envelope(id: &str) -> EventEnvelope {
+    EventEnvelope {
+        event_id: id.to_string(),
+        timestamp: "2025-08-03T08:00:00Z".to_string(),
+        source: "vscode".to_string(),
+        app: "vscode".to_string(),
+        content_pointer: String::new(),
+        content_hash: hash::compute_sha256(id.as_bytes()),
+        size_bytes: 0,
+        tags: vec![],
+        privacy_flag: PrivacyFlag::Default,
+    }
+}
+
+#[test]
+fn search_recall_matches_brute_force() {
+    let index = VectorIndex::in_memory();
+    let data: Vec<(String, Vec<f32>)> = (0..2000).map(|i| (format!("c{}", i), random_vector(32))).collect();
+    for (i, (chunk, v)) in data.iter().enumerate() {
+        index.add(chunk, &format!("ev{}", i / 4), v, "test-32").unwrap();
+    }
+    assert_eq!(index.len(), 2000);
+    let mut found = 0;
+    for _ in 0..50 {
+        let q = random_vector(32);
+        let mut exact: Vec<(f32, &str)> = data.iter().map(|(c, v)| (cosine(&q, v), c.as_str())).collect();
+        exact.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let truth: HashSet<&str> = exact.iter().take(10).map(|e| e.1).collect();
+        let hits = index.search(&q, 10);
+        assert_eq!(hits.len(), 10);
+        assert!(hits.windows(2).all(|w| w[0].score >= w[1].score));
+        found += hits.iter().filter(|h| truth.contains(h.chunk_id.as_str())).count();
+    }
+    assert!(found as f64 / 500.0 >= 0.95, "recall {}", found as f64 / 500.0);
+}
+
+#[test]
+fn add_remove_and_model_checks() {
+    let index = VectorIndex::in_memory();
+    let a = random_vector(8);
+    index.add("a1", "ev-a", &a, "m1").unwrap();
+    index.add("a2", "ev-a", &random_vector(8), "m1").unwrap();
+    index.add("b1", "ev-b", &random_vector(8), "m1").unwrap();
+    assert_eq!(index.search(&a, 1)[0].chunk_id, "a1");
+
+    // Same chunk id replaces its vector.
+    let moved = random_vector(8);
+    index.add("a1", "ev-a", &moved, "m1").unwrap();
+    assert_eq!(index.len(), 3);
+    assert!((index.search(&moved, 1)[0].score - 1.0).abs() < 1e-5);
+
+    assert!(index.add("c1", "ev-c", &random_vector(4), "m1").is_err());
+    assert!(index.add("c1", "ev-c", &random_vector(8), "m2").is_err());
+
+    assert_eq!(index.remove_events(&["ev-a".to_string()]), 2);
+    assert!(index.search(&moved, 3).iter().all(|h| h.event_id == "ev-b"));
+    assert_eq!(index.remove_chunks(&["b1".to_string(), "nope".to_string()]), 1);
+    assert!(index.is_empty());
+    // Emptied, the index accepts another model.
+    index.add("c1", "ev-c", &random_vector(4), "m2").unwrap();
+    assert_eq!(index.status().model_version.as_deref(), Some("m2"));
+    assert_eq!(index.status().dimension, Some(4));
+}
+
+#[test]
+fn persists_encrypted_and_reloads() {
+    let dir = std::path::PathBuf::from(format!("data/test_index_{}", uuid::Uuid::new_v4().simple()));
+    let index = VectorIndex::open(&dir).unwrap();
+    assert!(!index.flush().unwrap(), "nothing to write yet");
+    let marker = format!("chunk-{}", uuid::Uuid::new_v4().simple());
+    let q = random_vector(16);
+    index.add(&marker, "ev-1", &q, "test-16").unwrap();
+    for i in 0..200 {
+        index.add(&format!("c{}", i), &format!("ev-{}", i % 7), &random_vector(16), "test-16").unwrap();
+    }
+    index.remove_chunks(&["c0".to_string()]);
+    assert!(index.status().dirty);
+    assert!(index.flush().unwrap());
+    assert!(!index.flush().unwrap());
+
+    let file = std::fs::read(dir.join("vectors.idx")).unwrap();
+    assert!(!file.windows(marker.len()).any(|w| w == marker.as_bytes()), "chunk ids must not be stored in clear");
+
+    let reloaded = VectorIndex::open(&dir).unwrap();
+    assert_eq!(reloaded.len(), 200);
+    let status = reloaded.status();
+    assert_eq!((status.dimension, status.model_version.as_deref(), status.dirty), (Some(16), Some("test-16"), false));
+    assert_eq!(reloaded.search(&q, 1)[0].chunk_id, marker);
+    assert!(reloaded.search(&q, 200).iter().all(|h| h.chunk_id != "c0"));
+    reloaded.add("late", "ev-9", &random_vector(16), "test-16").unwrap();
+    assert!(reloaded.flush().unwrap());
+    assert_eq!(VectorIndex::open(&dir).unwrap().len(), 201);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+async fn serve(app_state: Arc<state::AppState>) -> String {
+    let app = routes::router(app_state);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap(); });
+    base
+}
+
+#[tokio::test]
+async fn similarity_endpoints_and_purge() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_vectors.db")).unwrap();
+    let ids: Vec<String> = (0..3).map(|_| uuid::Uuid::new_v4().to_string()).collect();
+    for id in &ids { db::insert_event(&conn, &envelope(id)).unwrap(); }
+    let (tx, _rx) = mpsc::channel::<state::IngestJob>(16);
+    let app_state = Arc::new(state::AppState::new(conn, tx, None));
+    let base_vec = random_vector(8);
+    let near: Vec<f32> = base_vec.iter().map(|x| x + 0.01).collect();
+    let far: Vec<f32> = base_vec.iter().map(|x| -x).collect();
+    app_state.vectors.add("a", &ids[0], &base_vec, "test-8").unwrap();
+    app_state.vectors.add("b", &ids[1], &near, "test-8").unwrap();
+    app_state.vectors.add("c", &ids[2], &far, "test-8").unwrap();
+    let base = serve(app_state.clone()).await;
+    let client = Client::new();
+    let get = |path: String| client.get(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "desktop-app").send();
+
+    let status: Value = get("/v1/index/status".to_string()).await.unwrap().json().await.unwrap();
+    assert_eq!(status["vectors"], 3);
+    assert_eq!(status["model_version"], "test-8");
+
+    let v: Value = get(format!("/v1/mem/{}/similar?limit=1", ids[0])).await.unwrap().json().await.unwrap();
+    assert_eq!(v["similar"].as_array().unwrap().len(), 1);
+    assert_eq!(v["similar"][0]["event_id"], ids[1]);
+    assert_eq!(v["similar"][0]["chunk_id"], "b");
+    assert_eq!(get(format!("/v1/mem/{}/similar", uuid::Uuid::new_v4())).await.unwrap().status(), reqwest::StatusCode::NOT_FOUND);
+
+    let post = |path: &str, body: Value| client.post(format!("{}{}", base, path)).header("X-Vyaso-Local-Client", "desktop-app").json(&body).send();
+    let v: Value = post("/v1/index/search", json!({ "vector": far, "limit": 2 })).await.unwrap().json().await.unwrap();
+    assert_eq!(v["hits"][0]["event_id"], ids[2]);
+    assert_eq!(v["hits"].as_array().unwrap().len(), 2);
+    let bad = post("/v1/index/search", json!({ "vector": [1.0, 2.0] })).await.unwrap();
+    assert_eq!(bad.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let v: Value = post("/v1/purge", json!({ "event_ids": [ids[1]], "skip_trash": true })).await.unwrap().json().await.unwrap();
+    assert_eq!(v["deleted_events"], 1);
+    assert_eq!(v["deleted_vectors"], 1);
+    assert!(app_state.vectors.vectors_for_event(&ids[1]).is_empty());
+    assert_eq!(app_state.vectors.len(), 2);
+}
+
+#[tokio::test]
+async fn python_embeddings_reach_the_index() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_vectors_pipeline.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let index_dir = std::env::temp_dir().join(format!("vyaso_vectors_{}", uuid::Uuid::new_v4().simple()));
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.intel = Arc::new(IntelPool::new(IntelConfig {
+        args: ["-m", "intelligence.cli", "serve", "--index-dir"].iter().map(|s| s.to_string())
+            .chain([index_dir.to_string_lossy().into_owned()]).collect(),
+        workdir: Some(std::fs::canonicalize("..").unwrap()),
+        workers: 1,
+        request_timeout: Duration::from_secs(30),
+        ..IntelConfig::default()
+    }));
+    let app_state = Arc::new(app_state);
+    queue::start_worker(rx, app_state.clone());
+    let base = serve(app_state.clone()).await;
+    let client = Client::new();
+
+    let upload = |privacy_flag: PrivacyFlag| {
+        let client = client.clone();
+        let base = base.clone();
+        async move {
+            let content = format!("Notes on {}.\n\nWe agreed to ship the beta next week.", uuid::Uuid::new_v4());
+            let env = EventEnvelope {
+                content_hash: hash::compute_sha256(content.as_bytes()),
+                size_bytes: content.len() as u64,
+                privacy_flag,
+                ..envelope(&uuid::Uuid::new_v4().to_string())
+            };
+            let form = multipart::Form::new()
+                .text("envelope", serde_json::to_string(&env).unwrap())
+                .part("content", multipart::Part::bytes(content.into_bytes()));
+            let resp = client.post(format!("{}/v1/events:upload", base)).header("X-Vyaso-Local-Client", "vscode").multipart(form).send().await.unwrap();
+            assert_eq!(resp.status(), reqwest::StatusCode::ACCEPTED);
+
+            let mut status = Value::Null;
+            for _ in 0..200 {
+                status = client.get(format!("{}/v1/mem/{}/status", base, env.event_id)).header("X-Vyaso-Local-Client", "vscode")
+                    .send().await.unwrap().json().await.unwrap();
+                if status["state"] == "indexed" || status["state"] == "failed" { break; }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            assert_eq!(status["state"], "indexed", "{}", status);
+            env.event_id
+        }
+    };
+    let chunk_count = |id: &str| app_state.db.lock().unwrap()
+        .query_row("SELECT COUNT(*) FROM chunks WHERE event_id = ?1", [id], |r| r.get::<_, i64>(0)).unwrap();
+
+    let id = upload(PrivacyFlag::Default).await;
+    let vectors = app_state.vectors.vectors_for_event(&id);
+    assert!(!vectors.is_empty());
+    assert_eq!(vectors.len() as i64, chunk_count(&id));
+    assert_eq!(app_state.vectors.status().model_version.as_deref(), Some("mock-emb-128"));
+    assert_eq!(app_state.vectors.search(&vectors[0].1, 1)[0].event_id, id);
+
+    // Sensitive content never reaches the workers, so it is never embedded.
+    let sensitive = upload(PrivacyFlag::Sensitive).await;
+    assert!(app_state.vectors.vectors_for_event(&sensitive).is_empty());
+    assert_eq!(chunk_count(&sensitive), 0);
+    let stages: Value = client.get(format!("{}/v1/mem/{}/stages", base, sensitive)).header("X-Vyaso-Local-Client", "vscode")
+        .send().await.unwrap().json().await.unwrap();
+    let intel = stages["stages"].as_array().unwrap().iter().find(|r| r["stage"] == "intel").unwrap();
+    assert_eq!(intel["status"], "skipped");
+    let _ = std::fs::remove_dir_all(&index_dir);
+}
+
+#[tokio::test]
+async fn shutdown_drains_the_queue_and_writes_the_index() {
+    let conn = db::init_db(std::path::Path::new("data/vyaso_test_vectors_shutdown.db")).unwrap();
+    blobs::ensure_blob_base().unwrap();
+    let index_dir = std::env::temp_dir().join(format!("vyaso_vectors_{}", uuid::Uuid::new_v4().simple()));
+    let (tx, rx) = mpsc::channel::<state::IngestJob>(16);
+    let mut app_state = state::AppState::new(conn, tx, None);
+    app_state.vectors = Arc::new(VectorIndex::open(&index_dir).unwrap());
+    let mut pipeline = vyasoai_daemon::pipeline::Pipeline::with_builtin_stages();
+    pipeline.configure("extract_text,chunk,embed,index_chunks");
+    app_state.pipeline = Arc::new(pipeline);
+    let app_state = Arc::new(app_state);
+    let vectors = app_state.vectors.clone();
+    let worker = queue::start_worker(rx, app_state.clone());
+
+    let content = format!("Shutdown notes {}.\n\nQueued right before the daemon stops.", uuid::Uuid::new_v4());
+    let env = EventEnvelope {
+        content_hash: hash::compute_sha256(content.as_bytes()),
+        size_bytes: content.len() as u64,
+        ..envelope(&uuid::Uuid::new_v4().to_string())
+    };
+    let job = state::IngestJob { envelope: env.clone(), content: Some(content.into_bytes()), journal_seq: None };
+    app_state.queue_tx.send(job).await.unwrap();
+
+    // The state, and with it a sender, is still alive: shutdown must not wait for it.
+    assert!(worker.shutdown(Duration::from_secs(10)).await);
+    assert!(app_state.queue_tx.send(envelope("late").into()).await.is_err(), "the queue is closed");
+    assert!(!vectors.vectors_for_event(&env.event_id).is_empty(), "queued events are processed before exiting");
+    vyasoai_daemon::index::flush_vector_index(&vectors);
+    let reopened = VectorIndex::open(&index_dir).unwrap();
+    assert!(index_dir.join("vectors.idx").exists());
+    assert_eq!(reopened.vectors_for_event(&env.event_id).len(), vectors.vectors_for_event(&env.event_id).len());
+    let _ = std::fs::remove_dir_all(&index_dir);
+}
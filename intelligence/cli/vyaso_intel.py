@@ -4,7 +4,7 @@ import os
 import sys
 import time
 from pathlib import Path
-from typing import Any, Dict, List, Tuple
+from typing import Any, BinaryIO, Dict, List, Optional, Tuple
 
 from intelligence.chunking.chunker import chunk_text
 from intelligence.embeddings.mock import MockEmbeddingAdapter
@@ -83,7 +83,33 @@ def _select_adapter(name: str) -> Tuple[Any, int, str]:
     return adapter, 128, "mock-emb-128"
 
 
-def process_job(job: Dict[str, Any], backend: str, log_path: Path) -> Dict[str, Any]:
+class Session:
+    """Embedding adapter, index and id map kept alive across `serve` requests.
+
+    Only a `persist` session saves its index after each job. `serve` workers
+    don't: the daemon keeps the index, and several workers would overwrite
+    each other's saves.
+    """
+
+    def __init__(self, backend: str, index_dir: Optional[Path] = None, intel_dir: Optional[Path] = None, persist: bool = True):
+        self.adapter, self.dim, self.model_ver = _select_adapter(backend)
+        self.index = VectorIndex(dim=self.dim)
+        self.idmap = IdMap()
+        self.persist = persist
+        self.index_dir = index_dir or (_repo_root() / "data/intel/index")
+        # Holds the in/, out/ and logs/ files of `process` CLI jobs.
+        self.intel_dir = intel_dir or (_repo_root() / "data/intel")
+
+
+def _log(log_path: Optional[Path], message: str) -> None:
+    if log_path is None:
+        sys.stderr.write(message)
+        sys.stderr.flush()
+    else:
+        log_path.write_text(message)
+
+
+def process_job(job: Dict[str, Any], backend: str, log_path: Optional[Path], session: Optional[Session] = None) -> Dict[str, Any]:
     start = time.time()
     event_id = job.get("event_id")
     blob_path = job.get("blob_path")
@@ -99,16 +125,17 @@ def process_job(job: Dict[str, Any], backend: str, log_path: Path) -> Dict[str,
         timings["load"] = int((time.time() - t0) * 1000)
         text = content.decode("utf-8", errors="replace")
     except Exception as e:
-        log_path.write_text(f"load_error: {e}\n")
+        _log(log_path, f"load_error: {e}\n")
         return {"status": "error", "errors": [{"code": "LOAD_FAIL", "message": str(e)}]}
 
     t1 = time.time()
     chunks = chunk_text(text, content_type, event_id=event_id)
     timings["chunk"] = int((time.time() - t1) * 1000)
 
-    adapter, dim, model_ver = _select_adapter(backend)
-    index = VectorIndex(dim=dim)
-    idmap = IdMap()
+    if session is None:
+        session = Session(backend)
+    adapter, dim, model_ver = session.adapter, session.dim, session.model_ver
+    index, idmap = session.index, session.idmap
     ids = [idmap.get_int(c.chunk_id) for c in chunks]
 
     t2 = time.time()
@@ -117,18 +144,20 @@ def process_job(job: Dict[str, Any], backend: str, log_path: Path) -> Dict[str,
 
     t3 = time.time()
     index.add(ids, vectors)
-    index_dir = _repo_root() / "data/intel/index"
-    index.save(str(index_dir), encrypt=True, model_version=model_ver)
+    if session.persist:
+        index.save(str(session.index_dir), encrypt=True, model_version=model_ver)
     timings["index"] = int((time.time() - t3) * 1000)
 
+    # Vectors go back to the daemon, which keeps its own index across events.
     chunk_items = [
         {
             "id": c.chunk_id,
             "start": c.start_offset,
             "end": c.end_offset,
             "type": c.content_type,
+            "vector": [float(x) for x in v],
         }
-        for c in chunks
+        for c, v in zip(chunks, vectors)
     ]
     prov_map = [{"chunk_id": c.chunk_id, "offset": c.start_offset} for c in chunks]
     out = {
@@ -140,16 +169,131 @@ def process_job(job: Dict[str, Any], backend: str, log_path: Path) -> Dict[str,
             "source": job.get("source"),
             "mapping": prov_map,
         },
-        "index": {"backend": "hnsw" if hasattr(index, "_use_hnsw") and index._use_hnsw else "store", "dimensions": dim, "added": len(chunks)},
+        "index": {"backend": "hnsw" if hasattr(index, "_use_hnsw") and index._use_hnsw else "store", "dimensions": dim, "model_version": model_ver, "added": len(chunks)},
         "timings_ms": timings,
         "errors": [],
     }
     return out
 
 
+def purge(session: Session, event_ids: List[str], chunk_ids: List[str], job_ids: List[str]) -> Dict[str, int]:
+    """Forgets purged events: their vectors and their job files.
+
+    The saved index is rewritten from the session's own index, which also
+    drops vectors left by earlier runs; their chunk ids aren't known. A
+    session that doesn't persist its index has nothing to rewrite it with,
+    so it empties the saved one.
+    """
+    ids = [session.idmap.pop(c) for c in chunk_ids]
+    removed = session.index.purge([i for i in ids if i is not None])
+    saved = (session.index_dir / "index.meta.json").exists()
+    if session.persist and (removed or saved):
+        session.index.save(str(session.index_dir), encrypt=True, model_version=session.model_ver)
+    elif saved:
+        VectorIndex(dim=session.dim).save(str(session.index_dir), encrypt=True, model_version=session.model_ver)
+
+    events, jobs = set(event_ids), set(job_ids)
+    # Jobs run through the CLI are only known by the event named in their files.
+    for sub in ("in", "out"):
+        for path in (session.intel_dir / sub).glob("*.json"):
+            try:
+                if json.loads(path.read_text(encoding="utf-8")).get("event_id") in events:
+                    jobs.add(path.stem)
+            except (OSError, ValueError, AttributeError):
+                continue
+    files = 0
+    for job in jobs:
+        if not job or "/" in job or "\\" in job or job.startswith("."):
+            continue
+        for rel in (f"in/{job}.json", f"out/{job}.json", f"logs/{job}.log"):
+            try:
+                (session.intel_dir / rel).unlink()
+                files += 1
+            except FileNotFoundError:
+                pass
+    return {"vectors": removed, "files": files}
+
+
+def read_frame(stream: BinaryIO) -> Optional[Dict[str, Any]]:
+    """Reads one `Content-Length`-framed JSON message; None at end of stream."""
+    length = None
+    while True:
+        line = stream.readline()
+        if not line:
+            return None
+        line = line.strip()
+        if not line:
+            break
+        name, _, value = line.decode("ascii").partition(":")
+        if name.strip().lower() == "content-length":
+            length = int(value.strip())
+    if length is None:
+        raise ValueError("frame without Content-Length")
+    body = stream.read(length)
+    if len(body) < length:
+        return None
+    return json.loads(body.decode("utf-8"))
+
+
+def write_frame(stream: BinaryIO, message: Dict[str, Any]) -> None:
+    body = json.dumps(message, ensure_ascii=False).encode("utf-8")
+    stream.write(f"Content-Length: {len(body)}\r\n\r\n".encode("ascii"))
+    stream.write(body)
+    stream.flush()
+
+
+def handle_request(session: Session, backend: str, request: Dict[str, Any]) -> Dict[str, Any]:
+    """Dispatches one JSON-RPC 2.0 request: `ping`, `process`, `embed` or `purge`."""
+    rid = request.get("id")
+    method = request.get("method")
+    params = request.get("params") or {}
+    if method == "ping":
+        return {"jsonrpc": "2.0", "id": rid, "result": {"status": "ok", "pid": os.getpid()}}
+    if method == "process":
+        try:
+            job_backend = (params.get("params") or {}).get("backend", backend)
+            job_session = session if job_backend == backend else Session(job_backend, session.index_dir, session.intel_dir, session.persist)
+            result = process_job(params, job_backend, None, job_session)
+            return {"jsonrpc": "2.0", "id": rid, "result": {"job_id": params.get("job_id"), **result}}
+        except Exception as e:
+            return {"jsonrpc": "2.0", "id": rid, "error": {"code": -32000, "message": f"process_error: {e}"}}
+    if method == "embed":
+        texts = params.get("texts")
+        if not isinstance(texts, list) or not all(isinstance(t, str) for t in texts):
+            return {"jsonrpc": "2.0", "id": rid, "error": {"code": -32602, "message": "texts must be a list of strings"}}
+        vectors = [[float(x) for x in session.adapter.embed(t)] for t in texts]
+        return {"jsonrpc": "2.0", "id": rid, "result": {"model_version": session.model_ver, "dimensions": session.dim, "vectors": vectors}}
+    if method == "purge":
+        lists = [params.get(k) or [] for k in ("event_ids", "chunk_ids", "job_ids")]
+        if not all(isinstance(l, list) and all(isinstance(x, str) for x in l) for l in lists):
+            return {"jsonrpc": "2.0", "id": rid, "error": {"code": -32602, "message": "event_ids, chunk_ids and job_ids must be lists of strings"}}
+        try:
+            return {"jsonrpc": "2.0", "id": rid, "result": purge(session, *lists)}
+        except Exception as e:
+            return {"jsonrpc": "2.0", "id": rid, "error": {"code": -32000, "message": f"purge_error: {e}"}}
+    return {"jsonrpc": "2.0", "id": rid, "error": {"code": -32601, "message": f"unknown method: {method}"}}
+
+
+def serve(backend: str, stdin: BinaryIO, stdout: BinaryIO, index_dir: Optional[Path] = None) -> None:
+    """Answers framed JSON-RPC requests until stdin closes."""
+    session = Session(backend, index_dir, persist=False)
+    while True:
+        try:
+            request = read_frame(stdin)
+        except ValueError as e:
+            write_frame(stdout, {"jsonrpc": "2.0", "id": None, "error": {"code": -32700, "message": str(e)}})
+            continue
+        if request is None:
+            return
+        write_frame(stdout, handle_request(session, backend, request))
+
+
 def main():
     parser = argparse.ArgumentParser(prog="vyaso_intel")
     sub = parser.add_subparsers(dest="cmd")
+    sv = sub.add_parser("serve")
+    sv.add_argument("--backend", choices=["mock", "llama"], default="mock")
+    sv.add_argument("--index-dir")
     p = sub.add_parser("process")
     p.add_argument("event_id")
     p.add_argument("--job", required=True)
@@ -163,6 +307,14 @@ def main():
     kr.add_argument("--force-env", action="store_true")
     args = parser.parse_args()
 
+    if args.cmd == "serve":
+        _ensure_dirs()
+        # stdout carries frames only; anything a library prints goes to stderr.
+        out = sys.stdout.buffer
+        sys.stdout = sys.stderr
+        serve(args.backend, sys.stdin.buffer, out, Path(args.index_dir) if args.index_dir else None)
+        sys.exit(0)
+
     if args.cmd == "key" and args.key_cmd == "rotate":
         _ensure_dirs()
         index_dir = Path(args.index_dir)
@@ -1,4 +1,4 @@
-from typing import Dict
+from typing import Dict, Optional
 
 class IdMap:
     def __init__(self):
@@ -16,4 +16,10 @@ class IdMap:
         return v
 
     def get_str(self, int_id: int) -> str:
-        return self._to_str[int_id]
\ No newline at end of file
+        return self._to_str[int_id]
+
+    def pop(self, chunk_id: str) -> Optional[int]:
+        v = self._to_int.pop(chunk_id, None)
+        if v is not None:
+            del self._to_str[v]
+        return v
@@ -52,6 +52,28 @@ class VectorIndex:
             for i in ids:
                 self._store.pop(i, None)
 
+    def purge(self, ids: List[int]) -> int:
+        """Drops `ids` for good and returns how many were present.
+
+        Unlike `remove`, which only marks hnswlib items deleted, the graph is
+        rebuilt so the vectors are not written out again by `save`.
+        """
+        if not self._use_hnsw:
+            return sum(1 for i in ids if self._store.pop(i, None) is not None)
+        drop = set(ids)
+        labels = list(self._index.get_ids_list())
+        present = [i for i in labels if i in drop]
+        if not present:
+            return 0
+        keep = [i for i in labels if i not in drop]
+        vectors = self._index.get_items(keep) if keep else []
+        self._index = self._hnswlib.Index(space=self.space, dim=self.dim)
+        self._index.init_index(max_elements=max(100000, len(keep)), ef_construction=200, M=16)
+        self._index.set_ef(64)
+        if keep:
+            self._index.add_items(vectors, keep)
+        return len(present)
+
     def save(self, path: str, encrypt: bool = True, model_version: str = "mock-emb-128"):
         os.makedirs(path, exist_ok=True)
         index_path = os.path.join(path, "index.hnsw")
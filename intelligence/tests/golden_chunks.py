@@ -0,0 +1,96 @@
+"""Golden chunking fixtures shared with the daemon's native chunker.
+
+Run `python3 -m intelligence.tests.golden_chunks` from the repo root to rewrite
+`daemon/tests/fixtures/chunking_golden.json` after changing a chunker; the Rust
+port (`daemon/src/chunking`) must then produce the same chunks.
+"""
+import json
+from pathlib import Path
+
+from intelligence.chunking import chunk_text
+
+FIXTURE = Path(__file__).resolve().parents[2] / "daemon/tests/fixtures/chunking_golden.json"
+
+_SENTENCES = [
+    "The daemon stores every captured event locally.",
+    "Chunks keep provenance offsets into the original text!",
+    "Why would anyone index the same paragraph twice?",
+    "Ranking blends keyword and vector scores… then decays by age.",
+    "नमस्ते दुनिया, यह एक परीक्षण वाक्य है।",
+    "Emoji 🚀 and accents like café or naïve count as text.",
+]
+
+
+def _long_prose() -> str:
+    paras = []
+    for p in range(4):
+        paras.append(" ".join(_SENTENCES[(p + i) % len(_SENTENCES)] for i in range(40)))
+    return "\n\n\n".join(paras)
+
+
+def _long_python() -> str:
+    body = "".join(f"    value_{i} = compute({i}, 'x' * {i})  # step {i}\n" for i in range(150))
+    return f"import os\n\n\ndef huge():\n{body}    return value_0\n\n\ndef tail():\n    return 'é'\n"
+
+
+CASES = [
+    ("empty_prose", "prose", ""),
+    ("short_prose", "text", "One sentence. Two sentences!  Three?\tFour.\n\nA new paragraph... ends here.\n"),
+    ("long_prose", "prose", _long_prose()),
+    ("unicode_prose", "plain", "Ünïcödé tëxt. 日本語の文です。 Second sentence here.\n\n\n" + "🚀 " * 20),
+    ("unknown_type", "application/x-thing", "Falls back to prose. Really."),
+    ("markdown", "markdown", "# H1\n\nText before.\n\n```python\nprint('x')\n```\n\nText after.\n\n```\nplain fence\n```\n\n- item one\n- item two\n"),
+    ("markdown_repeated", "md", "Same block.\n\n```\nSame block.\n```\n\nSame block.\n\nTail **bold** text."),
+    ("html", "html", "<html><body><h1>Title</h1><p>Para one. Still para one.</p><script>var x = '<p>no</p>';</script>"
+                     "<style>p { color: red; }</style><pre>code</pre><ul><li>First</li><li>Second <b>bold</b></li></ul>"
+                     "<!-- <p>commented</p> --><p class=\"a > b\">Quoted attr</p><p>Tom &amp; Jerry</p>"
+                     "<p>x &lt y</p><P>Upper</P><p>Outer <code>inner</code> tail</p><br/><p>a < b</p></body></html>"),
+    ("python", "python", "def a():\n    return 1\n\nclass B:\n    def m(self):\n        return 2\n"),
+    ("python_rich", "py", "import asyncio\n\n\n@decorator(\n    arg=1,\n)\ndef decorated(x,\n              y):\n    \"\"\"Doc\n    string.\"\"\"\n"
+                          "    if x:\n        return [\n            y,\n        ]\n    # trailing comment\n\n    return x  # done\n\n\n"
+                          "async def fetch():\n    await asyncio.sleep(0)\n\n\nclass Outer(Base):\n    value = 'a:b'\n\n"
+                          "    class Inner:\n        def deep(self): return '''multi\nline'''\n\n    def method(self, s=\"\\\"\"):\n"
+                          "        x = 1 + \\\n            2\n        return x\n\n\ndef one_liner(): return 1\nlambda_ = lambda: 0\ndefault = 2\n"),
+    ("python_tabs", "python", "class T:\n\tdef m(self):\n\t\treturn 1\n\n\tdef n(self):\n\t\tpass\n"),
+    ("python_long", "python", _long_python()),
+    ("python_syntax_error", "python", "def broken(:\n    return (\n"),
+    ("python_bad_indent", "python", "def f():\nreturn 1\n"),
+    ("python_no_defs", "python", "x = 1\nprint(x)\n"),
+    ("js", "js", "function a(){return 1;}\nclass X{}\nconst f = () => 2;"),
+    ("ts", "typescript", "import x from 'y';\n\nexport class Service {\n  run(): void {}\n}\n\nexport function helper(a: number) {\n"
+                         "  return a;\n}\n\nconst handler = (req, res) => {\n  res.send('ok');\n};\nsubclass Foo { }\n"),
+    ("js_long", "javascript", "function big() {\n" + "  doSomething();\n" * 400 + "}\nfunction small() {}\n"),
+    ("js_none", "js", "console.log('no definitions');"),
+    ("go", "go", "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tfmt.Println(\"hi\")\n}\n\nfunc (s *S) Method() int {\n\treturn 1\n}\n\nvar funcs = 1\nfunc\tTabbed() {}\n"),
+    ("rust", "rust", "use std::fmt;\n\npub struct Point { x: i32 }\n\nimpl fmt::Display for Point {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n"
+                     "        write!(f, \"{}\", self.x)\n    }\n}\n\nfn main() { let fnord = 1; let implied = fnord; }\n"),
+    ("rust_long", "rs", "fn big() {\n" + "    let s = \"ünïcödé padding\";\n" * 200 + "}\n"),
+]
+
+
+def build():
+    cases = []
+    for name, content_type, content in CASES:
+        event_id = f"golden-{name}"
+        chunks = chunk_text(content, content_type, event_id=event_id)
+        cases.append({
+            "name": name,
+            "content_type": content_type,
+            "event_id": event_id,
+            "content": content,
+            "chunks": [
+                {"chunk_id": c.chunk_id, "start": c.start_offset, "end": c.end_offset, "type": c.content_type, "text": c.text}
+                for c in chunks
+            ],
+        })
+    return {"cases": cases}
+
+
+def main():
+    FIXTURE.parent.mkdir(parents=True, exist_ok=True)
+    FIXTURE.write_text(json.dumps(build(), ensure_ascii=False, indent=1) + "\n", encoding="utf-8")
+    print(f"wrote {FIXTURE}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,8 @@
+import json
+
+from intelligence.tests.golden_chunks import FIXTURE, build
+
+
+def test_golden_fixture_is_current():
+    # Regenerate with `python3 -m intelligence.tests.golden_chunks` when a chunker changes.
+    assert json.loads(FIXTURE.read_text(encoding="utf-8")) == build()
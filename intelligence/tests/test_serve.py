@@ -0,0 +1,119 @@
+import io
+import json
+import os
+import tempfile
+from pathlib import Path
+
+from intelligence.cli.vyaso_intel import Session, handle_request, read_frame, serve, write_frame
+from intelligence.index.vector_index import VectorIndex
+
+
+def _frames(*messages):
+    buf = io.BytesIO()
+    for m in messages:
+        write_frame(buf, m)
+    buf.seek(0)
+    return buf
+
+
+def _responses(out):
+    out.seek(0)
+    responses = []
+    while True:
+        r = read_frame(out)
+        if r is None:
+            return responses
+        responses.append(r)
+
+
+def test_serve_answers_ping_and_process_in_order():
+    d = tempfile.mkdtemp()
+    blob = os.path.join(d, "note.txt")
+    with open(blob, "w", encoding="utf-8") as f:
+        f.write("First paragraph about the launch.\n\nSecond paragraph about the roadmap.")
+    job = {"job_id": "j1", "event_id": "ev-1", "blob_path": blob, "content_type": "prose", "source": "vscode", "params": {"backend": "mock"}}
+    stdin = _frames(
+        {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+        {"jsonrpc": "2.0", "id": 2, "method": "process", "params": job},
+        {"jsonrpc": "2.0", "id": 3, "method": "nope"},
+    )
+    out = io.BytesIO()
+    serve("mock", stdin, out, index_dir=Path(d) / "index")
+    ping, processed, unknown = _responses(out)
+    assert ping["id"] == 1 and ping["result"]["status"] == "ok"
+    assert processed["id"] == 2
+    assert processed["result"]["status"] == "ok"
+    assert processed["result"]["event_id"] == "ev-1"
+    assert processed["result"]["chunks"]
+    assert len(processed["result"]["chunks"][0]["vector"]) == processed["result"]["index"]["dimensions"]
+    assert processed["result"]["index"]["model_version"] == "mock-emb-128"
+    assert unknown["error"]["code"] == -32601
+    assert not (Path(d) / "index").exists(), "the daemon keeps the index; serve workers don't save one"
+
+
+def test_serve_reports_load_failures_as_results():
+    stdin = _frames({"jsonrpc": "2.0", "id": 7, "method": "process", "params": {"event_id": "ev-2", "blob_path": "/nonexistent/blob"}})
+    out = io.BytesIO()
+    serve("mock", stdin, out, index_dir=Path(tempfile.mkdtemp()))
+    (resp,) = _responses(out)
+    assert resp["result"]["status"] == "error"
+    assert resp["result"]["errors"][0]["code"] == "LOAD_FAIL"
+
+
+def test_serve_embeds_texts_like_process():
+    stdin = _frames(
+        {"jsonrpc": "2.0", "id": 1, "method": "embed", "params": {"texts": ["launch plan", "roadmap"]}},
+        {"jsonrpc": "2.0", "id": 2, "method": "embed", "params": {"texts": "launch plan"}},
+    )
+    out = io.BytesIO()
+    serve("mock", stdin, out, index_dir=Path(tempfile.mkdtemp()))
+    embedded, bad = _responses(out)
+    result = embedded["result"]
+    assert result["model_version"] == "mock-emb-128"
+    assert len(result["vectors"]) == 2 and len(result["vectors"][0]) == result["dimensions"] == 128
+    assert result["vectors"][0] != result["vectors"][1]
+    assert bad["error"]["code"] == -32602
+
+
+def test_purge_drops_vectors_and_job_files():
+    d = Path(tempfile.mkdtemp())
+    session = Session("mock", index_dir=d / "index", intel_dir=d)
+    blob = d / "note.txt"
+    blob.write_text("A note to forget.\n\nAnother paragraph to forget.", encoding="utf-8")
+    job = {"job_id": "j1", "event_id": "ev-1", "blob_path": str(blob), "content_type": "prose"}
+    processed = handle_request(session, "mock", {"jsonrpc": "2.0", "id": 1, "method": "process", "params": job})["result"]
+    assert session.index.count() == len(processed["chunk_ids"]) > 0
+    # Files a `process` CLI run leaves behind, one for another event.
+    for sub in ("in", "out", "logs"):
+        (d / sub).mkdir()
+    (d / "in/cli-job.json").write_text(json.dumps({"event_id": "ev-1"}), encoding="utf-8")
+    (d / "out/cli-job.json").write_text(json.dumps({"event_id": "ev-1", "status": "ok"}), encoding="utf-8")
+    (d / "logs/cli-job.log").write_text("ok\n", encoding="utf-8")
+    (d / "in/other.json").write_text(json.dumps({"event_id": "ev-2"}), encoding="utf-8")
+
+    params = {"event_ids": ["ev-1"], "chunk_ids": processed["chunk_ids"], "job_ids": ["j1", "../escape"]}
+    result = handle_request(session, "mock", {"jsonrpc": "2.0", "id": 2, "method": "purge", "params": params})["result"]
+    assert result == {"vectors": len(processed["chunk_ids"]), "files": 3}
+    assert session.index.count() == 0
+    assert [p.name for p in (d / "in").iterdir()] == ["other.json"]
+    assert not list((d / "out").iterdir())
+    assert not (d / "logs/cli-job.log").exists()
+    again = handle_request(session, "mock", {"jsonrpc": "2.0", "id": 3, "method": "purge", "params": params})["result"]
+    assert again == {"vectors": 0, "files": 0}
+    bad = handle_request(session, "mock", {"jsonrpc": "2.0", "id": 4, "method": "purge", "params": {"event_ids": "ev-1"}})
+    assert bad["error"]["code"] == -32602
+
+
+def test_purge_from_a_serve_worker_empties_the_saved_index():
+    d = Path(tempfile.mkdtemp())
+    blob = d / "note.txt"
+    blob.write_text("A note a CLI run saved.", encoding="utf-8")
+    cli = Session("mock", index_dir=d / "index", intel_dir=d)
+    handle_request(cli, "mock", {"jsonrpc": "2.0", "id": 1, "method": "process", "params": {"event_id": "ev-1", "blob_path": str(blob)}})
+    assert VectorIndex.load(str(d / "index"), decrypt=True).count() > 0
+
+    worker = Session("mock", index_dir=d / "index", intel_dir=d, persist=False)
+    params = {"event_ids": ["ev-1"], "chunk_ids": [], "job_ids": []}
+    result = handle_request(worker, "mock", {"jsonrpc": "2.0", "id": 2, "method": "purge", "params": params})["result"]
+    assert result == {"vectors": 0, "files": 0}
+    assert VectorIndex.load(str(d / "index"), decrypt=True).count() == 0
@@ -13,6 +13,9 @@ struct Memory {
   source: String,
 }
 
+#[derive(Deserialize)]
+struct TimelinePage { items: Vec<Memory> }
+
 #[derive(Serialize, Deserialize, Clone)]
 struct Provenance {
   docId: String,
@@ -27,12 +30,13 @@ async fn get_recent_memories() -> Result<Vec<Memory>, String> {
   let client = reqwest::Client::new();
   let res = client
     .get(format!("{}/v1/timeline", url))
+    .header("X-Vyaso-Local-Client", "desktop-app")
     .send()
     .await
     .map_err(|e| e.to_string())?;
   if res.status().is_success() {
-    let v = res.json::<Vec<Memory>>().await.map_err(|e| e.to_string())?;
-    Ok(v)
+    let page = res.json::<TimelinePage>().await.map_err(|e| e.to_string())?;
+    Ok(page.items)
   } else {
     Err(format!("status {}", res.status()))
   }
@@ -49,6 +53,7 @@ async fn search_memories(query: String, filters: Option<SearchFilters>) -> Resul
   if let Some(f) = filters { payload["filters"] = serde_json::to_value(&f).map_err(|e| e.to_string())?; }
   let res = client
     .post(format!("{}/v1/memories/search", url))
+    .header("X-Vyaso-Local-Client", "desktop-app")
     .json(&payload)
     .send()
     .await
@@ -63,6 +68,7 @@ async fn rag_query(query: String) -> Result<serde_json::Value, String> {
   let client = reqwest::Client::new();
   let res = client
     .post(format!("{}/v1/rag/query", url))
+    .header("X-Vyaso-Local-Client", "desktop-app")
     .json(&serde_json::json!({ "query": query }))
     .send()
     .await
@@ -126,13 +132,13 @@ fn main() {
             let tray = handle.tray_handle();
             if to_pause {
               tauri::async_runtime::spawn(async move {
-                let _ = reqwest::Client::new().post(format!("{}/v1/pause", url)).send().await;
+                let _ = reqwest::Client::new().post(format!("{}/v1/pause", url)).header("X-Vyaso-Local-Client", "desktop-app").send().await;
               });
               *paused = true;
               let _ = tray.get_item("toggle_capture").set_title("Resume Capture");
             } else {
               tauri::async_runtime::spawn(async move {
-                let _ = reqwest::Client::new().post(format!("{}/v1/resume", url)).send().await;
+                let _ = reqwest::Client::new().post(format!("{}/v1/resume", url)).header("X-Vyaso-Local-Client", "desktop-app").send().await;
               });
               *paused = false;
               let _ = tray.get_item("toggle_capture").set_title("Pause Capture");